@@ -0,0 +1,201 @@
+use anyhow::Result;
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, StatusCode},
+    routing::post,
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    net::SocketAddr,
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::{net::TcpListener, time};
+
+use crate::daemon::{self, Event, Monitor, RepoState};
+use crate::notifications::EventKind;
+use crate::secrets;
+use crate::{log_console, Config, ConsoleMessage, PolicyAlert, RepoConfig, RepoDefaults, RepoStatus};
+
+const REPORT_PATH: &str = "/api/agent";
+
+// Agents report at least this often, even when nothing changed
+const HEARTBEAT: Duration = Duration::from_secs(15);
+
+// A host that hasn't reported for this long is shown as offline
+pub const OFFLINE_AFTER: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AgentReport {
+    pub host: String,
+    pub repos: Vec<RepoState>,
+    pub messages: Vec<Event>,
+}
+
+// Name the agent reports under when --host isn't given
+pub fn local_hostname() -> String {
+    std::env::var("HOSTNAME")
+        .ok()
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .or_else(|| std::env::var("COMPUTERNAME").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "agent".to_string())
+}
+
+// Monitor this machine's repositories and push their state to the central instance
+pub async fn run(config: Config, central: String, host: String, token: Option<String>) -> Result<()> {
+    let monitor = Monitor::start(config);
+    let states = Mutex::new(Vec::new());
+    let url = format!("{}{}", central.trim_end_matches('/'), REPORT_PATH);
+    eprintln!("Reporting to {} as \"{}\"", url, host);
+
+    let mut pending: Vec<Event> = Vec::new();
+    let mut last_sent: Option<Instant> = None;
+    let mut failing = false;
+    let mut ticker = time::interval(Duration::from_secs(1));
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {}
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+        }
+
+        let events = daemon::collect_events(&monitor, &states);
+        let changed = events.iter().any(|event| matches!(event, Event::Status { .. }));
+        pending.extend(events.into_iter().filter(|event| !matches!(event, Event::Status { .. })));
+        if !changed && pending.is_empty() && last_sent.is_some_and(|sent| sent.elapsed() < HEARTBEAT) {
+            continue;
+        }
+
+        let report = AgentReport {
+            host: host.clone(),
+            repos: states.lock().unwrap().clone(),
+            messages: pending.clone(),
+        };
+        let (url, token) = (url.clone(), token.clone());
+        let result = tokio::task::spawn_blocking(move || post_report(&url, token.as_deref(), &report)).await?;
+        last_sent = Some(Instant::now());
+        match result {
+            Ok(()) => {
+                pending.clear();
+                if failing {
+                    eprintln!("Reporting to {} again", central);
+                    failing = false;
+                }
+            }
+            Err(err) => {
+                if !failing {
+                    eprintln!("Could not report to {}: {}", central, err);
+                    failing = true;
+                }
+                // Keep the newest messages for when the central instance is back
+                let len = pending.len();
+                if len > 100 {
+                    pending.drain(0..len - 100);
+                }
+            }
+        }
+    }
+}
+
+fn post_report(url: &str, token: Option<&str>, report: &AgentReport) -> Result<()> {
+    let mut request = ureq::post(url).set("User-Agent", "gitop");
+    if let Some(token) = token {
+        request = request.set("Authorization", &format!("Bearer {}", token));
+    }
+    match request.send_json(report) {
+        Ok(_) => Ok(()),
+        Err(ureq::Error::Status(code, _)) => anyhow::bail!("central instance returned {}", code),
+        Err(err) => Err(err.into()),
+    }
+}
+
+#[derive(Clone)]
+struct Central {
+    repos: Arc<Mutex<Vec<RepoStatus>>>,
+    console_messages: Arc<Mutex<Vec<ConsoleMessage>>>,
+    token: Option<String>,
+}
+
+// Accept agent reports into the TUI's repository list. `token` is an "env:" or "keyring:" reference.
+pub async fn listen(
+    addr: SocketAddr,
+    token: Option<String>,
+    repos: Arc<Mutex<Vec<RepoStatus>>>,
+    console_messages: Arc<Mutex<Vec<ConsoleMessage>>>,
+) -> Result<()> {
+    let token = token.map(|reference| secrets::resolve(&reference)).transpose()?;
+    if token.is_none() && !addr.ip().is_loopback() {
+        anyhow::bail!("agent_listen {} accepts reports from other machines, set agent_token or listen on 127.0.0.1", addr);
+    }
+    let listener = TcpListener::bind(addr).await?;
+    let central = Central { repos, console_messages, token };
+    let router = Router::new().route(REPORT_PATH, post(report_handler)).with_state(central);
+    tokio::spawn(async move { axum::serve(listener, router).await });
+    Ok(())
+}
+
+async fn report_handler(State(central): State<Central>, headers: HeaderMap, Json(report): Json<AgentReport>) -> StatusCode {
+    if let Some(token) = &central.token {
+        let authorization = headers.get(header::AUTHORIZATION).map_or(&[][..], |value| value.as_bytes());
+        if !secrets::constant_time_eq(authorization, format!("Bearer {}", token).as_bytes()) {
+            return StatusCode::UNAUTHORIZED;
+        }
+    }
+    apply_report(&central.repos, &central.console_messages, report);
+    StatusCode::NO_CONTENT
+}
+
+fn apply_report(repos: &Mutex<Vec<RepoStatus>>, console_messages: &Arc<Mutex<Vec<ConsoleMessage>>>, report: AgentReport) {
+    let mut repos = repos.lock().unwrap();
+    let host = report.host;
+
+    // Repositories the agent no longer monitors disappear with its next report
+    repos.retain(|repo| {
+        repo.host.as_deref() != Some(host.as_str()) || report.repos.iter().any(|state| Path::new(&state.path) == repo.path)
+    });
+
+    let now = Instant::now();
+    for state in report.repos {
+        let index = repos
+            .iter()
+            .position(|repo| repo.host.as_deref() == Some(host.as_str()) && repo.path == Path::new(&state.path));
+        let repo = match index {
+            Some(index) => &mut repos[index],
+            None => {
+                let repo_config = RepoConfig {
+                    name: state.repo.clone(),
+                    path: state.path.clone(),
+                    ..Default::default()
+                };
+                let mut repo = RepoStatus::from_config(repo_config, &RepoDefaults::default());
+                repo.host = Some(host.clone());
+                repos.push(repo);
+                repos.last_mut().unwrap()
+            }
+        };
+        repo.current_branch = state.branch;
+        repo.ahead = state.ahead;
+        repo.behind = state.behind;
+        repo.dirty = state.dirty;
+        repo.operation = state.operation;
+        repo.labels = state.labels;
+        repo.last_update = now;
+    }
+    drop(repos);
+
+    for event in report.messages {
+        match event {
+            Event::Message { repo, author, kind, message, .. } => {
+                log_console(console_messages, &format!("{}:{}", host, repo), &author, kind, message);
+            }
+            Event::Policy { timestamp, repo, kind, remote, branch, commit, author, summary } => {
+                let alert = PolicyAlert { timestamp, kind, remote, branch, commit, author, summary };
+                log_console(console_messages, &format!("{}:{}", host, repo), "Policy", EventKind::Policy, alert.describe());
+            }
+            Event::Status { .. } => {}
+        }
+    }
+}
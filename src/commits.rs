@@ -0,0 +1,128 @@
+use git2::{Oid, Repository};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::cache_dir;
+
+// Past this many commits or walks the cache starts over; history is immutable, so nothing is ever stale, only big
+const MAX_COMMITS: usize = 20_000;
+const MAX_WALKS: usize = 256;
+
+// Shared by every clone of a RepoStatus, so background jobs fill the same cache the views read
+pub type CommitCache = Arc<Mutex<CommitStore>>;
+
+// What the commit lists show of a commit
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedCommit {
+    pub author: String,
+    pub summary: String,
+    pub time: i64,
+    pub parents: usize,
+}
+
+// Commit metadata and revwalk results of one repository, keyed by object ids. Both only depend on the ids they
+// are keyed by, so they stay valid whatever happens to the refs.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct CommitStore {
+    commits: HashMap<String, CachedCommit>,
+    walks: HashMap<String, Vec<String>>, // "tip..hidden:limit" to the commits in revwalk order
+    #[serde(skip)]
+    file: Option<PathBuf>, // set when the cache is kept on disk
+    #[serde(skip)]
+    dirty: bool,
+}
+
+impl CommitStore {
+    // With `persist`, starts from what an earlier run saved for this repository
+    pub fn open(repo_path: &Path, persist: bool) -> CommitCache {
+        let file = persist.then(|| cache_file(repo_path)).flatten();
+        let mut store = file
+            .as_ref()
+            .and_then(|file| std::fs::read(file).ok())
+            .and_then(|data| serde_json::from_slice::<CommitStore>(&data).ok())
+            .unwrap_or_default();
+        store.file = file;
+        Arc::new(Mutex::new(store))
+    }
+
+    // Commits reachable from `tip` but not from `hidden`, newest first, at most `limit` of them
+    pub fn walk(&mut self, repo: &Repository, tip: Oid, hidden: Option<Oid>, limit: usize) -> Vec<Oid> {
+        let key = format!("{}..{}:{}", tip, hidden.map(|oid| oid.to_string()).unwrap_or_default(), limit);
+        if let Some(oids) = self.walks.get(&key) {
+            return oids.iter().filter_map(|oid| Oid::from_str(oid).ok()).collect();
+        }
+
+        let Ok(mut revwalk) = repo.revwalk() else {
+            return Vec::new();
+        };
+        if revwalk.push(tip).is_err() {
+            return Vec::new();
+        }
+        // A force-push can make the hidden commit unreachable; then everything reachable is walked
+        if let Some(hidden) = hidden {
+            let _ = revwalk.hide(hidden);
+        }
+        let oids: Vec<Oid> = revwalk.flatten().take(limit).collect();
+
+        if self.walks.len() >= MAX_WALKS {
+            self.walks.clear();
+        }
+        self.walks.insert(key, oids.iter().map(Oid::to_string).collect());
+        self.dirty = true;
+        oids
+    }
+
+    pub fn commit(&mut self, repo: &Repository, oid: Oid) -> Option<CachedCommit> {
+        let key = oid.to_string();
+        if let Some(commit) = self.commits.get(&key) {
+            return Some(commit.clone());
+        }
+
+        let commit = repo.find_commit(oid).ok()?;
+        let cached = CachedCommit {
+            author: commit.author().name().unwrap_or("Unknown").to_string(),
+            summary: commit.summary().unwrap_or("").to_string(),
+            time: commit.time().seconds(),
+            parents: commit.parent_count(),
+        };
+        if self.commits.len() >= MAX_COMMITS {
+            self.commits.clear();
+        }
+        self.commits.insert(key, cached.clone());
+        self.dirty = true;
+        Some(cached)
+    }
+
+    // Writes what was added since the last save, if the cache is kept on disk; a failed write only costs the
+    // next run some revwalks
+    pub fn save(&mut self) {
+        let Some(file) = self.file.as_ref().filter(|_| self.dirty) else {
+            return;
+        };
+        if let Some(parent) = file.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        if let Ok(data) = serde_json::to_vec(self) {
+            let temporary = file.with_extension("tmp");
+            if std::fs::write(&temporary, data).is_ok() {
+                let _ = std::fs::rename(&temporary, file);
+            }
+        }
+        self.dirty = false;
+    }
+}
+
+// Where persisted caches go with persist_commit_cache
+pub fn cache_root() -> Option<PathBuf> {
+    Some(cache_dir()?.join("commits"))
+}
+
+// One file per repository, named after a hash of its path
+fn cache_file(repo_path: &Path) -> Option<PathBuf> {
+    let path = repo_path.canonicalize().unwrap_or_else(|_| repo_path.to_path_buf());
+    let digest = Sha256::digest(path.to_string_lossy().as_bytes());
+    Some(cache_root()?.join(format!("{}.json", &hex::encode(digest)[..16])))
+}
@@ -0,0 +1,337 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::Notify;
+
+use crate::daemon::RepoState;
+use crate::notifications::EventKind;
+use crate::{log_console, ConsoleMessage, RepoStatus};
+
+// Hooks git runs after local changes; each one asks a running gitop to refresh the repository
+const HOOKS: &[&str] = &["post-commit", "post-merge", "post-checkout"];
+
+// Marks hooks gitop wrote, so they can be replaced but other hooks are left alone
+const HOOK_MARKER: &str = "# Installed by gitop install-hooks";
+
+// The protocol is one line per request and one line back, "ok ..." or "error ..."
+#[cfg(not(windows))]
+pub fn socket_path() -> Option<PathBuf> {
+    match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) => Some(PathBuf::from(dir).join("gitop.sock")),
+        None => Some(crate::cache_dir()?.join("control.sock")),
+    }
+}
+
+// Windows has no Unix sockets in tokio, so the same protocol runs over a named pipe, one per user
+#[cfg(windows)]
+pub fn socket_path() -> Option<PathBuf> {
+    let user = std::env::var("USERNAME").unwrap_or_default();
+    Some(PathBuf::from(format!(r"\\.\pipe\gitop-{}", user)))
+}
+
+// One repository in the answer to "status"
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ControlStatus {
+    #[serde(flatten)]
+    pub state: RepoState,
+    pub paused: bool,
+}
+
+#[derive(Clone)]
+struct Control {
+    repos: Arc<Mutex<Vec<RepoStatus>>>,
+    console_messages: Arc<Mutex<Vec<ConsoleMessage>>>,
+    wake: Arc<Notify>,
+}
+
+// Serve the control socket for `gitop ctl` and the hooks from `gitop install-hooks`
+#[cfg(unix)]
+pub fn listen(repos: Arc<Mutex<Vec<RepoStatus>>>, console_messages: Arc<Mutex<Vec<ConsoleMessage>>>, wake: Arc<Notify>) -> Result<PathBuf> {
+    let path = socket_path().ok_or_else(|| anyhow::anyhow!("no runtime or home directory for the control socket"))?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    // A socket nobody answers on is left over from a gitop that didn't exit cleanly
+    if path.exists() {
+        if std::os::unix::net::UnixStream::connect(&path).is_ok() {
+            anyhow::bail!("another gitop is listening on {}", path.display());
+        }
+        std::fs::remove_file(&path)?;
+    }
+    let listener = tokio::net::UnixListener::bind(&path)?;
+
+    let control = Control { repos, console_messages, wake };
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(control.clone().serve(stream));
+        }
+    });
+    Ok(path)
+}
+
+#[cfg(windows)]
+pub fn listen(repos: Arc<Mutex<Vec<RepoStatus>>>, console_messages: Arc<Mutex<Vec<ConsoleMessage>>>, wake: Arc<Notify>) -> Result<PathBuf> {
+    use tokio::net::windows::named_pipe::ServerOptions;
+
+    let path = socket_path().ok_or_else(|| anyhow::anyhow!("no name for the control pipe"))?;
+    // Only the first instance of a pipe name can be created this way, so a second gitop is refused here
+    let mut server = ServerOptions::new()
+        .first_pipe_instance(true)
+        .create(&path)
+        .map_err(|err| anyhow::anyhow!("another gitop is listening on {} ({})", path.display(), err))?;
+
+    let control = Control { repos, console_messages, wake };
+    let name = path.clone();
+    tokio::spawn(async move {
+        while server.connect().await.is_ok() {
+            let stream = server;
+            // The next instance exists before this client is answered, so others never find the pipe missing
+            server = match ServerOptions::new().create(&name) {
+                Ok(next) => next,
+                Err(_) => break,
+            };
+            tokio::spawn(control.clone().serve(stream));
+        }
+    });
+    Ok(path)
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn listen(_repos: Arc<Mutex<Vec<RepoStatus>>>, _console_messages: Arc<Mutex<Vec<ConsoleMessage>>>, _wake: Arc<Notify>) -> Result<PathBuf> {
+    anyhow::bail!("the control socket needs a Unix or Windows system")
+}
+
+impl Control {
+    async fn serve<S: AsyncRead + AsyncWrite>(self, stream: S) {
+        let (reader, mut writer) = tokio::io::split(stream);
+        let mut request = String::new();
+        if BufReader::new(reader).read_line(&mut request).await.is_ok() {
+            let response = self.handle(request.trim());
+            let _ = writer.write_all(format!("{}\n", response).as_bytes()).await;
+        }
+    }
+
+    fn handle(&self, request: &str) -> String {
+        let (command, argument) = request.split_once(' ').unwrap_or((request, ""));
+        match command {
+            "refresh" => self.refresh(argument.trim()),
+            "pause" => self.set_paused(argument.trim(), true),
+            "resume" => self.set_paused(argument.trim(), false),
+            "status" => self.status(),
+            _ => format!("error unknown command \"{}\"", command),
+        }
+    }
+
+    // By configured name, or the repository containing a path
+    fn refresh(&self, target: &str) -> String {
+        let mut repos = self.repos.lock().unwrap();
+        let Some(index) = find_repo(&repos, target) else {
+            return format!("error no monitored repository \"{}\"", target);
+        };
+        let repo = &mut repos[index];
+        if repo.paused {
+            return format!("error {} is paused, resume it first", repo.name);
+        }
+        repo.next_check = Instant::now();
+        if !repo.muted {
+            log_console(&self.console_messages, &repo.name, "GitOp", EventKind::Action, "Refresh requested".to_string());
+        }
+        let response = format!("ok refreshing {}", repo.name);
+        drop(repos);
+        self.wake.notify_one();
+        response
+    }
+
+    // One repository, or every local one when no target is given
+    fn set_paused(&self, target: &str, paused: bool) -> String {
+        let mut repos = self.repos.lock().unwrap();
+        let indices: Vec<usize> = if target.is_empty() {
+            (0..repos.len()).filter(|&index| repos[index].host.is_none()).collect()
+        } else {
+            match find_repo(&repos, target) {
+                Some(index) => vec![index],
+                None => return format!("error no monitored repository \"{}\"", target),
+            }
+        };
+        let (message, verb) = if paused { ("Monitoring paused", "paused") } else { ("Monitoring resumed", "resumed") };
+        let name = match indices.as_slice() {
+            [index] if !target.is_empty() => repos[*index].name.clone(),
+            _ => String::new(),
+        };
+        let mut changed = 0;
+        for index in indices {
+            let repo = &mut repos[index];
+            if repo.paused == paused {
+                continue;
+            }
+            repo.paused = paused;
+            // Resumed repositories are checked right away rather than a full interval later
+            if !paused {
+                repo.next_check = Instant::now();
+            }
+            if !repo.muted {
+                log_console(&self.console_messages, &repo.name, "GitOp", EventKind::Action, message.to_string());
+            }
+            changed += 1;
+        }
+        drop(repos);
+        if !paused {
+            self.wake.notify_one();
+        }
+        match (target.is_empty(), changed) {
+            (false, 0) => format!("ok {} was already {}", name, verb),
+            (false, _) => format!("ok {} {}", verb, name),
+            (true, _) => format!("ok {} {} repositories", verb, changed),
+        }
+    }
+
+    fn status(&self) -> String {
+        let repos = self.repos.lock().unwrap();
+        let statuses: Vec<ControlStatus> = repos
+            .iter()
+            .filter(|repo| repo.host.is_none())
+            .map(|repo| ControlStatus { state: RepoState::from_status(repo), paused: repo.paused })
+            .collect();
+        match serde_json::to_string(&statuses) {
+            Ok(json) => format!("ok {}", json),
+            Err(err) => format!("error {}", err),
+        }
+    }
+}
+
+// By configured name, or the repository containing a path; repositories reported by agents aren't ours to touch
+pub fn find_repo(repos: &[RepoStatus], target: &str) -> Option<usize> {
+    if let Some(index) = repos.iter().position(|repo| repo.host.is_none() && repo.name == target) {
+        return Some(index);
+    }
+    let target = Path::new(target).canonicalize().ok()?;
+    repos
+        .iter()
+        .enumerate()
+        .filter(|(_, repo)| repo.host.is_none())
+        .filter_map(|(index, repo)| Some((index, repo.path.canonicalize().ok()?)))
+        .filter(|(_, path)| target.starts_with(path))
+        .max_by_key(|(_, path)| path.as_os_str().len())
+        .map(|(index, _)| index)
+}
+
+// Paths are resolved here, the running gitop may have a different working directory. Anything that isn't an
+// existing path is passed on as a repository name.
+pub fn target(repo: &str) -> String {
+    match std::path::absolute(repo) {
+        Ok(path) if path.exists() => path.display().to_string(),
+        _ => repo.to_string(),
+    }
+}
+
+// Print the answer to "status", as it came or one line per repository
+pub fn print_status(answer: &str, json: bool) -> Result<()> {
+    if json {
+        println!("{}", answer);
+        return Ok(());
+    }
+    let statuses: Vec<ControlStatus> = serde_json::from_str(answer)?;
+    for status in statuses {
+        let state = &status.state;
+        let mut line = format!("{} on {}: {} ahead, {} behind", state.repo, state.branch, state.ahead, state.behind);
+        if state.dirty > 0 {
+            line.push_str(&format!(", {} changed files", state.dirty));
+        }
+        if let Some(operation) = &state.operation {
+            line.push_str(&format!(" ({})", operation));
+        }
+        if status.paused {
+            line.push_str(" (paused)");
+        }
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+// Send one request to a running gitop and return its answer
+#[cfg(unix)]
+pub fn send(request: &str) -> Result<String> {
+    let path = socket_path().ok_or_else(|| anyhow::anyhow!("no runtime or home directory for the control socket"))?;
+    let stream = std::os::unix::net::UnixStream::connect(&path)
+        .map_err(|err| anyhow::anyhow!("gitop is not running ({}: {})", path.display(), err))?;
+    stream.set_read_timeout(Some(std::time::Duration::from_secs(5)))?;
+    exchange(stream, request)
+}
+
+// A named pipe opens like a file
+#[cfg(windows)]
+pub fn send(request: &str) -> Result<String> {
+    let path = socket_path().ok_or_else(|| anyhow::anyhow!("no name for the control pipe"))?;
+    let stream = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&path)
+        .map_err(|err| anyhow::anyhow!("gitop is not running ({}: {})", path.display(), err))?;
+    exchange(stream, request)
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn send(_request: &str) -> Result<String> {
+    anyhow::bail!("the control socket needs a Unix or Windows system")
+}
+
+#[cfg(any(unix, windows))]
+fn exchange(mut stream: impl std::io::Read + std::io::Write, request: &str) -> Result<String> {
+    use std::io::{BufRead, BufReader};
+
+    stream.write_all(format!("{}\n", request).as_bytes())?;
+    let mut response = String::new();
+    BufReader::new(stream).read_line(&mut response)?;
+    let response = response.trim_end();
+    match response.split_once(' ').unwrap_or((response, "")) {
+        ("ok", message) => Ok(message.to_string()),
+        ("error", message) => anyhow::bail!("{}", message),
+        _ => anyhow::bail!("unexpected answer from gitop: {}", response),
+    }
+}
+
+// Write the hooks into the repository's hooks directory (core.hooksPath if set). Returns what was done per hook;
+// hooks that exist and weren't written by gitop are skipped.
+pub fn install_hooks(path: &Path) -> Result<Vec<String>> {
+    let repo = git2::Repository::open(path)?;
+    let hooks_dir = match repo.config()?.get_path("core.hooksPath") {
+        Ok(dir) if dir.is_relative() => repo.workdir().unwrap_or(repo.path()).join(dir),
+        Ok(dir) => dir,
+        // Linked worktrees share the hooks of the main repository
+        Err(_) => match std::fs::read_to_string(repo.path().join("commondir")) {
+            Ok(common) => repo.path().join(common.trim()).join("hooks"),
+            Err(_) => repo.path().join("hooks"),
+        },
+    };
+    std::fs::create_dir_all(&hooks_dir)?;
+
+    // The absolute path keeps the hooks working when gitop isn't on git's PATH
+    let gitop = std::env::current_exe()?;
+    // Git for Windows runs hooks with its own sh, which takes C:/... but reads backslashes as escapes
+    let gitop = if cfg!(windows) { gitop.display().to_string().replace('\\', "/") } else { gitop.display().to_string() };
+    let script = format!("#!/bin/sh\n{}\n\"{}\" ctl refresh \"$PWD\" >/dev/null 2>&1 &\nexit 0\n", HOOK_MARKER, gitop);
+
+    let mut results = Vec::new();
+    for hook in HOOKS {
+        let hook_path = hooks_dir.join(hook);
+        let existing = std::fs::read_to_string(&hook_path).ok();
+        if existing.as_ref().is_some_and(|content| !content.contains(HOOK_MARKER)) {
+            results.push(format!("skipped {}: a hook is already there", hook_path.display()));
+            continue;
+        }
+        std::fs::write(&hook_path, &script)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&hook_path, std::fs::Permissions::from_mode(0o755))?;
+        }
+        let verb = if existing.is_some() { "updated" } else { "installed" };
+        results.push(format!("{} {}", verb, hook_path.display()));
+    }
+    Ok(results)
+}
@@ -0,0 +1,343 @@
+use anyhow::Result;
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        State,
+    },
+    response::{Html, IntoResponse},
+    routing::get,
+    Json, Router,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::VecDeque,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::{
+    net::TcpListener,
+    sync::{broadcast, watch, Notify},
+    time,
+};
+
+use crate::notifications::{EventKind, Notifier};
+use crate::{
+    control, log_console, monitor_repositories, secrets, webhook, Config, ConsoleMessage, PolicyAlert, PolicyKind, RefreshPolicy, RepoDefaults,
+    RepoStatus,
+};
+
+// Repository state as published to JSONL and WebSocket consumers
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RepoState {
+    pub repo: String,
+    pub path: String,
+    pub branch: String,
+    pub ahead: usize,
+    pub behind: usize,
+    pub dirty: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub operation: Option<String>,
+    pub labels: Vec<String>,
+}
+
+impl RepoState {
+    pub fn from_status(status: &RepoStatus) -> Self {
+        RepoState {
+            repo: status.name.clone(),
+            path: status.path.display().to_string(),
+            branch: status.current_branch.clone(),
+            ahead: status.ahead,
+            behind: status.behind,
+            dirty: status.dirty,
+            operation: status.operation.clone(),
+            labels: status.labels.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    Status {
+        timestamp: DateTime<Utc>,
+        #[serde(flatten)]
+        state: RepoState,
+    },
+    Message {
+        timestamp: DateTime<Utc>,
+        repo: String,
+        author: String,
+        kind: EventKind,
+        message: String,
+    },
+    // A commit on a protected branch broke the repository's policy
+    Policy {
+        timestamp: DateTime<Utc>,
+        repo: String,
+        kind: PolicyKind,
+        remote: String,
+        branch: String,
+        commit: String,
+        author: String,
+        summary: String,
+    },
+}
+
+// Events kept for the dashboard's console and history panes
+const HISTORY_LEN: usize = 500;
+
+const DASHBOARD_HTML: &str = include_str!("dashboard.html");
+
+// Shared with every HTTP and WebSocket connection
+#[derive(Clone)]
+struct Feed {
+    events: broadcast::Sender<Event>,
+    states: Arc<Mutex<Vec<RepoState>>>,
+    history: Arc<Mutex<VecDeque<Event>>>,
+}
+
+#[derive(Serialize)]
+struct Snapshot {
+    repos: Vec<RepoState>,
+    history: Vec<Event>,
+}
+
+// The background monitor with the state it writes to, for running without the TUI
+pub struct Monitor {
+    pub repos: Arc<Mutex<Vec<RepoStatus>>>,
+    pub console_messages: Arc<Mutex<Vec<ConsoleMessage>>>,
+    pub wake: Arc<Notify>, // checks repositories that are due without waiting for the next one
+    _refresh: watch::Sender<RefreshPolicy>, // the monitor stops rescheduling if this is dropped
+}
+
+impl Monitor {
+    pub fn start(config: Config) -> Self {
+        let refresh = watch::Sender::new(RefreshPolicy::from_config(&config));
+        let repo_defaults = RepoDefaults::from_config(&config);
+        let repos: Vec<RepoStatus> = config
+            .repositories
+            .into_iter()
+            .map(|repo_config| RepoStatus::from_config(repo_config, &repo_defaults))
+            .collect();
+        let repos = Arc::new(Mutex::new(repos));
+        let console_messages = Arc::new(Mutex::new(Vec::new()));
+
+        let count = repos.lock().unwrap().len();
+        log_console(&console_messages, "System", "GitOp", EventKind::Status, format!("Started monitoring {} repositories", count));
+        let wake = Arc::new(Notify::new());
+        tokio::spawn(monitor_repositories(repos.clone(), console_messages.clone(), refresh.subscribe(), wake.clone()));
+        if let Err(err) = control::listen(repos.clone(), console_messages.clone(), wake.clone()) {
+            log_console(&console_messages, "System", "GitOp", EventKind::Status, format!("No control socket: {}", err));
+        }
+
+        Monitor { repos, console_messages, wake, _refresh: refresh }
+    }
+}
+
+// Run the monitor without a terminal. `listen` serves the WebSocket feed and, with `web`, the dashboard page;
+// `jsonl` prints every event to stdout as one JSON object per line.
+pub async fn run(config: Config, listen: Option<SocketAddr>, web: bool, jsonl: bool) -> Result<()> {
+    let webhook_listen = config.webhook_listen.clone();
+    let webhook_secret = config.credentials.as_ref().and_then(|credentials| credentials.webhook_secret.clone());
+    let notifications = config.notifications.clone().unwrap_or_default();
+    let mute_labels = config.mute_labels.clone().unwrap_or_default();
+    let monitor = Monitor::start(config);
+    let mut notifier = Notifier::new(notifications, mute_labels, monitor.repos.clone(), monitor.console_messages.clone());
+    let feed = Feed {
+        events: broadcast::channel(256).0,
+        states: Arc::new(Mutex::new(Vec::new())),
+        history: Arc::new(Mutex::new(VecDeque::new())),
+    };
+    if let Some(addr) = listen {
+        let listener = TcpListener::bind(addr).await?;
+        let addr = listener.local_addr()?;
+        let mut router = Router::new().route("/events", get(events_handler));
+        if web {
+            router = router
+                .route("/", get(|| async { Html(DASHBOARD_HTML) }))
+                .route("/api/state", get(state_handler));
+            eprintln!("Dashboard on http://{}/", addr);
+        } else {
+            eprintln!("WebSocket event feed on ws://{}/events", addr);
+        }
+        let router = router.with_state(feed.clone());
+        tokio::spawn(async move { axum::serve(listener, router).await });
+    }
+    if let Some(addr) = webhook_listen {
+        let addr: SocketAddr = addr.parse().map_err(|err| anyhow::anyhow!("invalid webhook_listen \"{}\": {}", addr, err))?;
+        let secret = webhook_secret.map(|reference| secrets::resolve(&reference)).transpose()?;
+        if secret.is_none() && !addr.ip().is_loopback() {
+            anyhow::bail!("webhook_listen {} accepts webhooks from other machines, set credentials.webhook_secret or listen on 127.0.0.1", addr);
+        }
+        webhook::listen(addr, secret, monitor.repos.clone(), monitor.console_messages.clone(), monitor.wake.clone()).await?;
+        eprintln!("Accepting webhooks on http://{}/webhook", addr);
+    }
+
+    let mut ticker = time::interval(Duration::from_millis(250));
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {}
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+        }
+
+        // Before collecting, which drains the console
+        notifier.poll();
+        for event in collect_events(&monitor, &feed.states) {
+            if jsonl {
+                println!("{}", serde_json::to_string(&event)?);
+            }
+            {
+                let mut history = feed.history.lock().unwrap();
+                history.push_back(event.clone());
+                if history.len() > HISTORY_LEN {
+                    history.pop_front();
+                }
+            }
+            // No receivers just means no WebSocket clients are connected
+            let _ = feed.events.send(event);
+        }
+    }
+}
+
+// `--plain`: the same monitor, printed as one readable line per change for screen readers, dumb terminals and logs
+pub async fn run_plain(config: Config) -> Result<()> {
+    let format = config.console_format.clone().unwrap_or_default();
+    let notifications = config.notifications.clone().unwrap_or_default();
+    let mute_labels = config.mute_labels.clone().unwrap_or_default();
+    let monitor = Monitor::start(config);
+    let mut notifier = Notifier::new(notifications, mute_labels, monitor.repos.clone(), monitor.console_messages.clone());
+    let states = Mutex::new(Vec::new());
+    let mut ticker = time::interval(Duration::from_millis(250));
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {}
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+        }
+
+        notifier.poll();
+        for event in collect_events(&monitor, &states) {
+            let line = match event {
+                Event::Status { timestamp, state } => {
+                    let mut line = format!(
+                        "[{}] {} on {}: {} ahead, {} behind",
+                        timestamp.format("%H:%M:%S"),
+                        state.repo,
+                        state.branch,
+                        state.ahead,
+                        state.behind
+                    );
+                    if state.dirty > 0 {
+                        line.push_str(&format!(", {} changed files", state.dirty));
+                    }
+                    if let Some(operation) = &state.operation {
+                        line.push_str(&format!(" ({})", operation));
+                    }
+                    line
+                }
+                Event::Message { timestamp, repo, author, kind, message } => {
+                    let message = ConsoleMessage { timestamp, repo, author, kind, message };
+                    format.line(&message, Default::default()).spans.iter().map(|span| span.content.as_ref()).collect()
+                }
+                Event::Policy { timestamp, repo, kind, remote, branch, commit, author, summary } => {
+                    let alert = PolicyAlert { timestamp, kind, remote, branch, commit, author, summary };
+                    format!("[{}] {}: Policy - {}", timestamp.format("%H:%M:%S"), repo, alert.describe())
+                }
+            };
+            println!("{}", line);
+        }
+    }
+}
+
+// Status events for repositories whose state changed since the last call, plus any new console messages
+pub fn collect_events(monitor: &Monitor, states: &Mutex<Vec<RepoState>>) -> Vec<Event> {
+    let mut repos = monitor.repos.lock().unwrap();
+    let current: Vec<RepoState> = repos.iter().map(RepoState::from_status).collect();
+    let mut states = states.lock().unwrap();
+    let mut events: Vec<Event> = current
+        .iter()
+        .filter(|state| !states.contains(state))
+        .map(|state| Event::Status { timestamp: Utc::now(), state: state.clone() })
+        .collect();
+    *states = current;
+
+    for repo in repos.iter_mut() {
+        let Some(policy) = repo.policy.as_mut() else {
+            continue;
+        };
+        events.extend(policy.alerts.drain(..).map(|alert| Event::Policy {
+            timestamp: alert.timestamp,
+            repo: repo.name.clone(),
+            kind: alert.kind,
+            remote: alert.remote,
+            branch: alert.branch,
+            commit: alert.commit,
+            author: alert.author,
+            summary: alert.summary,
+        }));
+    }
+    drop(repos);
+
+    // Nothing else reads the console in daemon mode, so drain it
+    let messages = std::mem::take(&mut *monitor.console_messages.lock().unwrap());
+    events.extend(messages.into_iter().map(|msg| Event::Message {
+        timestamp: msg.timestamp,
+        repo: msg.repo,
+        author: msg.author,
+        kind: msg.kind,
+        message: msg.message,
+    }));
+    events
+}
+
+async fn state_handler(State(feed): State<Feed>) -> Json<Snapshot> {
+    let repos = feed.states.lock().unwrap().clone();
+    let history = feed.history.lock().unwrap().iter().cloned().collect();
+    Json(Snapshot { repos, history })
+}
+
+async fn events_handler(ws: WebSocketUpgrade, State(feed): State<Feed>) -> impl IntoResponse {
+    ws.on_upgrade(move |socket| stream_events(socket, feed))
+}
+
+// New clients get the current state of every repository, then live events
+async fn stream_events(mut socket: WebSocket, feed: Feed) {
+    let mut events = feed.events.subscribe();
+    let snapshot: Vec<Event> = feed
+        .states
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|state| Event::Status { timestamp: Utc::now(), state: state.clone() })
+        .collect();
+    for event in &snapshot {
+        if send_event(&mut socket, event).await.is_err() {
+            return;
+        }
+    }
+
+    loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(event) => {
+                    if send_event(&mut socket, &event).await.is_err() {
+                        return;
+                    }
+                }
+                // A slow client misses some events but keeps the connection
+                Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(broadcast::error::RecvError::Closed) => return,
+            },
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
+
+async fn send_event(socket: &mut WebSocket, event: &Event) -> Result<()> {
+    socket.send(Message::Text(serde_json::to_string(event)?)).await?;
+    Ok(())
+}
@@ -0,0 +1,151 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::time::Duration;
+
+const GITHUB_API: &str = "https://api.github.com";
+
+// Below this many requests left, wait for the window to reset rather than run into 403s
+const RATE_LIMIT_RESERVE: u64 = 5;
+
+// How often a page refused for an exhausted limit is asked for again before giving up
+const RATE_LIMIT_RETRIES: u32 = 3;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ForgeRepo {
+    pub name: String,
+    pub full_name: String,
+    pub clone_url: String,
+    pub archived: bool,
+}
+
+// Request budget reported with every API response
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimit {
+    pub limit: u64,
+    pub remaining: u64,
+    pub reset: DateTime<Utc>,
+}
+
+impl RateLimit {
+    // From GitHub's x-ratelimit-* headers; the reset is a Unix timestamp
+    fn from_response(response: &ureq::Response) -> Option<Self> {
+        let header = |name: &str| response.header(name)?.trim().parse::<u64>().ok();
+        Some(RateLimit {
+            limit: header("x-ratelimit-limit")?,
+            remaining: header("x-ratelimit-remaining")?,
+            reset: DateTime::from_timestamp(header("x-ratelimit-reset")? as i64, 0)?,
+        })
+    }
+
+    pub fn describe(&self) -> String {
+        format!(
+            "{} of {} requests left, resets at {}",
+            self.remaining,
+            self.limit,
+            self.reset.with_timezone(&chrono::Local).format("%H:%M")
+        )
+    }
+
+    // Sleep until the window resets; the API doesn't count down any faster
+    fn wait(&self) {
+        let wait = (self.reset - Utc::now()).to_std().unwrap_or_default();
+        std::thread::sleep(wait + Duration::from_secs(1));
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum GithubOwner {
+    Org(String),
+    User(String),
+}
+
+// Token from the environment, as used by the gh CLI
+pub fn github_token() -> Option<String> {
+    std::env::var("GITHUB_TOKEN")
+        .or_else(|_| std::env::var("GH_TOKEN"))
+        .ok()
+        .filter(|token| !token.is_empty())
+}
+
+// Every repository of the owner, and the rate limit as of the last page. `on_wait` is told before each wait
+// for the limit to reset, so the caller can say why nothing happens.
+pub fn list_github_repos(
+    owner: &GithubOwner,
+    token: Option<&str>,
+    on_wait: impl Fn(&RateLimit),
+) -> Result<(Vec<ForgeRepo>, Option<RateLimit>)> {
+    let base = match owner {
+        GithubOwner::Org(org) => format!("{}/orgs/{}/repos", GITHUB_API, org),
+        GithubOwner::User(user) => format!("{}/users/{}/repos", GITHUB_API, user),
+    };
+
+    let mut repos = Vec::new();
+    let mut rate_limit: Option<RateLimit> = None;
+    let mut page = 1;
+    let mut refused = 0;
+    loop {
+        if let Some(limit) = rate_limit
+            && limit.remaining < RATE_LIMIT_RESERVE
+            && limit.reset > Utc::now()
+        {
+            on_wait(&limit);
+            limit.wait();
+        }
+
+        let mut request = ureq::get(&base)
+            .query("per_page", "100")
+            .query("page", &page.to_string())
+            .set("Accept", "application/vnd.github+json")
+            .set("User-Agent", "gitop");
+        if let Some(token) = token {
+            request = request.set("Authorization", &format!("Bearer {}", token));
+        }
+
+        let batch: Vec<ForgeRepo> = match request.call() {
+            Ok(response) => {
+                rate_limit = RateLimit::from_response(&response).or(rate_limit);
+                response.into_json()?
+            }
+            Err(ureq::Error::Status(code, response)) => {
+                // An exhausted limit is answered with 403 or 429; wait for the reset and ask for the same page again
+                if let Some(limit) = RateLimit::from_response(&response)
+                    && limit.remaining == 0
+                    && (code == 403 || code == 429)
+                {
+                    refused += 1;
+                    if refused > RATE_LIMIT_RETRIES {
+                        anyhow::bail!("GitHub API rate limit still used up after {} waits ({})", RATE_LIMIT_RETRIES, limit.describe());
+                    }
+                    rate_limit = Some(limit);
+                    continue;
+                }
+                let body = response.into_string().unwrap_or_default();
+                anyhow::bail!("GitHub API returned {}: {}", code, body.trim());
+            }
+            Err(err) => return Err(err.into()),
+        };
+        refused = 0;
+        let done = batch.len() < 100;
+        repos.extend(batch);
+        if done {
+            break;
+        }
+        page += 1;
+    }
+    Ok((repos, rate_limit))
+}
+
+// "owner/name" for a GitHub remote URL in https, ssh or scp-like form
+pub fn github_slug(url: &str) -> Option<String> {
+    let rest = url
+        .strip_prefix("git@github.com:")
+        .or_else(|| url.split_once("github.com/").map(|(_, rest)| rest))?;
+    let slug = rest.trim_end_matches('/').trim_end_matches(".git");
+    let mut parts = slug.split('/');
+    let (owner, name) = (parts.next()?, parts.next()?);
+    if owner.is_empty() || name.is_empty() {
+        return None;
+    }
+    Some(format!("{}/{}", owner, name).to_lowercase())
+}
@@ -1,27 +1,111 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, MouseButton, MouseEvent, MouseEventKind},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use git2::Repository;
+use git2::{
+    build::{CheckoutBuilder, RepoBuilder},
+    BranchType, Cred, CredentialType, FetchOptions, PushOptions, RemoteCallbacks, Repository, RepositoryState, StatusOptions,
+};
 use ratatui::{
     backend::{Backend, CrosstermBackend},
-    layout::{Constraint, Direction, Layout},
+    layout::{Constraint, Direction, Flex, Layout, Rect},
     style::{Color, Modifier, Style},
-    widgets::{Block, Borders, Cell, Row, Table, TableState, Paragraph, Wrap},
+    text::{Line, Span, Text},
+    widgets::{Block, Borders, Cell, Clear, Row, Table, TableState, Paragraph, Wrap},
     Frame, Terminal,
 };
+use commits::CommitCache;
+use notifications::{EventKind, NotificationConfig, Notifier};
+use secrets::{CredentialsConfig, Login};
 use serde::{Deserialize, Serialize};
 use std::{
+    collections::BTreeMap,
     io,
-    path::PathBuf,
+    path::{Path, PathBuf},
     sync::{Arc, Mutex},
     time::{Duration, Instant},
 };
-use tokio::time;
+use tokio::{
+    sync::{watch, Notify},
+    time,
+};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+mod agent;
+mod commits;
+mod control;
+mod daemon;
+mod forge;
+mod notifications;
+mod prompt;
+mod schema;
+mod secrets;
+mod snapshot;
+mod status;
+mod validate;
+mod webhook;
+
+const DOCS_URL: &str = "https://github.com/torkEng/gitop#readme";
+
+// How long a repository must stay selected before its refresh is moved forward
+const FOCUS_SETTLE: Duration = Duration::from_millis(500);
+
+// Commits drawn in the graph view; older history is cut off
+const GRAPH_COMMITS: usize = 300;
+
+// Files listed at once in the commit view; the list follows the selected file
+const COMMIT_FILES: usize = 15;
+
+// Lines shown when previewing a file from the tree view
+const PREVIEW_LINES: usize = 2000;
+
+// Commit search walks this far back from HEAD and from the upstream unless search_depth says otherwise, and
+// stops after this many matches
+const SEARCH_DEPTH: usize = 2000;
+const SEARCH_RESULTS: usize = 200;
+
+// Outgoing commits checked before a push, newest first, and the file size that is flagged in them and in what a
+// pull would bring in, unless large_file_warning says otherwise (GitHub's own warning starts there)
+const PUSH_CHECK_COMMITS: usize = 200;
+const LARGE_FILE_WARNING: u64 = 50 * 1024 * 1024;
+
+// Repository statistics walk the whole object store, so they're only recomputed this often
+const STATS_INTERVAL: Duration = Duration::from_secs(3600);
+
+// Console authors that are gitop itself rather than people, never colored
+const APP_AUTHORS: &[&str] = &["System", "GitOp", "Git Monitor", "Policy", "Trailer"];
+
+// Colors commit authors are spread over, leaving out the ones the table uses for state
+const AUTHOR_PALETTE: &[Color] = &[
+    Color::Green,
+    Color::Blue,
+    Color::Magenta,
+    Color::LightRed,
+    Color::LightGreen,
+    Color::LightBlue,
+    Color::LightMagenta,
+    Color::LightYellow,
+];
+
+// Newest console messages shown below the table
+const CONSOLE_LINES: usize = 8;
+
+// Fetches the average fetch time is taken over
+const FETCH_HISTORY: usize = 10;
+
+// How much longer every interval gets on a metered connection
+const METERED_SLOWDOWN: u32 = 4;
+
+// Intervals the +/- keys step through, in seconds
+const REFRESH_STEPS: &[u64] = &[1, 2, 5, 10, 15, 30, 60, 120, 300, 600, 900, 1800, 3600];
+
+// What the `:` command line understands, in the order Tab offers them
+const COMMANDS: &[&str] = &["filter", "label", "sort", "layout", "metered", "refresh", "fetch", "pull", "mute", "open", "quit"];
+const SORT_KEYS: &[&str] = &["config", "name", "label", "behind", "ahead", "attention", "size", "fetch", "diff", "branch"];
 
 #[derive(Parser)]
 #[command(name = "gitop")]
@@ -35,6 +119,10 @@ struct Cli {
     /// Path to config file (default: ~/.config/gitop/gitop.toml)
     #[arg(short, long)]
     config: Option<PathBuf>,
+    
+    /// Print status changes and messages as plain lines instead of drawing the full-screen interface
+    #[arg(long)]
+    plain: bool,
 }
 
 #[derive(Subcommand)]
@@ -44,9 +132,206 @@ enum Commands {
         /// Force overwrite existing config
         #[arg(short, long)]
         force: bool,
+        
+        /// Populate the config with git repositories found under this directory
+        #[arg(long, value_name = "DIR")]
+        scan: Option<PathBuf>,
+        
+        /// How many directory levels to descend when scanning
+        #[arg(long, default_value_t = 4)]
+        max_depth: usize,
+        
+        /// Walk through the settings with prompts
+        #[arg(short, long, conflicts_with = "scan")]
+        interactive: bool,
     },
     /// Show the current config file path
-    Config,
+    Config {
+        #[command(subcommand)]
+        action: Option<ConfigAction>,
+    },
+    /// Print where the config, state, cache and control socket live
+    Paths,
+    /// Import repositories from another multi-repo tool into the config
+    Import {
+        /// Tool to import from
+        #[arg(long, value_enum)]
+        from: ImportSource,
+        
+        /// Registry file to read instead of the tool's default location (required for "paths")
+        file: Option<PathBuf>,
+        
+        /// Print what would be imported without changing the config
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Clone configured repositories whose path doesn't exist but which have a url
+    CloneMissing {
+        /// Only list what would be cloned
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Check that every configured remote is reachable and accepts our credentials
+    Health,
+    /// Install git hooks that make a running gitop refresh the repository after commits, merges and checkouts
+    InstallHooks {
+        /// Repository to install the hooks into
+        #[arg(default_value = ".")]
+        repo: PathBuf,
+    },
+    /// Send a command to a running gitop over its control socket
+    Ctl {
+        #[command(subcommand)]
+        action: CtlAction,
+    },
+    /// Store or remove secrets in the OS keyring for `keyring:NAME` references in the config
+    Credentials {
+        #[command(subcommand)]
+        action: CredentialsAction,
+    },
+    /// Monitor without the TUI, printing status changes and messages as JSON lines
+    Daemon {
+        /// Also stream the events over WebSocket at ws://ADDR/events
+        #[arg(long, value_name = "ADDR")]
+        ws: Option<std::net::SocketAddr>,
+    },
+    /// Report this machine's repositories to a central gitop instance
+    Agent {
+        /// URL of the central instance, e.g. http://laptop.local:7879
+        #[arg(long)]
+        central: String,
+        
+        /// Name to group the repositories under (defaults to the hostname)
+        #[arg(long)]
+        host: Option<String>,
+        
+        /// Token the central instance expects (or set GITOP_AGENT_TOKEN)
+        #[arg(long)]
+        token: Option<String>,
+    },
+    /// Print a short ahead/behind segment for a shell prompt, from the state a running gitop last saved
+    Prompt {
+        /// Directory inside the repository to report on
+        #[arg(long, default_value = ".")]
+        repo: PathBuf,
+    },
+    /// Fetch every repository and list the ones that need attention, most urgent first
+    Status {
+        /// Output format, e.g. vim-quickfix to load the list into an editor
+        #[arg(long, value_enum, default_value_t = status::StatusFormat::Text)]
+        format: status::StatusFormat,
+        
+        /// Line per repository for --format template, with {name}, {path}, {branch}, {ahead}, {behind}, {dirty}, {operation} and {summary}
+        #[arg(long, required_if_eq("format", "template"))]
+        template: Option<String>,
+        
+        /// List every repository in config order, including those with nothing to do
+        #[arg(long)]
+        all: bool,
+    },
+    /// Fetch every repository and exit non-zero with a report if any breaks the given limits, for cron and CI
+    Check {
+        /// Most commits a repository may be behind its upstream
+        #[arg(long, value_name = "N")]
+        max_behind: Option<usize>,
+        
+        /// Most unpushed commits a repository may have
+        #[arg(long, value_name = "N")]
+        max_ahead: Option<usize>,
+        
+        /// Most uncommitted changes a repository may have
+        #[arg(long, value_name = "N")]
+        max_dirty: Option<usize>,
+        
+        /// Oldest the newest local or upstream commit may be, in days, e.g. 14d
+        #[arg(long, value_name = "DAYS", value_parser = status::parse_days)]
+        max_age: Option<i64>,
+    },
+    /// Fetch every repository and save its branch, ahead/behind and dirty counts to a file
+    Snapshot {
+        /// File to write the snapshot to
+        #[arg(long, value_name = "FILE")]
+        out: PathBuf,
+    },
+    /// Fetch every repository and show what changed since a snapshot
+    DiffSnapshot {
+        /// Snapshot written by `gitop snapshot`
+        file: PathBuf,
+    },
+    /// Serve a web dashboard with the repository table, console and history
+    Serve {
+        /// Address to serve the dashboard on
+        #[arg(long, value_name = "ADDR")]
+        web: std::net::SocketAddr,
+    },
+    /// Match a GitHub org's or user's repositories to local clones and add them to the config
+    SyncGithub {
+        /// GitHub organization to list
+        #[arg(long, required_unless_present = "user", conflicts_with = "user")]
+        org: Option<String>,
+        
+        /// GitHub user to list
+        #[arg(long)]
+        user: Option<String>,
+        
+        /// Directory holding the local clones
+        #[arg(long)]
+        root: PathBuf,
+        
+        /// How many directory levels to descend when looking for clones
+        #[arg(long, default_value_t = 4)]
+        max_depth: usize,
+        
+        /// Print the result without changing the config
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Strictly check the config file and report problems with line/column positions
+    Validate,
+    /// Print a JSON Schema of the config file for editor completion and validation
+    Schema,
+}
+
+#[derive(Subcommand)]
+enum CtlAction {
+    /// Refresh a repository now, by name or by a path inside it
+    Refresh { repo: String },
+    /// Stop polling a repository, or every repository when none is given
+    Pause { repo: Option<String> },
+    /// Resume polling a repository, or every repository when none is given
+    Resume { repo: Option<String> },
+    /// Show every monitored repository
+    Status {
+        /// Print the status as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum CredentialsAction {
+    /// Prompt for a secret and store it under NAME
+    Set { name: String },
+    /// Remove the secret stored under NAME
+    Delete { name: String },
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum ImportSource {
+    /// `ghq list --full-path`
+    Ghq,
+    /// gita's repos.csv
+    Gita,
+    /// mu-repo's .mu_repo file
+    MuRepo,
+    /// vcsh repositories under repo.d
+    Vcsh,
+    /// Newline-delimited list of paths
+    Paths,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -55,624 +340,7844 @@ struct Config {
     refresh_interval: u64, // seconds
     max_commits: usize,    // number of commits to show when expanded
     colors: Option<ColorConfig>,
+    show_descriptions: Option<bool>, // dimmed second line under each repo name
+    show_size: Option<bool>,         // disk usage column, refreshed with the repository statistics
+    show_fetch_time: Option<bool>,   // column with how long the last fetch took
+    show_diffstat: Option<bool>,     // column with +insertions/-deletions of the uncommitted changes
+    persist_commit_cache: Option<bool>, // keep commit metadata under ~/.cache/gitop/commits between runs
+    search_depth: Option<usize>,     // commits searched back from HEAD and from the upstream, default SEARCH_DEPTH
+    author_colors: Option<bool>,     // color commit authors in the console, each name always getting the same color
+    console_format: Option<ConsoleFormat>, // e.g. "{time} {repo:>12} {author} {message}"
+    highlights: Option<Vec<HighlightRule>>, // row colors by threshold, first matching rule wins
+    flash: Option<FlashConfig>,             // briefly color rows whose ahead/behind changed, off unless set
+    mute_labels: Option<Vec<String>>, // repos with any of these labels are never notified about
+    adaptive_refresh: Option<AdaptiveRefreshConfig>, // poll idle repos less often, active ones more
+    focus_interval: Option<u64>, // seconds between refreshes of the selected or expanded repos
+    metered: Option<bool>,       // start in metered mode, see RefreshPolicy
+    agent_listen: Option<String>, // address to accept `gitop agent` reports on
+    agent_token: Option<String>,  // "env:NAME" or "keyring:NAME", required from agents when set
+    webhook_listen: Option<String>, // address for GitHub/GitLab webhooks in daemon mode
+    rebase_command: Option<String>, // run for diverged branches instead of `git rebase -i {upstream}`
+    worktree_dir: Option<String>,   // where new worktrees go, defaults to "{path}-{branch}"
+    prune: Option<bool>,            // fetches delete remote-tracking branches that are gone on the server
+    maintenance_interval: Option<u64>, // seconds between `git maintenance` runs in every repo, off by default
+    size_warning: Option<u64>,         // megabytes; repos whose .git grows past this are highlighted
+    expected_email: Option<String>,    // user.email commits should get, "@example.com" for any address there
+    large_file_warning: Option<u64>,   // megabytes; bigger files in outgoing and incoming commits are flagged, default 50
+    credentials: Option<CredentialsConfig>, // forge tokens and HTTPS passwords as env:/keyring: references
+    notifications: Option<NotificationConfig>, // desktop, webhook and email notifications, off unless set
+    icons: Option<String>, // "emoji" (default), "nerdfont" or "none", see Icons
+    layout: Option<String>, // "table" (default) or "split", see LayoutMode
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct ColorConfig {
-    ahead_color: Option<String>,     // Color for ahead count arrows
-    behind_color: Option<String>,    // Color for behind count arrows  
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            repositories: vec![
+                RepoConfig {
+                    name: "Current Directory".to_string(),
+                    path: ".".to_string(),
+                    remote: Some("origin".to_string()),
+                    ..Default::default()
+                }
+            ],
+            refresh_interval: 5,
+            max_commits: 5,
+            colors: Some(ColorConfig {
+                ahead_color: Some("yellow".to_string()),
+                behind_color: Some("cyan".to_string()),
+                ..Default::default()
+            }),
+            show_descriptions: None,
+            show_size: None,
+            show_fetch_time: None,
+            show_diffstat: None,
+            persist_commit_cache: None,
+            search_depth: None,
+            author_colors: None,
+            console_format: None,
+            highlights: None,
+            flash: None,
+            mute_labels: None,
+            adaptive_refresh: None,
+            metered: None,
+            focus_interval: None,
+            agent_listen: None,
+            webhook_listen: None,
+            agent_token: None,
+            rebase_command: None,
+            worktree_dir: None,
+            prune: None,
+            maintenance_interval: None,
+            size_warning: None,
+            expected_email: None,
+            large_file_warning: None,
+            credentials: None,
+            notifications: None,
+            icons: None,
+            layout: None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-struct RepoConfig {
-    name: String,
-    path: String,
-    remote: Option<String>, // defaults to "origin"
-}
-
-#[derive(Debug, Clone)]
-struct RepoStatus {
-    name: String,
-    path: PathBuf,
-    ahead: usize,
-    behind: usize,
-    current_branch: String,
-    last_update: Instant,
-    expanded: bool,
-    recent_commits: Vec<CommitInfo>,
+struct AdaptiveRefreshConfig {
+    min_interval: Option<u64>, // seconds, for repos with activity in the last hour
+    max_interval: Option<u64>, // seconds, for repos idle for a week or more
 }
 
-#[derive(Debug, Clone)]
-struct CommitInfo {
-    hash: String,
-    author: String,
-    message: String,
-    branch: String,
-    timestamp: DateTime<Utc>,
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct FlashConfig {
+    duration: Option<u64>, // seconds, default 2
+    color: Option<String>, // row background, default yellow
 }
 
-#[derive(Debug, Clone)]
-struct ConsoleMessage {
-    timestamp: DateTime<Utc>,
-    repo: String,
-    author: String,
-    message: String,
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct ColorConfig {
+    theme: Option<String>,           // "default", "high-contrast" or "monochrome", for colors not set below
+    ahead_color: Option<String>,     // Color for ahead count arrows
+    behind_color: Option<String>,    // Color for behind count arrows  
+    branch_color: Option<String>,    // Branch column
+    repo_color: Option<String>,      // Repository names
+    header_color: Option<String>,    // Table header row
+    dirty_color: Option<String>,     // Uncommitted change count next to the branch
+    error_color: Option<String>,     // Failed backups, policy violations and size warnings
 }
 
-struct App {
-    repos: Arc<Mutex<Vec<RepoStatus>>>,
-    console_messages: Arc<Mutex<Vec<ConsoleMessage>>>,
-    table_state: TableState,
-    should_quit: bool,
-    max_commits: usize,
-    colors: ColorConfig,
+// How the selected repository's details are shown: table expands it in place with its recent commits, split
+// keeps a pane of them next to the table and leaves the table one row per repository
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+enum LayoutMode {
+    #[default]
+    Table,
+    Split,
 }
 
-fn parse_color(color_str: &str) -> Color {
-    match color_str.to_lowercase().as_str() {
-        "black" => Color::Black,
-        "red" => Color::Red,
-        "green" => Color::Green,
-        "yellow" => Color::Yellow,
-        "blue" => Color::Blue,
-        "magenta" => Color::Magenta,
-        "cyan" => Color::Cyan,
-        "gray" | "grey" => Color::Gray,
-        "darkgray" | "darkgrey" => Color::DarkGray,
-        "lightred" => Color::LightRed,
-        "lightgreen" => Color::LightGreen,
-        "lightyellow" => Color::LightYellow,
-        "lightblue" => Color::LightBlue,
-        "lightmagenta" => Color::LightMagenta,
-        "lightcyan" => Color::LightCyan,
-        "white" => Color::White,
-        "reset" | "default" | "normal" => Color::Reset,
-        _ => {
-            // Try to parse as RGB hex (e.g., "#FF5500" or "FF5500")
-            let hex = color_str.trim_start_matches('#');
-            if hex.len() == 6 {
-                if let (Ok(r), Ok(g), Ok(b)) = (
-                    u8::from_str_radix(&hex[0..2], 16),
-                    u8::from_str_radix(&hex[2..4], 16),
-                    u8::from_str_radix(&hex[4..6], 16),
-                ) {
-                    return Color::Rgb(r, g, b);
-                }
-            }
-            // Default to reset if parsing fails
-            Color::Reset
+impl LayoutMode {
+    fn from_name(name: &str) -> Option<LayoutMode> {
+        match name.to_ascii_lowercase().as_str() {
+            "table" => Some(LayoutMode::Table),
+            "split" => Some(LayoutMode::Split),
+            _ => None,
         }
     }
 }
 
-fn expand_path(path: &str) -> PathBuf {
-    if path.starts_with('~') {
-        // Try HOME first (Unix/Linux), then USERPROFILE (Windows)
-        if let Some(home) = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE")) {
-            let mut home_path = PathBuf::from(home);
-            // Handle both "~/" and "~" cases
-            if path.len() > 1 && path.chars().nth(1) == Some('/') {
-                home_path.push(&path[2..]); // Skip "~/"
-            } else if path.len() > 1 {
-                home_path.push(&path[1..]); // Skip "~"
-            }
-            home_path
-        } else {
-            PathBuf::from(path)
-        }
-    } else {
-        PathBuf::from(path)
-    }
+// What the split layout's pane shows beyond RepoStatus, loaded again when the selection or its activity changes
+#[derive(Debug, Clone)]
+struct SidePane {
+    repo: usize,
+    activity: Option<DateTime<Utc>>,
+    remotes: Vec<(String, String)>, // name and URL
 }
 
-impl App {
-    fn new(config: Config) -> Self {
-        let repos: Vec<RepoStatus> = config
-            .repositories
-            .into_iter()
-            .map(|repo_config| RepoStatus {
-                name: repo_config.name,
-                path: expand_path(&repo_config.path),
-                ahead: 0,
-                behind: 0,
-                current_branch: "unknown".to_string(),
-                last_update: Instant::now(),
-                expanded: false,
-                recent_commits: Vec::new(),
-            })
-            .collect();
+// Decorations in the table and the console. The default set sticks to symbols every font has, plus an emoji;
+// nerdfont swaps them for Nerd Font glyphs and adds a branch icon, none leaves plain ASCII.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+enum Icons {
+    #[default]
+    Emoji,
+    NerdFont,
+    None,
+}
 
-        let repos_empty = repos.is_empty();
-        
-        // Set up colors with defaults
-        let colors = config.colors.unwrap_or(ColorConfig {
-            ahead_color: Some("yellow".to_string()),
-            behind_color: Some("cyan".to_string()),
-        });
-        
-        Self {
-            repos: Arc::new(Mutex::new(repos)),
-            console_messages: Arc::new(Mutex::new(Vec::new())),
-            table_state: {
-                let mut state = TableState::default();
-                if !repos_empty {
-                    state.select(Some(0)); // Start with first repository selected
-                }
-                state
-            },
-            should_quit: false,
-            max_commits: config.max_commits,
-            colors,
+impl Icons {
+    fn from_name(name: &str) -> Option<Icons> {
+        match name.to_ascii_lowercase().as_str() {
+            "emoji" => Some(Icons::Emoji),
+            "nerdfont" => Some(Icons::NerdFont),
+            "none" => Some(Icons::None),
+            _ => None,
         }
     }
-
-    fn handle_key(&mut self, key: KeyCode) {
-        match key {
-            KeyCode::Char('q') => self.should_quit = true,
-            KeyCode::Down => self.next(),
-            KeyCode::Up => self.previous(),
-            KeyCode::Enter => self.toggle_expand(),
-            _ => {}
+    
+    // Ends "Repository is now up to date!"
+    fn celebration(self) -> &'static str {
+        match self {
+            Icons::Emoji => " 🎉",
+            Icons::NerdFont => " \u{f058}",
+            Icons::None => "",
         }
     }
-
-    fn next(&mut self) {
-        let repos = self.repos.lock().unwrap();
-        if repos.is_empty() {
-            return;
+    
+    fn branch(self) -> &'static str {
+        match self {
+            Icons::NerdFont => "\u{e725} ",
+            _ => "",
         }
-        
-        let current_repo_index = self.get_selected_repo_index(&repos);
-        let next_repo_index = if current_repo_index >= repos.len() - 1 {
-            0
-        } else {
-            current_repo_index + 1
-        };
-        
-        // Calculate the table row for this repository
-        let table_row = self.calculate_table_row(&repos, next_repo_index);
-        self.table_state.select(Some(table_row));
     }
-
-    fn previous(&mut self) {
-        let repos = self.repos.lock().unwrap();
-        if repos.is_empty() {
-            return;
+    
+    // Before the changed file count
+    fn dirty(self) -> &'static str {
+        match self {
+            Icons::Emoji => "●",
+            Icons::NerdFont => "\u{f044} ",
+            Icons::None => "*",
         }
-        
-        let current_repo_index = self.get_selected_repo_index(&repos);
-        let prev_repo_index = if current_repo_index == 0 {
-            repos.len() - 1
-        } else {
-            current_repo_index - 1
-        };
-        
-        // Calculate the table row for this repository
-        let table_row = self.calculate_table_row(&repos, prev_repo_index);
-        self.table_state.select(Some(table_row));
     }
     
-    fn get_selected_repo_index(&self, repos: &[RepoStatus]) -> usize {
-        if repos.is_empty() {
-            return 0;
-        }
-        
-        if let Some(selected_table_row) = self.table_state.selected() {
-            // Convert table row back to repository index
-            let mut current_table_row = 0;
-            for (repo_index, repo) in repos.iter().enumerate() {
-                if current_table_row == selected_table_row {
-                    return repo_index;
-                }
-                current_table_row += 1;
-                if repo.expanded {
-                    current_table_row += repo.recent_commits.len();
-                }
-                if current_table_row > selected_table_row {
-                    return repo_index;
-                }
-            }
+    fn marked(self) -> &'static str {
+        match self {
+            Icons::Emoji => "● ",
+            Icons::NerdFont => "\u{f046} ",
+            Icons::None => "x ",
         }
-        0
     }
     
-    fn calculate_table_row(&self, repos: &[RepoStatus], repo_index: usize) -> usize {
-        let mut table_row = 0;
-        for (i, repo) in repos.iter().enumerate() {
-            if i == repo_index {
-                return table_row;
-            }
-            table_row += 1; // Repository row
-            if repo.expanded {
-                table_row += repo.recent_commits.len(); // Commit rows
-            }
+    fn pinned(self) -> &'static str {
+        match self {
+            Icons::Emoji => "★ ",
+            Icons::NerdFont => "\u{f08d} ",
+            Icons::None => "^ ",
         }
-        table_row
     }
-
-    fn toggle_expand(&mut self) {
-        let mut repos = self.repos.lock().unwrap();
-        if repos.is_empty() {
-            return;
+    
+    // The attention priority, colored
+    fn priority(self) -> &'static str {
+        match self {
+            Icons::Emoji => "• ",
+            Icons::NerdFont => "\u{f111} ",
+            Icons::None => "o ",
         }
-        
-        let repo_index = self.get_selected_repo_index(&repos);
-        
-        if let Some(repo) = repos.get_mut(repo_index) {
-            repo.expanded = !repo.expanded;
-            if repo.expanded {
-                // Fetch recent commits when expanding
-                repo.recent_commits = get_recent_commits(&repo.path, self.max_commits);
-            }
+    }
+    
+    // After the header of the column the table is sorted by
+    fn sort_arrow(self, descending: bool) -> &'static str {
+        match (self, descending) {
+            (Icons::None, true) => " v",
+            (Icons::None, false) => " ^",
+            (_, true) => " ▼",
+            (_, false) => " ▲",
         }
-        
-        // Recalculate the table row after expanding/collapsing
-        let table_row = self.calculate_table_row(&repos, repo_index);
-        self.table_state.select(Some(table_row));
     }
 }
 
-fn get_config_path(custom_path: Option<PathBuf>) -> PathBuf {
-    // Use custom path if provided
-    if let Some(path) = custom_path {
-        return path;
+// ColorConfig resolved against its theme
+#[derive(Debug, Clone, Copy)]
+struct Palette {
+    ahead: Color,
+    behind: Color,
+    branch: Color,
+    repo: Color,
+    header: Color,
+    dirty: Color,
+    error: Color,
+}
+
+impl Palette {
+    fn theme(name: &str) -> Option<Palette> {
+        match name {
+            "default" => Some(Palette {
+                ahead: Color::Yellow,
+                behind: Color::Cyan,
+                branch: Color::Reset,
+                repo: Color::Reset,
+                header: Color::Reset,
+                dirty: Color::Red,
+                error: Color::Red,
+            }),
+            "high-contrast" => Some(Palette {
+                ahead: Color::LightRed,
+                behind: Color::LightGreen,
+                branch: Color::White,
+                repo: Color::White,
+                header: Color::LightYellow,
+                dirty: Color::LightMagenta,
+                error: Color::LightRed,
+            }),
+            "monochrome" => Some(Palette {
+                ahead: Color::Reset,
+                behind: Color::Reset,
+                branch: Color::Reset,
+                repo: Color::Reset,
+                header: Color::Reset,
+                dirty: Color::Reset,
+                error: Color::Reset,
+            }),
+            _ => None,
+        }
     }
     
-    // Try multiple locations in order of preference:
-    
-    // 1. Current directory (project-specific config) - check but don't prefer
-    let local_config = PathBuf::from("gitop.toml");
-    
-    // 2. User config directory (Linux: ~/.config/gitop/gitop.toml)
-    if let Some(config_dir) = std::env::var_os("XDG_CONFIG_HOME")
-        .map(PathBuf::from)
-        .or_else(|| {
-            std::env::var_os("HOME").map(|home| {
-                let mut path = PathBuf::from(home);
-                path.push(".config");
-                path
-            })
-        })
-    {
-        let user_config = config_dir.join("gitop").join("gitop.toml");
-        
-        // Prefer global config, but fall back to local if global doesn't exist and local does
-        if user_config.exists() || !local_config.exists() {
-            return user_config;
+    fn from_config(colors: Option<&ColorConfig>) -> Palette {
+        let Some(colors) = colors else {
+            return Palette::theme("default").unwrap();
+        };
+        let theme = colors.theme.as_deref().and_then(Palette::theme).unwrap_or_else(|| Palette::theme("default").unwrap());
+        let pick = |color: &Option<String>, fallback: Color| color.as_deref().map(parse_color).unwrap_or(fallback);
+        Palette {
+            ahead: pick(&colors.ahead_color, theme.ahead),
+            behind: pick(&colors.behind_color, theme.behind),
+            branch: pick(&colors.branch_color, theme.branch),
+            repo: pick(&colors.repo_color, theme.repo),
+            header: pick(&colors.header_color, theme.header),
+            dirty: pick(&colors.dirty_color, theme.dirty),
+            error: pick(&colors.error_color, theme.error),
         }
     }
-    
-    // 3. Fallback to current directory
-    local_config
 }
 
-fn create_default_config(config_path: &PathBuf) -> Result<()> {
-    // Create parent directory if it doesn't exist
-    if let Some(parent) = config_path.parent() {
-        std::fs::create_dir_all(parent)?;
-    }
-    
-    let default_config = Config {
-        repositories: vec![
-            RepoConfig {
-                name: "Current Directory".to_string(),
-                path: ".".to_string(),
-                remote: Some("origin".to_string()),
-            }
-        ],
-        refresh_interval: 5,
-        max_commits: 5,
-        colors: Some(ColorConfig {
-            ahead_color: Some("yellow".to_string()),
-            behind_color: Some("cyan".to_string()),
-        }),
-    };
-    
-    let config_content = toml::to_string_pretty(&default_config)?;
+// Foreground style that leaves the terminal default alone, so row highlights still show through
+fn fg(color: Color) -> Style {
+    if color == Color::Reset { Style::default() } else { Style::default().fg(color) }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct RepoConfig {
+    name: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    path: String, // empty for repositories watched through `url` without a clone
+    remote: Option<String>, // defaults to "origin"
+    description: Option<String>,
+    labels: Option<Vec<String>>,
+    pinned: Option<bool>, // always shown at the top of the table
+    url: Option<String>,  // clone source when the path doesn't exist yet, or the repository to watch when there is no path
+    backup_remote: Option<String>, // remote that all branches and tags are mirrored to
+    backup_interval: Option<u64>,  // seconds between backup pushes, defaults to an hour
+    protected_branches: Option<Vec<String>>, // incoming commits on these are checked against the policy
+    require_signed: Option<bool>,            // warn about unsigned commits on protected branches
+    detect_direct_pushes: Option<bool>,      // flag non-merge commits landing on protected branches
+    refspecs: Option<Vec<String>>, // fetched instead of the remote's configured refspecs
+    prs: Option<Vec<u64>>,         // pull/merge request numbers whose head refs are tracked
+    forge: Option<String>,         // "github" or "gitlab" pull request ref layout, detected from the url by default
+    notes: Option<bool>,           // fetch refs/notes/commits and show notes in the commit view
+    trailer_alerts: Option<Vec<TrailerAlert>>, // console alerts for incoming commits carrying these trailers
+    prune: Option<bool>,           // overrides the top-level prune
+    maintenance_interval: Option<u64>, // overrides the top-level maintenance_interval, 0 turns it off
+    check_mode: Option<String>,    // "fetch" (default) or "ls-remote" to fetch only when the branch tip moved
+    archived: Option<bool>,        // kept in the config but never checked, listed in a collapsed section
+    ignore_untracked: Option<bool>, // untracked files don't count as changes, e.g. build output nobody ignored
+    expected_email: Option<String>, // overrides the top-level expected_email
+    fetch_depth: Option<u32>,       // fetches stay this many commits deep, like `git fetch --depth`
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct TrailerAlert {
+    trailer: String,          // e.g. "Fixes", matched case-insensitively
+    contains: Option<String>, // only values containing this, any value when unset
+}
+
+// Console line layout, parsed when the config is loaded so a bad template is a config error
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+struct ConsoleFormat {
+    template: String,
+    segments: Vec<ConsoleSegment>,
+}
+
+#[derive(Debug, Clone)]
+enum ConsoleSegment {
+    Text(String),
+    // A field with an optional alignment ('<', '>' or '^') and minimum width, as in `{repo:>12}`
+    Field { field: ConsoleField, align: char, width: usize },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ConsoleField {
+    Time,
+    Date,
+    Repo,
+    Author,
+    Message,
+}
+
+impl Default for ConsoleFormat {
+    fn default() -> Self {
+        ConsoleFormat::try_from("[{time}] {repo}: {author} - {message}".to_string()).unwrap()
+    }
+}
+
+impl TryFrom<String> for ConsoleFormat {
+    type Error = String;
+    
+    fn try_from(template: String) -> Result<Self, String> {
+        let mut segments = Vec::new();
+        let mut rest = template.as_str();
+        while let Some(start) = rest.find('{') {
+            if start > 0 {
+                segments.push(ConsoleSegment::Text(rest[..start].to_string()));
+            }
+            let end = rest[start..].find('}').ok_or_else(|| format!("unclosed \"{{\" in \"{}\"", template))? + start;
+            let (name, spec) = rest[start + 1..end].split_once(':').unwrap_or((&rest[start + 1..end], ""));
+            let field = match name {
+                "time" => ConsoleField::Time,
+                "date" => ConsoleField::Date,
+                "repo" => ConsoleField::Repo,
+                "author" => ConsoleField::Author,
+                "message" => ConsoleField::Message,
+                _ => return Err(format!("unknown field {{{}}}, expected time, date, repo, author or message", name)),
+            };
+            let (align, width) = match spec.chars().next() {
+                Some(align @ ('<' | '>' | '^')) => (align, &spec[1..]),
+                _ => ('<', spec),
+            };
+            let width = if width.is_empty() { 0 } else { width.parse().map_err(|_| format!("invalid width \"{}\" for {{{}}}", spec, name))? };
+            segments.push(ConsoleSegment::Field { field, align, width });
+            rest = &rest[end + 1..];
+        }
+        if !rest.is_empty() {
+            segments.push(ConsoleSegment::Text(rest.to_string()));
+        }
+        Ok(ConsoleFormat { template, segments })
+    }
+}
+
+impl From<ConsoleFormat> for String {
+    fn from(format: ConsoleFormat) -> String {
+        format.template
+    }
+}
+
+impl ConsoleFormat {
+    fn line(&self, msg: &ConsoleMessage, author_style: Style) -> Line<'static> {
+        let spans = self.segments.iter().map(|segment| match segment {
+            ConsoleSegment::Text(text) => Span::raw(text.clone()),
+            ConsoleSegment::Field { field, align, width } => {
+                let value = match field {
+                    ConsoleField::Time => msg.timestamp.format("%H:%M:%S").to_string(),
+                    ConsoleField::Date => msg.timestamp.format("%Y-%m-%d").to_string(),
+                    ConsoleField::Repo => msg.repo.clone(),
+                    ConsoleField::Author => msg.author.clone(),
+                    ConsoleField::Message => msg.message.clone(),
+                };
+                let value = pad_width(&value, *width, *align);
+                if *field == ConsoleField::Author { Span::styled(value, author_style) } else { Span::raw(value) }
+            }
+        });
+        Line::from(spans.collect::<Vec<_>>())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct HighlightRule {
+    when: Condition, // e.g. "behind >= 10" or "age > 30"
+    color: String,
+}
+
+// A repository metric compared with a number, parsed when the config is loaded
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+struct Condition {
+    expression: String,
+    metric: Metric,
+    op: String,
+    value: i64,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Metric {
+    Ahead,
+    Behind,
+    Dirty,
+    Age, // days since the newest local or upstream commit
+}
+
+impl TryFrom<String> for Condition {
+    type Error = String;
+    
+    fn try_from(expression: String) -> Result<Self, String> {
+        let invalid = || format!("invalid condition \"{}\", expected e.g. \"behind >= 10\"", expression);
+        let mut parts = expression.split_whitespace();
+        let (Some(metric), Some(op), Some(value), None) = (parts.next(), parts.next(), parts.next(), parts.next()) else {
+            return Err(invalid());
+        };
+        let metric = match metric {
+            "ahead" => Metric::Ahead,
+            "behind" => Metric::Behind,
+            "dirty" => Metric::Dirty,
+            "age" => Metric::Age,
+            _ => return Err(format!("unknown metric \"{}\", expected ahead, behind, dirty or age", metric)),
+        };
+        if ![">=", "<=", ">", "<", "=="].contains(&op) {
+            return Err(invalid());
+        }
+        let op = op.to_string();
+        // Ages may be written with a unit for readability, "30d"
+        let value = value.strip_suffix('d').filter(|_| matches!(metric, Metric::Age)).unwrap_or(value);
+        let value = value.parse().map_err(|_| invalid())?;
+        Ok(Condition { expression, metric, op, value })
+    }
+}
+
+impl From<Condition> for String {
+    fn from(condition: Condition) -> String {
+        condition.expression
+    }
+}
+
+impl Condition {
+    fn matches(&self, repo: &RepoStatus) -> bool {
+        let actual = match self.metric {
+            Metric::Ahead => repo.ahead as i64,
+            Metric::Behind => repo.behind as i64,
+            Metric::Dirty => repo.dirty as i64,
+            Metric::Age => match repo.last_commit {
+                Some(time) => (Utc::now() - time).num_days(),
+                None => return false,
+            },
+        };
+        match self.op.as_str() {
+            ">=" => actual >= self.value,
+            "<=" => actual <= self.value,
+            ">" => actual > self.value,
+            "<" => actual < self.value,
+            _ => actual == self.value,
+        }
+    }
+}
+
+impl TrailerAlert {
+    fn matches(&self, key: &str, value: &str) -> bool {
+        key.eq_ignore_ascii_case(&self.trailer)
+            && self.contains.as_ref().is_none_or(|needle| value.to_lowercase().contains(&needle.to_lowercase()))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct RepoStatus {
+    name: String,
+    path: PathBuf,
+    remote: String,
+    refspecs: Vec<String>, // empty means the remote's configured refspecs
+    prune: bool,
+    url: Option<String>,
+    description: Option<String>,
+    labels: Vec<String>,
+    ahead: usize,
+    behind: usize,
+    dirty: usize, // uncommitted changes, including untracked files
+    diffstat: (usize, usize), // insertions and deletions in tracked files, work tree against HEAD
+    operation: Option<String>, // merge, rebase, etc. stopped half way, see repo_operation
+    conflicts: Vec<(String, &'static str)>, // unmerged paths and how they conflict; notifications are held while any remain
+    current_branch: String,
+    default_branch: Option<String>, // the remote's HEAD, or main/master, without the remote name
+    upstream_gone: Option<String>, // configured upstream of the current branch whose remote-tracking ref is gone
+    renamed_default: Option<(String, String)>, // old and new name of the remote's default branch, see default_branch_rename
+    rename_asked: Option<(String, u64)>, // candidate the server was last asked about, and the remote's branches then
+    counted: Option<CountedTips>, // tips the ahead/behind counts were computed for
+    last_update: Instant,
+    expanded: bool,
+    recent_commits: Vec<CommitInfo>,
+    commits: CommitCache, // metadata and revwalks behind the commit lists
+    marked: bool, // selected for batch actions
+    muted: bool,  // still monitored, but no console notifications
+    paused: bool, // not polled until resumed with `gitop ctl resume`
+    pinned: bool,
+    hidden: bool, // still monitored, but not displayed this session
+    archived: bool, // not monitored at all, listed at the bottom while the archived section is shown
+    backup: Option<BackupStatus>,
+    maintenance: Option<MaintenanceStatus>,
+    stats: Option<RepoStats>,
+    stats_checked: Option<Instant>, // when the last computation started, None forces one
+    stats_running: bool,
+    size_warning: Option<u64>, // bytes
+    policy: Option<PolicyStatus>,
+    pull_requests: Vec<PullRequestStatus>,
+    forge: Option<String>,
+    notes: bool,
+    light_check: bool, // ls-remote before fetching, see check_mode
+    ignore_untracked: bool,
+    fetch_depth: Option<u32>,
+    icons: Icons,
+    expected_email: Option<String>,
+    wrong_identity: Option<String>, // the user.email commits would get when it isn't the expected one
+    large_file_warning: u64,        // bytes
+    push_warnings: Vec<String>,     // about the outgoing commits, see push_warnings
+    outgoing_tip: Option<git2::Oid>, // HEAD when push_warnings were last worked out
+    incoming_large: Vec<(String, u64)>, // files over large_file_warning a pull would bring in, with their sizes
+    incoming_checked: Option<git2::Oid>, // upstream tip incoming_large was worked out for
+    last_error: Option<String>, // why the last status check failed, cleared by the next one that works
+    trailer_alerts: Vec<TrailerAlert>,
+    incoming_tip: Option<(String, git2::Oid)>, // remote-tracking branch and tip the trailer alerts last checked
+    last_activity: Option<DateTime<Utc>>, // newest commit or status change seen
+    last_commit: Option<DateTime<Utc>>,   // newest commit on the branch or its upstream
+    changed_at: Option<Instant>,          // when ahead/behind last changed after the first check
+    fetch_times: Vec<Duration>,           // newest last, at most FETCH_HISTORY
+    transfer: Transfer,                   // progress of the fetch in flight, if any
+    logins: Arc<Vec<Login>>,              // HTTPS credentials from the config, matched by host
+    remote_refs: Option<RemoteRefs>,      // set for repositories watched through `url` without a clone
+    poll_interval: Duration,
+    next_check: Instant,
+    checking: bool, // a scheduled check is running on a blocking thread, see check_repo
+    focused: bool, // selected in the table
+    host: Option<String>, // reported by `gitop agent` on this host rather than monitored locally
+}
+
+// Ahead/behind only change when one of the two tips does, and walking the graph is the expensive part of a
+// status check in large repositories
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct CountedTips {
+    local: git2::Oid,
+    upstream: git2::Oid,
+    ahead: usize,
+    behind: usize,
+    partial: bool, // counted in a shallow clone without reaching the merge base, so there are at least this many
+}
+
+// What a remote-only repository advertised at its last check, like `git ls-remote`
+#[derive(Debug, Clone, Default)]
+struct RemoteRefs {
+    head: Option<String>, // default branch, when the server names it
+    branches: BTreeMap<String, git2::Oid>,
+    tags: BTreeMap<String, git2::Oid>,
+    checked: bool, // changes are only reported after the first check
+    error: Option<String>,
+}
+
+// Shared with the fetch's progress callback, which runs on whichever thread is fetching
+type Transfer = Arc<Mutex<Option<TransferProgress>>>;
+
+#[derive(Debug, Clone, Copy)]
+struct TransferProgress {
+    received_objects: usize,
+    total_objects: usize,
+    received_bytes: usize,
+    indexed_deltas: usize,
+    total_deltas: usize,
+}
+
+impl TransferProgress {
+    fn describe(&self) -> String {
+        if self.received_objects < self.total_objects {
+            format!("receiving {}/{} objects, {}", self.received_objects, self.total_objects, format_size(self.received_bytes as u64))
+        } else {
+            format!("resolving deltas {}/{}", self.indexed_deltas, self.total_deltas)
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct BackupStatus {
+    remote: String,
+    interval: Duration,
+    last_attempt: Option<Instant>,
+    last_success: Option<DateTime<Utc>>,
+    lag: usize, // local branches whose tip differs from the backup remote
+    error: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+struct MaintenanceStatus {
+    interval: Duration,
+    last_run: Option<Instant>,
+    running: bool,
+    last_finished: Option<DateTime<Utc>>,
+    error: Option<String>,
+}
+
+// Object store metrics, computed in the background every STATS_INTERVAL
+#[derive(Debug, Clone)]
+struct RepoStats {
+    git_size: u64,   // everything under the git directory
+    disk_usage: u64, // the working tree including its git directory
+    loose_objects: usize,
+    loose_size: u64,
+    packed_objects: usize,
+    packs: usize,
+    pack_size: u64,
+    largest_files: Vec<(String, u64)>, // biggest blobs in HEAD's tree
+}
+
+#[derive(Debug, Clone)]
+struct PolicyStatus {
+    protected_branches: Vec<String>,
+    require_signed: bool,
+    detect_direct_pushes: bool,
+    tips: std::collections::HashMap<String, git2::Oid>, // last seen remote tip per protected branch
+    violations: usize,
+    direct_pushes: usize,
+    alerts: Vec<PolicyAlert>, // most recent last; daemon mode drains these into events
+}
+
+#[derive(Debug, Clone)]
+struct PullRequestStatus {
+    number: u64,
+    tip: Option<git2::Oid>,
+    last_commit: Option<String>, // summary line of the newest commit
+    updated: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum PolicyKind {
+    UnsignedCommit,
+    DirectPush,
+}
+
+#[derive(Debug, Clone)]
+struct PolicyAlert {
+    timestamp: DateTime<Utc>,
+    kind: PolicyKind,
+    remote: String,
+    branch: String,
+    commit: String,
+    author: String,
+    summary: String,
+}
+
+impl PolicyAlert {
+    fn describe(&self) -> String {
+        let what = match self.kind {
+            PolicyKind::UnsignedCommit => "Unsigned commit",
+            PolicyKind::DirectPush => "Direct push (not a merge)",
+        };
+        format!("{} {} on {}/{} by {}: {}", what, self.commit, self.remote, self.branch, self.author, self.summary)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct CommitInfo {
+    hash: String,
+    author: String,
+    message: String,
+    branch: String,
+    timestamp: DateTime<Utc>,
+}
+
+// Everything the commit popup shows, loaded when it opens
+#[derive(Debug, Clone)]
+struct CommitDetail {
+    repo: usize,  // index into repos
+    index: usize, // index into the repository's recent commits
+    hash: String,
+    author: String,
+    timestamp: DateTime<Utc>,
+    message: String,               // without the trailer block
+    trailers: Vec<(String, String)>,
+    notes: Option<String>, // only looked up when the repository has notes enabled
+    files: Vec<(String, usize, usize)>, // path, insertions, deletions against the first parent
+    selected_file: usize,               // the file blame opens for
+    search: Option<Box<CommitSearch>>,  // opened from search results, which ↑/↓ and Esc go back to
+}
+
+// Commits whose message, author or hash matched a search, by repository and newest first
+#[derive(Debug, Clone)]
+struct CommitSearch {
+    repo: Option<usize>, // index into repos, None when every repository was searched
+    query: String,
+    hits: Vec<SearchHit>,
+    selected: usize,
+    searching: bool, // a search of every repository still running in the background
+}
+
+#[derive(Debug, Clone)]
+struct SearchHit {
+    repo: usize, // index into repos
+    hash: String,
+    author: String,
+    summary: String,
+    timestamp: DateTime<Utc>,
+    local: bool,    // reachable from HEAD
+    upstream: bool, // reachable from the remote-tracking branch
+}
+
+// A directory of a commit's tree, browsed read-only
+#[derive(Debug, Clone)]
+struct TreeView {
+    repo: usize,                 // index into repos
+    rev: String,                 // what the tree is of, e.g. "HEAD", "origin/main" or a commit hash
+    other_rev: Option<String>,   // HEAD or upstream to switch to with u, when opened from the table
+    dir: String,                 // "" for the root, otherwise "src/bin/"
+    entries: Vec<(String, bool)>, // name and whether it's a directory, directories first
+    selected: usize,
+}
+
+// Incoming or outgoing commits of a repository, rendered as markdown for the popup and the export
+#[derive(Debug, Clone)]
+struct Changelog {
+    repo: usize, // index into repos
+    outgoing: bool, // HEAD's commits missing upstream, instead of upstream's commits missing locally
+    range: String,
+    commits: Vec<CommitInfo>, // newest first, merges left out
+    by_author: bool,          // group by author instead of conventional-commit type
+    selected: usize,          // position in display order, the target of cherry-picks and reverts
+}
+
+#[derive(Debug, Clone, Copy)]
+enum CommitAction {
+    CherryPick,
+    Revert,
+}
+
+// Headings for conventional-commit types, in the order they're listed
+const CHANGELOG_SECTIONS: &[(&str, &str)] = &[
+    ("feat", "Features"),
+    ("fix", "Bug Fixes"),
+    ("perf", "Performance"),
+    ("refactor", "Refactoring"),
+    ("docs", "Documentation"),
+    ("test", "Tests"),
+    ("build", "Build"),
+    ("ci", "Build"),
+    ("chore", "Chores"),
+];
+
+impl Changelog {
+    // Headings with the indexes of their commits, in the order they're shown
+    fn groups(&self) -> Vec<(String, Vec<usize>)> {
+        let mut groups: Vec<(String, Vec<usize>)> = Vec::new();
+        let mut other = Vec::new();
+        for (index, commit) in self.commits.iter().enumerate() {
+            let group = if self.by_author {
+                commit.author.as_str()
+            } else {
+                match conventional_commit(&commit.message) {
+                    Some((_, _, true, _)) => "Breaking Changes",
+                    Some((kind, _, _, _)) => match CHANGELOG_SECTIONS.iter().find(|(name, _)| *name == kind) {
+                        Some((_, section)) => section,
+                        None => {
+                            other.push(index);
+                            continue;
+                        }
+                    },
+                    None => {
+                        other.push(index);
+                        continue;
+                    }
+                }
+            };
+            match groups.iter_mut().find(|(title, _)| title == group) {
+                Some((_, indexes)) => indexes.push(index),
+                None => groups.push((group.to_string(), vec![index])),
+            }
+        }
+        
+        if !self.by_author {
+            // Breaking changes first, then the usual section order, then anything unconventional
+            groups.sort_by_key(|(title, _)| match title.as_str() {
+                "Breaking Changes" => 0,
+                title => 1 + CHANGELOG_SECTIONS.iter().position(|(_, section)| *section == title).unwrap_or(0),
+            });
+        }
+        if !other.is_empty() {
+            groups.push(("Other Changes".to_string(), other));
+        }
+        groups
+    }
+    
+    // Commit index at each display position
+    fn display_order(&self) -> Vec<usize> {
+        self.groups().into_iter().flat_map(|(_, indexes)| indexes).collect()
+    }
+    
+    fn selected_commit(&self) -> Option<&CommitInfo> {
+        self.display_order().get(self.selected).map(|&index| &self.commits[index])
+    }
+    
+    // Markdown lines, with the entry at display position `selected` marked for the popup
+    fn lines(&self, name: &str, selected: Option<usize>) -> Vec<String> {
+        let mut lines = vec![format!("# {}: {}", name, self.range), String::new(), format!("{} {} commits", self.commits.len(), self.direction())];
+        let mut position = 0;
+        for (title, indexes) in self.groups() {
+            lines.extend([String::new(), format!("## {}", title), String::new()]);
+            for index in indexes {
+                let commit = &self.commits[index];
+                let text = match conventional_commit(&commit.message) {
+                    Some((_, scope, _, description)) if !self.by_author => {
+                        let scope = scope.map(|scope| format!("**{}:** ", scope)).unwrap_or_default();
+                        format!("{}{}", scope, description)
+                    }
+                    _ => commit.message.clone(),
+                };
+                let bullet = if selected == Some(position) { ">" } else { "-" };
+                lines.push(format!("{} {} ({})", bullet, text, commit.hash));
+                position += 1;
+            }
+        }
+        lines
+    }
+    
+    fn direction(&self) -> &'static str {
+        if self.outgoing { "outgoing" } else { "incoming" }
+    }
+    
+    fn markdown(&self, name: &str) -> String {
+        let mut markdown = self.lines(name, None).join("\n");
+        markdown.push('\n');
+        markdown
+    }
+}
+
+// Split "type(scope)!: description" into its parts; None for messages that don't follow the convention
+fn conventional_commit(summary: &str) -> Option<(String, Option<&str>, bool, &str)> {
+    let (prefix, description) = summary.split_once(": ")?;
+    let (prefix, breaking) = match prefix.strip_suffix('!') {
+        Some(prefix) => (prefix, true),
+        None => (prefix, false),
+    };
+    let (kind, scope) = match prefix.split_once('(') {
+        Some((kind, scope)) => (kind, Some(scope.strip_suffix(')')?)),
+        None => (prefix, None),
+    };
+    if kind.is_empty() || !kind.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    Some((kind.to_ascii_lowercase(), scope, breaking, description.trim()))
+}
+
+#[derive(Debug, Clone)]
+struct StaleBranch {
+    name: String,
+    tip: git2::Oid,      // skipped at deletion if the branch moved since
+    upstream_gone: bool, // configured upstream no longer exists on the remote
+}
+
+// One row of the branch overview
+#[derive(Debug, Clone)]
+struct BranchInfo {
+    name: String,
+    current: bool,
+    upstream: Option<String>, // None when no upstream is configured
+    upstream_gone: bool,
+    ahead: usize, // relative to the upstream
+    behind: usize,
+    last_commit: DateTime<Utc>,
+    base: Option<(usize, usize)>, // commits ahead of and behind the default branch since their merge base
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Bump {
+    Patch,
+    Minor,
+    Major,
+}
+
+// Next version based on the conventional commits since the newest tag reachable from HEAD
+#[derive(Debug, Clone)]
+struct ReleaseSuggestion {
+    last_tag: Option<String>,
+    commits: usize,
+    bump: Bump,
+    next: Option<String>, // None when the tag isn't a version gitop can bump
+}
+
+impl ReleaseSuggestion {
+    fn describe(&self) -> String {
+        let bump = match self.bump {
+            Bump::Patch => "patch",
+            Bump::Minor => "minor",
+            Bump::Major => "major",
+        };
+        match (&self.last_tag, &self.next) {
+            (None, _) => format!("no tags yet, {} commits", self.commits),
+            (Some(tag), _) if self.commits == 0 => format!("{} is up to date", tag),
+            (Some(tag), Some(next)) => format!("{} + {} commits -> {} ({})", tag, self.commits, next, bump),
+            (Some(tag), None) => format!("{} + {} commits, {} bump", tag, self.commits, bump),
+        }
+    }
+}
+
+// "v1.2.3" or "1.2.3" bumped, keeping the prefix; pre-1.0 versions bump minor for breaking changes
+fn bump_version(tag: &str, bump: Bump) -> Option<String> {
+    let (prefix, version) = match tag.strip_prefix('v') {
+        Some(version) => ("v", version),
+        None => ("", tag),
+    };
+    let mut parts = version.splitn(3, '.').map(|part| part.parse::<u64>().ok());
+    let (major, minor, patch) = (parts.next()??, parts.next()??, parts.next()??);
+    let (major, minor, patch) = match bump {
+        Bump::Major if major > 0 => (major + 1, 0, 0),
+        Bump::Major | Bump::Minor => (major, minor + 1, 0),
+        Bump::Patch => (major, minor, patch + 1),
+    };
+    Some(format!("{}{}.{}.{}", prefix, major, minor, patch))
+}
+
+#[derive(Debug, Clone)]
+enum PullOutcome {
+    UpToDate,
+    FastForward,
+    Merge,
+    Conflicts(Vec<String>),
+}
+
+// What pulling would do, worked out from the remote-tracking branch without touching the work tree
+#[derive(Debug, Clone)]
+struct PullPreview {
+    branch: String,
+    upstream: String,
+    outcome: PullOutcome,
+    commits: usize,
+    files: Vec<(String, usize, usize)>, // path, insertions, deletions
+    insertions: usize,
+    deletions: usize,
+    blocked: Vec<String>, // uncommitted changes to files the pull would update
+}
+
+#[derive(Debug, Clone)]
+struct ConsoleMessage {
+    timestamp: DateTime<Utc>,
+    repo: String,
+    author: String,
+    kind: EventKind, // what notifications route it by
+    message: String,
+}
+
+#[derive(Debug, Clone)]
+enum Popup {
+    // Confirmation for pulling every clean, behind repository
+    PullAll { targets: Vec<usize>, skipped: Vec<String> },
+    // Details for the repository at this index, with the release suggestion worked out when it opened
+    Detail { repo: usize, release: Option<ReleaseSuggestion>, identity: Option<Identity> },
+    // One of the repository's recent commits
+    Commit(CommitDetail),
+    // What pulling the repository would bring in, or what pushing would send
+    Changelog(Changelog),
+    // Confirmation before reverting an outgoing commit, returning to the changelog on cancel
+    ConfirmRevert { changelog: Changelog, commit: CommitInfo },
+    // Single-line text input
+    Prompt(Prompt),
+    // Results of a commit search, Enter shows the selected commit
+    CommitSearch(CommitSearch),
+    // Offer to clone configured repositories whose path doesn't exist
+    CloneMissing(Vec<usize>),
+    // First run without a config file
+    Welcome,
+    // Offer to push a tag that was just created
+    PushTag { repo: usize, name: String },
+    // Offer to switch to a branch that was just created
+    CheckoutBranch { repo: usize, name: String },
+    // Confirmation for deleting local branches that are merged or whose upstream is gone
+    CleanupBranches { repo: usize, default_branch: String, branches: Vec<StaleBranch> },
+    // Confirmation for tracking the remote's renamed default branch
+    RetargetUpstream { repo: usize, old: String, new: String },
+    // Ways out for a branch that is both ahead of and behind its upstream
+    Diverged(usize),
+    // Dry run of pulling the repository at this index
+    PullPreview { repo: usize, preview: PullPreview },
+    // Every local branch of the repository against its upstream and the default branch
+    Branches { repo: usize, default_branch: Option<String>, branches: Vec<BranchInfo> },
+    // `git log --graph --oneline` style history, scrolled by line
+    Graph { repo: usize, lines: Vec<String>, scroll: usize },
+    // Blame of a file touched by the commit, as of that commit; Esc goes back to the commit
+    Blame { commit: CommitDetail, file: String, lines: Vec<String>, scroll: usize },
+    // `git status --short` of the repository, scrolled by line
+    WorkTree { repo: usize, lines: Vec<String>, scroll: usize },
+    // Ahead/behind of the current branch against every remote's copy of it and default branch
+    RemoteMatrix { repo: usize, lines: Vec<String> },
+    // Unmerged paths and how each side changed them, scrolled by line
+    Conflicts { repo: usize, lines: Vec<String>, scroll: usize },
+    // Files and directories of a commit's tree
+    Tree(TreeView),
+    // Contents of a file from the tree view; Esc goes back to the tree
+    FilePreview { tree: TreeView, file: String, lines: Vec<String>, scroll: usize },
+}
+
+#[derive(Debug, Clone)]
+struct Prompt {
+    title: String,
+    input: String,
+    error: Option<String>,
+    action: PromptAction,
+}
+
+#[derive(Debug, Clone)]
+enum PromptAction {
+    AddRepoPath,
+    ScanDirectory,
+    AddRepoName { path: String },
+    TagName { repo: usize },
+    TagMessage { repo: usize, name: String },
+    WorktreeBranch { repo: usize },
+    BranchName { repo: usize, commit: String },
+    SearchCommits { repo: usize },
+    SearchEverywhere,
+}
+
+impl Prompt {
+    fn new(title: &str, input: String, action: PromptAction) -> Self {
+        Prompt {
+            title: title.to_string(),
+            input,
+            error: None,
+            action,
+        }
+    }
+}
+
+// Work that needs the real terminal, run by the UI loop with the TUI suspended
+#[derive(Debug, Clone)]
+enum SuspendAction {
+    EditConfig,
+    Rebase { repo: usize, upstream: String },
+}
+
+// How the console treats messages about repositories other than the selected one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ConsoleFilter {
+    All,
+    Dim,
+    Only,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SortKey {
+    Config,
+    Name,
+    Label,
+    Behind,
+    Ahead,
+    Attention, // RepoStatus::attention, most urgent first
+    Size,      // disk usage
+    FetchTime, // how long the last fetch took
+    Diff,      // lines changed in the work tree
+    Branch,
+}
+
+impl SortKey {
+    // As listed in SORT_KEYS
+    fn from_name(name: &str) -> Option<SortKey> {
+        match name.to_lowercase().as_str() {
+            "config" => Some(SortKey::Config),
+            "name" => Some(SortKey::Name),
+            "label" => Some(SortKey::Label),
+            "behind" => Some(SortKey::Behind),
+            "ahead" => Some(SortKey::Ahead),
+            "attention" => Some(SortKey::Attention),
+            "size" => Some(SortKey::Size),
+            "fetch" => Some(SortKey::FetchTime),
+            "diff" => Some(SortKey::Diff),
+            "branch" => Some(SortKey::Branch),
+            _ => None,
+        }
+    }
+    
+    fn name(self) -> &'static str {
+        match self {
+            SortKey::Config => "config",
+            SortKey::Name => "name",
+            SortKey::Label => "label",
+            SortKey::Behind => "behind",
+            SortKey::Ahead => "ahead",
+            SortKey::Attention => "attention",
+            SortKey::Size => "size",
+            SortKey::FetchTime => "fetch",
+            SortKey::Diff => "diff",
+            SortKey::Branch => "branch",
+        }
+    }
+    
+    // Counts put the biggest first, names go alphabetically
+    fn descending(self) -> bool {
+        !matches!(self, SortKey::Config | SortKey::Name | SortKey::Label | SortKey::Branch)
+    }
+    
+    fn compare(self, a: &RepoStatus, b: &RepoStatus) -> std::cmp::Ordering {
+        let ascending = match self {
+            SortKey::Config => std::cmp::Ordering::Equal,
+            SortKey::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+            // Unlabeled repositories go last
+            SortKey::Label => (a.labels.is_empty(), a.labels.first()).cmp(&(b.labels.is_empty(), b.labels.first())),
+            SortKey::Behind => a.behind.cmp(&b.behind),
+            SortKey::Ahead => a.ahead.cmp(&b.ahead),
+            SortKey::Attention => a.attention().cmp(&b.attention()),
+            SortKey::Size => a.stats.as_ref().map(|stats| stats.disk_usage).cmp(&b.stats.as_ref().map(|stats| stats.disk_usage)),
+            SortKey::FetchTime => a.fetch_times.last().cmp(&b.fetch_times.last()),
+            SortKey::Diff => (a.diffstat.0 + a.diffstat.1).cmp(&(b.diffstat.0 + b.diffstat.1)),
+            SortKey::Branch => a.current_branch.to_lowercase().cmp(&b.current_branch.to_lowercase()),
+        };
+        if self.descending() { ascending.reverse() } else { ascending }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum BatchAction {
+    Fetch,
+    Pull,
+    Mute,
+    Open,
+}
+
+struct App {
+    repos: Arc<Mutex<Vec<RepoStatus>>>,
+    console_messages: Arc<Mutex<Vec<ConsoleMessage>>>,
+    notifier: Notifier,
+    table_state: TableState,
+    selected: usize, // index into repos, independent of sorting and filtering
+    label_filter: Option<String>,
+    name_filter: Option<String>, // from `:filter`, matched against names and labels ignoring case
+    sort_key: SortKey,
+    sort_reversed: bool, // against the key's own direction, by picking its column again
+    show_hidden: bool,
+    show_archived: bool,
+    should_quit: bool,
+    max_commits: usize,
+    colors: Palette,
+    page_size: usize, // visible table rows, updated on every draw
+    popup: Option<Popup>,
+    show_descriptions: bool,
+    show_size: bool,
+    show_fetch_time: bool,
+    show_diffstat: bool,
+    search_depth: usize,
+    author_colors: bool,
+    console_format: ConsoleFormat,
+    highlights: Vec<HighlightRule>,
+    flash: Option<(Duration, Color)>,
+    config_path: PathBuf,
+    suspend: Option<SuspendAction>,
+    rebase_command: Option<String>,
+    worktree_dir: Option<String>,
+    repo_defaults: RepoDefaults, // for repositories added while running
+    refresh: watch::Sender<RefreshPolicy>, // the monitor picks up changes immediately
+    wake_monitor: Arc<Notify>,
+    focus: Option<(usize, Option<Instant>)>, // selected repo and when it was selected, None once its refresh was bumped
+    console_selected: Option<usize>, // console has keyboard focus on this message, 0 being the newest
+    console_filter: ConsoleFilter,
+    console_rows: (Rect, Vec<usize>), // console text area and the message shown on each of its rows, updated on every draw
+    header_cells: Vec<(Rect, SortKey)>, // where each column's header is, for clicks, updated on every draw
+    command_line: Option<String>, // typed after `:`, shown in place of the controls
+    command_error: Option<String>, // why the last command failed, shown until the next key
+    layout: LayoutMode,
+    side_pane: Option<SidePane>, // split layout only
+    search_results: Arc<Mutex<Option<CommitSearch>>>, // finished search of every repository, shown if its popup is still open
+}
+
+fn flash_settings(flash: &FlashConfig) -> (Duration, Color) {
+    let duration = Duration::from_secs(flash.duration.unwrap_or(2));
+    (duration, flash.color.as_deref().map(parse_color).unwrap_or(Color::Yellow))
+}
+
+fn parse_color(color_str: &str) -> Color {
+    // Default to reset if parsing fails
+    try_parse_color(color_str).unwrap_or(Color::Reset)
+}
+
+fn try_parse_color(color_str: &str) -> Option<Color> {
+    let color = match color_str.to_lowercase().as_str() {
+        "black" => Color::Black,
+        "red" => Color::Red,
+        "green" => Color::Green,
+        "yellow" => Color::Yellow,
+        "blue" => Color::Blue,
+        "magenta" => Color::Magenta,
+        "cyan" => Color::Cyan,
+        "gray" | "grey" => Color::Gray,
+        "darkgray" | "darkgrey" => Color::DarkGray,
+        "lightred" => Color::LightRed,
+        "lightgreen" => Color::LightGreen,
+        "lightyellow" => Color::LightYellow,
+        "lightblue" => Color::LightBlue,
+        "lightmagenta" => Color::LightMagenta,
+        "lightcyan" => Color::LightCyan,
+        "white" => Color::White,
+        "reset" | "default" | "normal" => Color::Reset,
+        _ => {
+            // Try to parse as RGB hex (e.g., "#FF5500" or "FF5500")
+            let hex = color_str.trim_start_matches('#');
+            if hex.len() == 6
+                && let (Ok(r), Ok(g), Ok(b)) = (
+                    u8::from_str_radix(&hex[0..2], 16),
+                    u8::from_str_radix(&hex[2..4], 16),
+                    u8::from_str_radix(&hex[4..6], 16),
+                )
+            {
+                return Some(Color::Rgb(r, g, b));
+            }
+            return None;
+        }
+    };
+    Some(color)
+}
+
+fn expand_path(path: &str) -> PathBuf {
+    let path = expand_env_vars(path);
+    if let Some(rest) = path.strip_prefix('~') {
+        if let Some(mut home_path) = home_dir() {
+            // Handle "~/", "~\" and "~" alike
+            let rest = rest.strip_prefix(['/', '\\']).unwrap_or(rest);
+            if !rest.is_empty() {
+                home_path.push(rest);
+            }
+            windows_path(home_path)
+        } else {
+            PathBuf::from(path)
+        }
+    } else {
+        windows_path(PathBuf::from(path))
+    }
+}
+
+// HOME first (Unix/Linux, and Git Bash on Windows), then USERPROFILE (Windows)
+fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE")).map(PathBuf::from)
+}
+
+// Windows-style %NAME% references, e.g. "%USERPROFILE%\src\app". Unset variables and lone percent signs are
+// left as they are, so the same config works on every machine it's shared with.
+fn expand_env_vars(path: &str) -> String {
+    let mut expanded = String::new();
+    let mut rest = path;
+    while let Some(start) = rest.find('%') {
+        let Some(length) = rest[start + 1..].find('%') else {
+            break;
+        };
+        let name = &rest[start + 1..start + 1 + length];
+        expanded.push_str(&rest[..start]);
+        match std::env::var(name).ok().filter(|_| !name.is_empty()) {
+            Some(value) => {
+                expanded.push_str(&value);
+                rest = &rest[start + length + 2..];
+            }
+            // The closing percent sign may open the next reference
+            None => {
+                expanded.push('%');
+                rest = &rest[start + 1..];
+            }
+        }
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+// On Windows, Git Bash style "/c/Users/me" becomes "C:\Users\me", a bare drive "D:" its root, and forward
+// slashes (also in UNC paths like "//server/share") become backslashes, so paths display and compare the same
+// however the config wrote them. Elsewhere paths are left alone.
+fn windows_path(path: PathBuf) -> PathBuf {
+    if !cfg!(windows) {
+        return path;
+    }
+    let mut path = path.to_string_lossy().replace('/', "\\");
+    let bytes = path.as_bytes();
+    if bytes.len() >= 2 && bytes[0] == b'\\' && bytes[1].is_ascii_alphabetic() && (bytes.len() == 2 || bytes[2] == b'\\') {
+        path = format!("{}:\\{}", (bytes[1] as char).to_ascii_uppercase(), path.get(3..).unwrap_or(""));
+    } else if bytes.len() == 2 && bytes[1] == b':' && bytes[0].is_ascii_alphabetic() {
+        path.push('\\');
+    }
+    PathBuf::from(path)
+}
+
+// Top-level settings that apply to every repository unless it overrides them
+#[derive(Debug, Clone, Default)]
+struct RepoDefaults {
+    prune: bool,
+    maintenance_interval: Option<u64>,
+    size_warning: Option<u64>, // bytes
+    logins: Arc<Vec<Login>>,
+    persist_commit_cache: bool,
+    icons: Icons,
+    expected_email: Option<String>,
+    large_file_warning: u64, // bytes
+}
+
+impl RepoDefaults {
+    fn from_config(config: &Config) -> Self {
+        RepoDefaults {
+            prune: config.prune.unwrap_or(false),
+            maintenance_interval: config.maintenance_interval,
+            size_warning: config.size_warning.map(|megabytes| megabytes * 1024 * 1024),
+            logins: Arc::new(secrets::logins(config.credentials.as_ref())),
+            persist_commit_cache: config.persist_commit_cache.unwrap_or(false),
+            icons: config.icons.as_deref().and_then(Icons::from_name).unwrap_or_default(),
+            expected_email: config.expected_email.clone(),
+            large_file_warning: config.large_file_warning.map_or(LARGE_FILE_WARNING, |megabytes| megabytes * 1024 * 1024),
+        }
+    }
+}
+
+impl RepoStatus {
+    fn from_config(repo_config: RepoConfig, defaults: &RepoDefaults) -> Self {
+        let labels = repo_config.labels.unwrap_or_default();
+        let remote_only = repo_config.path.is_empty() && repo_config.url.is_some();
+        let path = expand_path(&repo_config.path);
+        RepoStatus {
+            name: repo_config.name,
+            commits: commits::CommitStore::open(&path, defaults.persist_commit_cache && !remote_only),
+            path,
+            remote: repo_config.remote.unwrap_or_else(|| "origin".to_string()),
+            refspecs: repo_config.refspecs.unwrap_or_default(),
+            prune: repo_config.prune.unwrap_or(defaults.prune),
+            url: repo_config.url,
+            description: repo_config.description,
+            labels,
+            ahead: 0,
+            behind: 0,
+            dirty: 0,
+            diffstat: (0, 0),
+            operation: None,
+            conflicts: Vec::new(),
+            current_branch: "unknown".to_string(),
+            counted: None,
+            default_branch: None,
+            upstream_gone: None,
+            renamed_default: None,
+            rename_asked: None,
+            last_update: Instant::now(),
+            expanded: false,
+            recent_commits: Vec::new(),
+            marked: false,
+            muted: false,
+            paused: false,
+            pinned: repo_config.pinned.unwrap_or(false),
+            hidden: false,
+            archived: repo_config.archived.unwrap_or(false),
+            backup: repo_config.backup_remote.map(|remote| BackupStatus {
+                remote,
+                interval: Duration::from_secs(repo_config.backup_interval.unwrap_or(3600)),
+                last_attempt: None,
+                last_success: None,
+                lag: 0,
+                error: None,
+            }),
+            maintenance: repo_config
+                .maintenance_interval
+                .or(defaults.maintenance_interval)
+                .filter(|&seconds| seconds > 0)
+                .map(|seconds| MaintenanceStatus {
+                    interval: Duration::from_secs(seconds),
+                    last_run: None,
+                    running: false,
+                    last_finished: None,
+                    error: None,
+                }),
+            stats: None,
+            stats_checked: None,
+            stats_running: false,
+            size_warning: defaults.size_warning,
+            policy: repo_config
+                .protected_branches
+                .filter(|branches| !branches.is_empty())
+                .map(|protected_branches| PolicyStatus {
+                    protected_branches,
+                    require_signed: repo_config.require_signed.unwrap_or(false),
+                    detect_direct_pushes: repo_config.detect_direct_pushes.unwrap_or(false),
+                    tips: Default::default(),
+                    violations: 0,
+                    direct_pushes: 0,
+                    alerts: Vec::new(),
+                }),
+            pull_requests: repo_config
+                .prs
+                .unwrap_or_default()
+                .into_iter()
+                .map(|number| PullRequestStatus { number, tip: None, last_commit: None, updated: None })
+                .collect(),
+            forge: repo_config.forge,
+            notes: repo_config.notes.unwrap_or(false),
+            light_check: repo_config.check_mode.as_deref().is_some_and(|mode| mode.eq_ignore_ascii_case("ls-remote")),
+            ignore_untracked: repo_config.ignore_untracked.unwrap_or(false),
+            fetch_depth: repo_config.fetch_depth,
+            icons: defaults.icons,
+            expected_email: repo_config.expected_email.or_else(|| defaults.expected_email.clone()),
+            wrong_identity: None,
+            large_file_warning: defaults.large_file_warning,
+            push_warnings: Vec::new(),
+            outgoing_tip: None,
+            incoming_large: Vec::new(),
+            incoming_checked: None,
+            last_error: None,
+            trailer_alerts: repo_config.trailer_alerts.unwrap_or_default(),
+            incoming_tip: None,
+            last_activity: None,
+            last_commit: None,
+            changed_at: None,
+            fetch_times: Vec::new(),
+            transfer: Transfer::default(),
+            logins: defaults.logins.clone(),
+            remote_refs: remote_only.then(RemoteRefs::default),
+            poll_interval: Duration::ZERO,
+            next_check: Instant::now(),
+            checking: false,
+            focused: false,
+            host: None,
+        }
+    }
+    
+    // Write back what check_repo found, leaving alone what changed in the UI or a config reload meanwhile.
+    // `started` is the copy the check began from; fields the UI also sets are only taken when they still match it.
+    fn take_check(&mut self, started: &RepoStatus, checked: RepoStatus) {
+        self.ahead = checked.ahead;
+        self.behind = checked.behind;
+        self.dirty = checked.dirty;
+        self.diffstat = checked.diffstat;
+        self.operation = checked.operation;
+        if self.conflicts == started.conflicts {
+            self.conflicts = checked.conflicts;
+        }
+        self.current_branch = checked.current_branch;
+        self.counted = checked.counted;
+        self.default_branch = checked.default_branch;
+        self.upstream_gone = checked.upstream_gone;
+        if self.renamed_default == started.renamed_default {
+            self.renamed_default = checked.renamed_default;
+        }
+        self.rename_asked = checked.rename_asked;
+        self.last_activity = checked.last_activity;
+        self.last_commit = checked.last_commit;
+        self.changed_at = checked.changed_at;
+        self.fetch_times = checked.fetch_times;
+        if self.remote_refs.is_some() {
+            self.remote_refs = checked.remote_refs;
+        }
+        self.poll_interval = checked.poll_interval;
+        // A refresh requested while the check ran still happens
+        self.next_check = self.next_check.min(checked.next_check);
+        self.checking = false;
+        self.incoming_tip = checked.incoming_tip;
+        self.wrong_identity = checked.wrong_identity;
+        self.push_warnings = checked.push_warnings;
+        self.outgoing_tip = checked.outgoing_tip;
+        self.incoming_large = checked.incoming_large;
+        self.incoming_checked = checked.incoming_checked;
+        self.last_error = checked.last_error;
+        if let (Some(backup), Some(checked_backup)) = (&mut self.backup, checked.backup) {
+            backup.last_attempt = checked_backup.last_attempt;
+            backup.last_success = checked_backup.last_success;
+            backup.lag = checked_backup.lag;
+            backup.error = checked_backup.error;
+        }
+        for pull_request in &mut self.pull_requests {
+            if let Some(checked_pr) = checked.pull_requests.iter().find(|checked_pr| checked_pr.number == pull_request.number) {
+                *pull_request = checked_pr.clone();
+            }
+        }
+        if let (Some(policy), Some(checked_policy)) = (&mut self.policy, checked.policy) {
+            policy.tips = checked_policy.tips;
+            policy.violations = checked_policy.violations;
+            policy.direct_pushes = checked_policy.direct_pushes;
+            policy.alerts = checked_policy.alerts;
+        }
+    }
+    
+    // "+" after a behind count that stopped at a shallow clone's cut-off rather than the merge base
+    fn behind_bound(&self) -> &'static str {
+        if self.counted.is_some_and(|tips| tips.partial) { "+" } else { "" }
+    }
+    
+    // How urgently the repository needs a look: anything broken outweighs any amount of drift, and drift is
+    // capped so one far-behind repository doesn't bury a conflict. 0 means nothing to do.
+    fn attention(&self) -> u32 {
+        let mut score = 0;
+        let broken = [
+            !self.conflicts.is_empty(),
+            self.operation.is_some(),
+            self.last_error.is_some(),
+            self.backup.as_ref().is_some_and(|backup| backup.error.is_some()),
+            self.remote_refs.as_ref().is_some_and(|refs| refs.error.is_some()),
+        ];
+        score += 100 * broken.iter().filter(|&&broken| broken).count() as u32;
+        if let Some(policy) = &self.policy {
+            score += 40 * policy.violations.min(2) as u32;
+        }
+        score += 3 * self.behind.min(20) as u32;
+        score += 2 * self.dirty.min(20) as u32;
+        score += self.ahead.min(10) as u32;
+        // Drift only goes stale once there is some; a quiet, clean repository is fine however old
+        if score > 0
+            && let Some(last_commit) = self.last_commit
+        {
+            score += ((Utc::now() - last_commit).num_weeks().clamp(0, 10)) as u32;
+        }
+        score
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct RefreshPolicy {
+    interval: Duration,
+    adaptive: Option<(Duration, Duration)>, // min/max bounds when polling follows activity
+    focus: Option<Duration>,
+    // Tethered to a phone: intervals METERED_SLOWDOWN times longer, ls-remote before every fetch, only the
+    // checked-out branch fetched, and no notes, pull request refs, backup pushes or maintenance
+    metered: bool,
+}
+
+impl RefreshPolicy {
+    fn from_config(config: &Config) -> Self {
+        RefreshPolicy {
+            interval: Duration::from_secs(config.refresh_interval.max(1)),
+            adaptive: config.adaptive_refresh.as_ref().map(|adaptive| {
+                let min = adaptive.min_interval.unwrap_or(1).max(1);
+                let max = adaptive.max_interval.unwrap_or(600).max(min);
+                (Duration::from_secs(min), Duration::from_secs(max))
+            }),
+            focus: config.focus_interval.map(|secs| Duration::from_secs(secs.max(1))),
+            metered: config.metered.unwrap_or(false),
+        }
+    }
+
+    fn interval_for_repo(&self, repo: &RepoStatus) -> Duration {
+        let interval = self.interval_for(repo.last_activity);
+        let interval = match self.focus {
+            Some(focus) if repo.focused || repo.expanded => interval.min(focus),
+            _ => interval,
+        };
+        self.metered(interval)
+    }
+
+    // The base interval as it applies right now
+    fn current_interval(&self) -> Duration {
+        self.metered(self.interval)
+    }
+
+    fn metered(&self, interval: Duration) -> Duration {
+        if self.metered { interval * METERED_SLOWDOWN } else { interval }
+    }
+
+    // Halve the interval for repos active in the last hour, stretch it for repos idle for days
+    fn interval_for(&self, last_activity: Option<DateTime<Utc>>) -> Duration {
+        let Some((min, max)) = self.adaptive else {
+            return self.interval;
+        };
+        let Some(last_activity) = last_activity else {
+            return self.interval.clamp(min, max);
+        };
+        let idle = Utc::now() - last_activity;
+        let interval = if idle < chrono::Duration::hours(1) {
+            self.interval / 2
+        } else if idle < chrono::Duration::days(1) {
+            self.interval
+        } else if idle < chrono::Duration::days(7) {
+            self.interval * 4
+        } else {
+            self.interval * 12
+        };
+        interval.clamp(min, max)
+    }
+}
+
+impl App {
+    fn new(config: Config, config_path: PathBuf) -> Self {
+        let refresh = RefreshPolicy::from_config(&config);
+        let repo_defaults = RepoDefaults::from_config(&config);
+        let repos: Vec<RepoStatus> = config
+            .repositories
+            .into_iter()
+            .map(|repo_config| RepoStatus::from_config(repo_config, &repo_defaults))
+            .collect();
+
+        let colors = Palette::from_config(config.colors.as_ref());
+        let console_messages = Arc::new(Mutex::new(Vec::new()));
+        let repos = Arc::new(Mutex::new(repos));
+        let mute_labels = config.mute_labels.unwrap_or_default();
+        
+        Self {
+            notifier: Notifier::new(config.notifications.unwrap_or_default(), mute_labels, repos.clone(), console_messages.clone()),
+            repos,
+            console_messages,
+            table_state: TableState::default(),
+            selected: 0,
+            label_filter: None,
+            name_filter: None,
+            sort_key: SortKey::Config,
+            sort_reversed: false,
+            show_hidden: false,
+            show_archived: false,
+            should_quit: false,
+            max_commits: config.max_commits,
+            colors,
+            page_size: 10,
+            popup: None,
+            show_descriptions: config.show_descriptions.unwrap_or(false),
+            show_size: config.show_size.unwrap_or(false),
+            show_fetch_time: config.show_fetch_time.unwrap_or(false),
+            show_diffstat: config.show_diffstat.unwrap_or(false),
+            search_depth: config.search_depth.unwrap_or(SEARCH_DEPTH),
+            author_colors: config.author_colors.unwrap_or(false),
+            console_format: config.console_format.unwrap_or_default(),
+            highlights: config.highlights.unwrap_or_default(),
+            flash: config.flash.as_ref().map(flash_settings),
+            config_path,
+            suspend: None,
+            rebase_command: config.rebase_command,
+            worktree_dir: config.worktree_dir,
+            repo_defaults,
+            refresh: watch::Sender::new(refresh),
+            wake_monitor: Arc::new(Notify::new()),
+            focus: None,
+            console_selected: None,
+            console_filter: ConsoleFilter::All,
+            console_rows: (Rect::default(), Vec::new()),
+            header_cells: Vec::new(),
+            command_line: None,
+            command_error: None,
+            layout: config.layout.as_deref().and_then(LayoutMode::from_name).unwrap_or_default(),
+            side_pane: None,
+            search_results: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    fn handle_key(&mut self, key: KeyCode) {
+        self.command_error = None;
+        if let Some(popup) = self.popup.take() {
+            self.handle_popup_key(popup, key);
+            return;
+        }
+        if let Some(input) = self.command_line.take() {
+            self.handle_command_key(input, key);
+            return;
+        }
+        if let Some(selected) = self.console_selected {
+            self.handle_console_key(selected, key);
+            return;
+        }
+        
+        match key {
+            KeyCode::Char('q') => self.should_quit = true,
+            KeyCode::Down => self.next(),
+            KeyCode::Up => self.previous(),
+            KeyCode::PageDown => self.page_down(),
+            KeyCode::PageUp => self.page_up(),
+            KeyCode::Home => self.first(),
+            KeyCode::End => self.last(),
+            // The split layout's pane already shows what expanding would
+            KeyCode::Enter if self.layout == LayoutMode::Split => self.show_detail(),
+            KeyCode::Enter => self.toggle_expand(),
+            KeyCode::Char(' ') => self.toggle_mark(),
+            KeyCode::Esc => self.clear_marks(),
+            KeyCode::Tab => self.console_selected = Some(0),
+            KeyCode::Char('F') => self.cycle_console_filter(),
+            KeyCode::Char('f') => self.run_batch_action(BatchAction::Fetch),
+            KeyCode::Char('p') => self.run_batch_action(BatchAction::Pull),
+            KeyCode::Char('m') => self.run_batch_action(BatchAction::Mute),
+            KeyCode::Char('o') => self.run_batch_action(BatchAction::Open),
+            KeyCode::Char('P') => self.confirm_pull_all(),
+            KeyCode::Char('i') => self.show_detail(),
+            KeyCode::Char('c') => self.show_commit(),
+            KeyCode::Char('v') => self.show_changelog(false),
+            KeyCode::Char('u') => self.show_changelog(true),
+            KeyCode::Char('t') => self.prompt_tag(),
+            KeyCode::Char('d') => self.show_divergence(),
+            KeyCode::Char('D') => self.show_pull_preview(),
+            KeyCode::Char('g') => self.show_graph(),
+            KeyCode::Char('T') => self.show_tree(),
+            KeyCode::Char('S') => self.show_work_tree(),
+            KeyCode::Char('X') => self.show_conflicts(),
+            KeyCode::Char('M') => self.show_remote_matrix(),
+            KeyCode::Char('R') => self.confirm_retarget_upstream(),
+            KeyCode::Char('/') => self.prompt_search_everywhere(),
+            KeyCode::Char('b') => self.show_branches(),
+            KeyCode::Char('w') => self.prompt_worktree(),
+            KeyCode::Char('B') => self.confirm_cleanup_branches(),
+            KeyCode::Char('l') => self.cycle_label_filter(),
+            KeyCode::Char('s') => self.cycle_sort(),
+            KeyCode::Char('*') => self.toggle_pin(),
+            KeyCode::Char('h') => self.toggle_hidden(),
+            KeyCode::Char('H') => self.toggle_show_hidden(),
+            KeyCode::Char('z') => self.toggle_archived(),
+            KeyCode::Char('Z') => self.toggle_show_archived(),
+            KeyCode::Char('e') => self.suspend = Some(SuspendAction::EditConfig),
+            KeyCode::Char('C') => self.request_clone_missing(),
+            KeyCode::Char('+') => self.step_refresh_interval(true),
+            KeyCode::Char('-') => self.step_refresh_interval(false),
+            KeyCode::Char('W') => self.save_refresh_interval(),
+            KeyCode::Char('N') => self.set_metered(None),
+            // Adding a repository was on '+' until '+'/'-' took over the refresh interval
+            KeyCode::Char('a') => {
+                let prompt = Prompt::new("Add Repository - Path", String::new(), PromptAction::AddRepoPath);
+                self.popup = Some(Popup::Prompt(prompt));
+            }
+            KeyCode::Char(':') => self.command_line = Some(String::new()),
+            KeyCode::Char(digit @ '1'..='9') => {
+                if let Some(&(_, key)) = self.table_columns().get(digit as usize - '1' as usize) {
+                    self.sort_by_column(key);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Like vim's command line: Backspace on an empty line leaves it as Esc does
+    fn handle_command_key(&mut self, mut input: String, key: KeyCode) {
+        match key {
+            KeyCode::Esc => return,
+            KeyCode::Enter => {
+                if let Err(err) = self.run_command(input.trim()) {
+                    self.command_error = Some(err.to_string());
+                }
+                return;
+            }
+            KeyCode::Backspace if input.is_empty() => return,
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            KeyCode::Tab => input = self.complete_command(&input),
+            KeyCode::Char(c) => input.push(c),
+            _ => {}
+        }
+        self.command_line = Some(input);
+    }
+
+    // Fills in the command, sort key, label or repository name being typed as far as the candidates agree
+    fn complete_command(&self, input: &str) -> String {
+        let Some((command, partial)) = input.split_once(' ') else {
+            return complete(input, COMMANDS.iter().map(|command| command.to_string()).collect());
+        };
+        let repos = self.repos.lock().unwrap();
+        let candidates: Vec<String> = match command {
+            "sort" => SORT_KEYS.iter().map(|key| key.to_string()).collect(),
+            "layout" => vec!["table".to_string(), "split".to_string()],
+            "metered" => vec!["on".to_string(), "off".to_string()],
+            "label" => repos.iter().flat_map(|repo| repo.labels.clone()).collect(),
+            "filter" => return input.to_string(),
+            _ => repos.iter().filter(|repo| repo.host.is_none()).map(|repo| repo.name.clone()).collect(),
+        };
+        format!("{} {}", command, complete(partial, candidates))
+    }
+
+    // Commands from the `:` line, see COMMANDS; a repository argument is a configured name or a path inside it,
+    // and without one the marked or selected repositories are meant, as with the keys
+    fn run_command(&mut self, input: &str) -> Result<()> {
+        let (command, argument) = input.split_once(char::is_whitespace).unwrap_or((input, ""));
+        let argument = argument.trim();
+        let batch = match command {
+            "" => return Ok(()),
+            "q" | "quit" => {
+                self.should_quit = true;
+                return Ok(());
+            }
+            "filter" => {
+                self.name_filter = (!argument.is_empty()).then(|| argument.to_string());
+                return Ok(());
+            }
+            "label" => {
+                self.label_filter = (!argument.is_empty()).then(|| argument.to_string());
+                return Ok(());
+            }
+            "sort" => {
+                self.sort_key = SortKey::from_name(argument).ok_or_else(|| anyhow::anyhow!("Sort by one of: {}", SORT_KEYS.join(", ")))?;
+                self.sort_reversed = false;
+                return Ok(());
+            }
+            "metered" => {
+                let on = match argument {
+                    "" => None,
+                    "on" => Some(true),
+                    "off" => Some(false),
+                    _ => anyhow::bail!("Metered is on or off, or nothing to switch it"),
+                };
+                self.set_metered(on);
+                return Ok(());
+            }
+            "layout" => {
+                let layout = LayoutMode::from_name(argument).ok_or_else(|| anyhow::anyhow!("Layout is table or split"))?;
+                self.set_layout(layout);
+                return Ok(());
+            }
+            "refresh" => None,
+            "fetch" => Some(BatchAction::Fetch),
+            "pull" => Some(BatchAction::Pull),
+            "mute" => Some(BatchAction::Mute),
+            "open" => Some(BatchAction::Open),
+            _ => anyhow::bail!("Unknown command \"{}\", expected one of: {}", command, COMMANDS.join(", ")),
+        };
+
+        let mut repos = self.repos.lock().unwrap();
+        let targets = if argument.is_empty() {
+            self.action_targets(&repos)
+        } else {
+            let target = expand_path(argument).display().to_string();
+            vec![control::find_repo(&repos, &target).ok_or_else(|| anyhow::anyhow!("No monitored repository \"{}\"", argument))?]
+        };
+        match batch {
+            Some(action) => self.run_action(&mut repos, action, targets),
+            None => {
+                for index in targets {
+                    let repo = &mut repos[index];
+                    if repo.paused {
+                        anyhow::bail!("{} is paused", repo.name);
+                    }
+                    repo.next_check = Instant::now();
+                    if !repo.muted {
+                        log_console(&self.console_messages, &repo.name, "GitOp", EventKind::Action, "Refresh requested".to_string());
+                    }
+                }
+                drop(repos);
+                self.wake_monitor.notify_one();
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_console_key(&mut self, selected: usize, key: KeyCode) {
+        let count = {
+            let repos = self.repos.lock().unwrap();
+            self.console_view(&self.console_messages.lock().unwrap(), &repos).len()
+        };
+        match key {
+            KeyCode::Down => self.console_selected = Some((selected + 1).min(count.saturating_sub(1))),
+            KeyCode::Up => self.console_selected = Some(selected.saturating_sub(1)),
+            KeyCode::Enter => self.jump_to_message(selected),
+            KeyCode::Tab | KeyCode::Esc => self.console_selected = None,
+            KeyCode::Char('q') => self.should_quit = true,
+            _ => {}
+        }
+    }
+    
+    fn handle_mouse(&mut self, mouse: MouseEvent) {
+        if self.popup.is_some() || mouse.kind != MouseEventKind::Down(MouseButton::Left) {
+            return;
+        }
+        let clicked = |area: &Rect| (area.x..area.x + area.width).contains(&mouse.column) && (area.y..area.y + area.height).contains(&mouse.row);
+        if let Some(&(_, key)) = self.header_cells.iter().find(|(area, _)| clicked(area)) {
+            self.sort_by_column(key);
+            return;
+        }
+        let (area, rows) = &self.console_rows;
+        if mouse.column < area.x || mouse.column >= area.x + area.width || mouse.row < area.y {
+            return;
+        }
+        if let Some(&message) = rows.get((mouse.row - area.y) as usize) {
+            self.console_selected = Some(message);
+            self.jump_to_message(message);
+        }
+    }
+    
+    // Newest console messages that are shown, as indexes into the console, newest first
+    fn console_view(&self, messages: &[ConsoleMessage], repos: &[RepoStatus]) -> Vec<usize> {
+        let selected = match self.console_filter {
+            ConsoleFilter::Only => self.get_selected_repo_index(repos).map(|index| console_name(&repos[index])),
+            _ => None,
+        };
+        (0..messages.len())
+            .rev()
+            .filter(|&index| selected.as_ref().is_none_or(|name| messages[index].repo == *name))
+            .take(CONSOLE_LINES)
+            .collect()
+    }
+    
+    fn cycle_console_filter(&mut self) {
+        self.console_filter = match self.console_filter {
+            ConsoleFilter::All => ConsoleFilter::Dim,
+            ConsoleFilter::Dim => ConsoleFilter::Only,
+            ConsoleFilter::Only => ConsoleFilter::All,
+        };
+        self.console_selected = self.console_selected.map(|_| 0);
+    }
+    
+    // Select and expand the repository a console message is about
+    fn jump_to_message(&mut self, position: usize) {
+        let repos = self.repos.lock().unwrap();
+        let name = {
+            let messages = self.console_messages.lock().unwrap();
+            let Some(&message) = self.console_view(&messages, &repos).get(position) else {
+                return;
+            };
+            messages[message].repo.clone()
+        };
+        let Some(index) = repos.iter().position(|repo| console_name(repo) == name) else {
+            return;
+        };
+        // Make sure the table shows it
+        if !self.display_order(&repos).contains(&index) {
+            self.label_filter = None;
+            self.show_hidden |= repos[index].hidden;
+            self.show_archived |= repos[index].archived;
+        }
+        let expanded = repos[index].expanded;
+        drop(repos);
+        self.selected = index;
+        if !expanded && self.layout == LayoutMode::Table {
+            self.toggle_expand();
+        }
+    }
+    
+    fn handle_popup_key(&mut self, popup: Popup, key: KeyCode) {
+        match (popup, key) {
+            (Popup::Prompt(prompt), key) => self.handle_prompt_key(prompt, key),
+            (Popup::PullAll { targets, .. }, KeyCode::Char('y') | KeyCode::Enter) => self.pull_all(targets),
+            (Popup::CloneMissing(targets), KeyCode::Char('y') | KeyCode::Enter) => self.clone_missing(targets),
+            (Popup::PushTag { repo, name }, KeyCode::Char('y') | KeyCode::Enter) => self.push_tag(repo, name),
+            (Popup::CheckoutBranch { repo, name }, KeyCode::Char('y') | KeyCode::Enter) => self.checkout_branch(repo, name),
+            (Popup::CleanupBranches { repo, branches, .. }, KeyCode::Char('y') | KeyCode::Enter) => self.cleanup_branches(repo, branches),
+            (Popup::RetargetUpstream { repo, old, new }, KeyCode::Char('y') | KeyCode::Enter) => self.retarget_upstream(repo, old, new),
+            (Popup::Commit(detail), KeyCode::Char('b')) => self.prompt_branch(detail.repo, detail.hash),
+            (Popup::Changelog(changelog), KeyCode::Char('b')) => match changelog.selected_commit() {
+                Some(commit) => self.prompt_branch(changelog.repo, commit.hash.clone()),
+                None => self.popup = Some(Popup::Changelog(changelog)),
+            },
+            (Popup::Diverged(repo_index), KeyCode::Char('r')) => {
+                let repos = self.repos.lock().unwrap();
+                if let Some(repo) = repos.get(repo_index) {
+                    let upstream = format!("{}/{}", repo.remote, repo.current_branch);
+                    self.suspend = Some(SuspendAction::Rebase { repo: repo_index, upstream });
+                }
+            }
+            (Popup::Welcome, KeyCode::Char('c')) => {
+                match write_config(&self.config_path, &Config::default()) {
+                    Ok(()) => log_console(
+                        &self.console_messages,
+                        "System",
+                        "GitOp",
+                        EventKind::Action,
+                        format!("Created default config at {} (press e to edit)", self.config_path.display()),
+                    ),
+                    Err(err) => log_console(&self.console_messages, "System", "System", EventKind::Action, format!("Could not create config: {}", err)),
+                }
+            }
+            (Popup::Welcome, KeyCode::Char('s')) => {
+                let prompt = Prompt::new("Scan Directory for Repositories", "~".to_string(), PromptAction::ScanDirectory);
+                self.popup = Some(Popup::Prompt(prompt));
+            }
+            (Popup::Welcome, KeyCode::Char('d')) => {
+                if let Err(err) = open_path(DOCS_URL) {
+                    log_console(&self.console_messages, "System", "System", EventKind::Action, format!("Could not open {}: {}", DOCS_URL, err));
+                }
+                self.popup = Some(Popup::Welcome);
+            }
+            (Popup::Detail { .. }, KeyCode::Char('i') | KeyCode::Enter) => {}
+            (Popup::Commit(detail), KeyCode::Esc) if detail.search.is_some() => {
+                self.popup = detail.search.map(|search| Popup::CommitSearch(*search));
+            }
+            (Popup::Commit(detail), key @ (KeyCode::Down | KeyCode::Up)) if detail.search.is_some() => {
+                let mut search = *detail.search.unwrap();
+                search.selected = match key {
+                    KeyCode::Down => (search.selected + 1).min(search.hits.len().saturating_sub(1)),
+                    _ => search.selected.saturating_sub(1),
+                };
+                self.show_search_hit(search);
+            }
+            (Popup::Commit(detail), KeyCode::Char('/')) => self.prompt_search(detail.repo),
+            (Popup::Changelog(changelog), KeyCode::Char('/')) => self.prompt_search(changelog.repo),
+            (Popup::CommitSearch(search), KeyCode::Char('/')) => match search.repo {
+                Some(repo_index) => self.prompt_search(repo_index),
+                None => self.prompt_search_everywhere(),
+            },
+            (Popup::CommitSearch(mut search), key @ (KeyCode::Down | KeyCode::Up | KeyCode::PageDown | KeyCode::PageUp | KeyCode::Home | KeyCode::End)) => {
+                let last = search.hits.len().saturating_sub(1);
+                search.selected = match key {
+                    KeyCode::Down => (search.selected + 1).min(last),
+                    KeyCode::Up => search.selected.saturating_sub(1),
+                    KeyCode::PageDown => (search.selected + self.page_size).min(last),
+                    KeyCode::PageUp => search.selected.saturating_sub(self.page_size),
+                    KeyCode::Home => 0,
+                    _ => last,
+                };
+                self.popup = Some(Popup::CommitSearch(search));
+            }
+            (Popup::CommitSearch(search), KeyCode::Enter) => self.show_search_hit(search),
+            (Popup::Commit(detail), KeyCode::Down | KeyCode::Up) => {
+                let index = match key {
+                    KeyCode::Down => detail.index + 1,
+                    _ => detail.index.saturating_sub(1),
+                };
+                let repos = self.repos.lock().unwrap();
+                let next = repos.get(detail.repo).and_then(|repo| load_commit_detail(repo, detail.repo, index));
+                self.popup = Some(Popup::Commit(next.unwrap_or(detail)));
+            }
+            (Popup::Commit(mut detail), key @ (KeyCode::Left | KeyCode::Right)) => {
+                let last = detail.files.len().saturating_sub(1);
+                detail.selected_file = match key {
+                    KeyCode::Right => (detail.selected_file + 1).min(last),
+                    _ => detail.selected_file.saturating_sub(1),
+                };
+                self.popup = Some(Popup::Commit(detail));
+            }
+            (Popup::Commit(detail), KeyCode::Char('a')) => self.show_blame(detail),
+            (Popup::Commit(detail), KeyCode::Char('t')) => {
+                let repos = self.repos.lock().unwrap();
+                self.popup = Some(match repos.get(detail.repo).map(|repo| open_tree(repo, detail.repo, detail.hash.clone(), None)) {
+                    Some(Ok(tree)) => Popup::Tree(tree),
+                    _ => Popup::Commit(detail),
+                });
+            }
+            (Popup::Tree(mut tree), key @ (KeyCode::Down | KeyCode::Up | KeyCode::PageDown | KeyCode::PageUp | KeyCode::Home | KeyCode::End)) => {
+                let last = tree.entries.len().saturating_sub(1);
+                tree.selected = match key {
+                    KeyCode::Down => (tree.selected + 1).min(last),
+                    KeyCode::Up => tree.selected.saturating_sub(1),
+                    KeyCode::PageDown => (tree.selected + self.page_size).min(last),
+                    KeyCode::PageUp => tree.selected.saturating_sub(self.page_size),
+                    KeyCode::Home => 0,
+                    _ => last,
+                };
+                self.popup = Some(Popup::Tree(tree));
+            }
+            (Popup::Tree(tree), KeyCode::Enter | KeyCode::Right) => self.open_tree_entry(tree),
+            (Popup::Tree(tree), KeyCode::Left | KeyCode::Backspace) => {
+                // Back to the parent directory, with the directory we came from selected
+                let Some(current) = tree.dir.strip_suffix('/') else {
+                    self.popup = Some(Popup::Tree(tree));
+                    return;
+                };
+                let (parent, name) = match current.rsplit_once('/') {
+                    Some((parent, name)) => (format!("{}/", parent), name.to_string()),
+                    None => (String::new(), current.to_string()),
+                };
+                let rev = tree.rev.clone();
+                self.load_tree_dir(tree, rev, parent, Some(&name));
+            }
+            (Popup::Tree(tree), KeyCode::Char('u')) => {
+                let Some(other) = tree.other_rev.clone() else {
+                    self.popup = Some(Popup::Tree(tree));
+                    return;
+                };
+                let dir = tree.dir.clone();
+                self.load_tree_dir(tree, other, dir, None);
+            }
+            (Popup::FilePreview { tree, file, lines, scroll }, key @ (KeyCode::Down | KeyCode::Up | KeyCode::PageDown | KeyCode::PageUp | KeyCode::Home | KeyCode::End)) => {
+                let last = lines.len().saturating_sub(1);
+                let scroll = match key {
+                    KeyCode::Down => (scroll + 1).min(last),
+                    KeyCode::Up => scroll.saturating_sub(1),
+                    KeyCode::PageDown => (scroll + self.page_size).min(last),
+                    KeyCode::PageUp => scroll.saturating_sub(self.page_size),
+                    KeyCode::Home => 0,
+                    _ => last,
+                };
+                self.popup = Some(Popup::FilePreview { tree, file, lines, scroll });
+            }
+            (Popup::FilePreview { tree, .. }, KeyCode::Esc | KeyCode::Left | KeyCode::Backspace) => self.popup = Some(Popup::Tree(tree)),
+            (Popup::Commit(_), KeyCode::Char('c') | KeyCode::Enter) => {}
+            (Popup::Blame { commit, file, lines, scroll }, key @ (KeyCode::Down | KeyCode::Up | KeyCode::PageDown | KeyCode::PageUp | KeyCode::Home | KeyCode::End)) => {
+                let last = lines.len().saturating_sub(1);
+                let scroll = match key {
+                    KeyCode::Down => (scroll + 1).min(last),
+                    KeyCode::Up => scroll.saturating_sub(1),
+                    KeyCode::PageDown => (scroll + self.page_size).min(last),
+                    KeyCode::PageUp => scroll.saturating_sub(self.page_size),
+                    KeyCode::Home => 0,
+                    _ => last,
+                };
+                self.popup = Some(Popup::Blame { commit, file, lines, scroll });
+            }
+            (Popup::Blame { commit, .. }, KeyCode::Esc | KeyCode::Char('a')) => self.popup = Some(Popup::Commit(commit)),
+            (Popup::Graph { repo, lines, scroll }, key @ (KeyCode::Down | KeyCode::Up | KeyCode::PageDown | KeyCode::PageUp | KeyCode::Home | KeyCode::End)) => {
+                let last = lines.len().saturating_sub(1);
+                let scroll = match key {
+                    KeyCode::Down => (scroll + 1).min(last),
+                    KeyCode::Up => scroll.saturating_sub(1),
+                    KeyCode::PageDown => (scroll + self.page_size).min(last),
+                    KeyCode::PageUp => scroll.saturating_sub(self.page_size),
+                    KeyCode::Home => 0,
+                    _ => last,
+                };
+                self.popup = Some(Popup::Graph { repo, lines, scroll });
+            }
+            (Popup::Graph { .. }, KeyCode::Char('g') | KeyCode::Enter) => {}
+            (Popup::WorkTree { repo, lines, scroll }, key @ (KeyCode::Down | KeyCode::Up | KeyCode::PageDown | KeyCode::PageUp | KeyCode::Home | KeyCode::End)) => {
+                let last = lines.len().saturating_sub(1);
+                let scroll = match key {
+                    KeyCode::Down => (scroll + 1).min(last),
+                    KeyCode::Up => scroll.saturating_sub(1),
+                    KeyCode::PageDown => (scroll + self.page_size).min(last),
+                    KeyCode::PageUp => scroll.saturating_sub(self.page_size),
+                    KeyCode::Home => 0,
+                    _ => last,
+                };
+                self.popup = Some(Popup::WorkTree { repo, lines, scroll });
+            }
+            (Popup::WorkTree { .. }, KeyCode::Char('S') | KeyCode::Enter) => {}
+            (Popup::Conflicts { repo, lines, scroll }, key @ (KeyCode::Down | KeyCode::Up | KeyCode::PageDown | KeyCode::PageUp | KeyCode::Home | KeyCode::End)) => {
+                let last = lines.len().saturating_sub(1);
+                let scroll = match key {
+                    KeyCode::Down => (scroll + 1).min(last),
+                    KeyCode::Up => scroll.saturating_sub(1),
+                    KeyCode::PageDown => (scroll + self.page_size).min(last),
+                    KeyCode::PageUp => scroll.saturating_sub(self.page_size),
+                    KeyCode::Home => 0,
+                    _ => last,
+                };
+                self.popup = Some(Popup::Conflicts { repo, lines, scroll });
+            }
+            (Popup::Conflicts { .. }, KeyCode::Char('X') | KeyCode::Enter) => {}
+            (Popup::RemoteMatrix { .. }, KeyCode::Char('M') | KeyCode::Enter) => {}
+            (Popup::Branches { .. }, KeyCode::Char('b') | KeyCode::Enter) => {}
+            (Popup::Changelog(mut changelog), key @ (KeyCode::Down | KeyCode::Up | KeyCode::PageDown | KeyCode::PageUp | KeyCode::Char('g'))) => {
+                let last = changelog.commits.len().saturating_sub(1);
+                match key {
+                    KeyCode::Down => changelog.selected = (changelog.selected + 1).min(last),
+                    KeyCode::Up => changelog.selected = changelog.selected.saturating_sub(1),
+                    KeyCode::PageDown => changelog.selected = (changelog.selected + self.page_size).min(last),
+                    KeyCode::PageUp => changelog.selected = changelog.selected.saturating_sub(self.page_size),
+                    _ => {
+                        changelog.by_author = !changelog.by_author;
+                        changelog.selected = 0;
+                    }
+                }
+                self.popup = Some(Popup::Changelog(changelog));
+            }
+            (Popup::Changelog(changelog), KeyCode::Char('p')) if !changelog.outgoing => {
+                if let Some(commit) = changelog.selected_commit() {
+                    self.apply_commit(changelog.repo, CommitAction::CherryPick, commit.hash.clone());
+                }
+                self.popup = Some(Popup::Changelog(changelog));
+            }
+            (Popup::Changelog(changelog), KeyCode::Char('r')) if changelog.outgoing => {
+                self.popup = match changelog.selected_commit() {
+                    Some(commit) => Some(Popup::ConfirmRevert { changelog: changelog.clone(), commit: commit.clone() }),
+                    None => Some(Popup::Changelog(changelog)),
+                };
+            }
+            (Popup::ConfirmRevert { changelog, commit }, KeyCode::Char('y') | KeyCode::Enter) => {
+                self.apply_commit(changelog.repo, CommitAction::Revert, commit.hash);
+            }
+            (Popup::ConfirmRevert { changelog, .. }, KeyCode::Char('n') | KeyCode::Esc) => self.popup = Some(Popup::Changelog(changelog)),
+            (Popup::Changelog(changelog), KeyCode::Char('x')) => {
+                self.export_changelog(&changelog);
+                self.popup = Some(Popup::Changelog(changelog));
+            }
+            (Popup::Changelog(_), KeyCode::Char('v' | 'u') | KeyCode::Enter) => {}
+            (_, KeyCode::Char('n') | KeyCode::Esc | KeyCode::Char('q')) => {}
+            (popup, _) => self.popup = Some(popup), // Ignore other keys while a popup is open
+        }
+    }
+
+    fn handle_prompt_key(&mut self, mut prompt: Prompt, key: KeyCode) {
+        match key {
+            KeyCode::Esc => return,
+            KeyCode::Enter => {
+                match self.submit_prompt(&prompt) {
+                    Ok(next) => self.popup = next,
+                    Err(err) => {
+                        prompt.error = Some(err.to_string());
+                        self.popup = Some(Popup::Prompt(prompt));
+                    }
+                }
+                return;
+            }
+            KeyCode::Backspace => {
+                prompt.input.pop();
+            }
+            KeyCode::Char(c) => prompt.input.push(c),
+            _ => {}
+        }
+        prompt.error = None;
+        self.popup = Some(Popup::Prompt(prompt));
+    }
+
+    // Returns the follow-up prompt or confirmation, if any
+    fn submit_prompt(&mut self, prompt: &Prompt) -> Result<Option<Popup>> {
+        let input = prompt.input.trim();
+        match &prompt.action {
+            PromptAction::AddRepoPath => {
+                let path = expand_path(input);
+                Repository::open(&path)
+                    .map_err(|_| anyhow::anyhow!("Not a git repository: {}", path.display()))?;
+                
+                let canonical = path.canonicalize().unwrap_or(path);
+                let repos = self.repos.lock().unwrap();
+                if let Some(existing) = repos
+                    .iter()
+                    .find(|repo| repo.path.canonicalize().is_ok_and(|p| p == canonical))
+                {
+                    anyhow::bail!("Already monitored as \"{}\"", existing.name);
+                }
+                
+                // Suggest the directory name as the display name
+                let default_name = canonical
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                Ok(Some(Popup::Prompt(Prompt::new(
+                    "Add Repository - Name",
+                    default_name,
+                    PromptAction::AddRepoName { path: input.to_string() },
+                ))))
+            }
+            PromptAction::ScanDirectory => {
+                let root = expand_path(input);
+                let repositories = scan_for_repositories(&root, 4);
+                if repositories.is_empty() {
+                    anyhow::bail!("No git repositories found under {}", root.display());
+                }
+                
+                let config = Config {
+                    repositories,
+                    ..Config::default()
+                };
+                write_config(&self.config_path, &config)?;
+                self.reload_config();
+                Ok(None)
+            }
+            PromptAction::AddRepoName { path } => {
+                if input.is_empty() {
+                    anyhow::bail!("Name cannot be empty");
+                }
+                
+                let repo_config = RepoConfig {
+                    name: input.to_string(),
+                    path: path.clone(),
+                    remote: Some("origin".to_string()),
+                    ..Default::default()
+                };
+                append_repos_to_config(&self.config_path, std::slice::from_ref(&repo_config))?;
+                
+                let mut repos = self.repos.lock().unwrap();
+                repos.push(RepoStatus::from_config(repo_config, &self.repo_defaults));
+                self.selected = repos.len() - 1;
+                log_console(
+                    &self.console_messages,
+                    input,
+                    "GitOp",
+                    EventKind::Action,
+                    format!("Added repository, saved to {}", self.config_path.display()),
+                );
+                Ok(None)
+            }
+            PromptAction::TagName { repo } => {
+                if input.is_empty() || !git2::Reference::is_valid_name(&format!("refs/tags/{}", input)) {
+                    anyhow::bail!("Not a valid tag name: \"{}\"", input);
+                }
+                let repos = self.repos.lock().unwrap();
+                let path = &repos.get(*repo).context("Repository is gone")?.path;
+                if Repository::open(path)?.find_reference(&format!("refs/tags/{}", input)).is_ok() {
+                    anyhow::bail!("Tag {} already exists", input);
+                }
+                Ok(Some(Popup::Prompt(Prompt::new(
+                    &format!("Create Tag {} - Message", input),
+                    format!("Release {}", input),
+                    PromptAction::TagMessage { repo: *repo, name: input.to_string() },
+                ))))
+            }
+            PromptAction::TagMessage { repo, name } => {
+                if input.is_empty() {
+                    anyhow::bail!("An annotated tag needs a message");
+                }
+                let repos = self.repos.lock().unwrap();
+                let status = repos.get(*repo).context("Repository is gone")?;
+                let target = create_tag(&status.path, name, input)?;
+                log_console(&self.console_messages, &status.name, "GitOp", EventKind::Action, format!("Created tag {} at {:.8}", name, target));
+                Ok(Some(Popup::PushTag { repo: *repo, name: name.clone() }))
+            }
+            PromptAction::WorktreeBranch { repo } => {
+                if input.is_empty() {
+                    anyhow::bail!("Branch cannot be empty");
+                }
+                let mut repos = self.repos.lock().unwrap();
+                let parent = repos.get(*repo).context("Repository is gone")?;
+                let pattern = self.worktree_dir.as_deref().unwrap_or("{path}-{branch}");
+                let path = pattern
+                    .replace("{path}", &parent.path.display().to_string())
+                    .replace("{name}", &parent.name)
+                    .replace("{branch}", &input.replace('/', "-"));
+                create_worktree(&parent.path, &parent.remote, input, &expand_path(&path))?;
+                
+                let repo_config = RepoConfig {
+                    name: format!("{} ({})", parent.name, input),
+                    path,
+                    remote: Some(parent.remote.clone()),
+                    labels: (!parent.labels.is_empty()).then(|| parent.labels.clone()),
+                    ..Default::default()
+                };
+                append_repos_to_config(&self.config_path, std::slice::from_ref(&repo_config))?;
+                log_console(
+                    &self.console_messages,
+                    &repo_config.name,
+                    "GitOp",
+                    EventKind::Action,
+                    format!("Created worktree at {}, saved to {}", repo_config.path, self.config_path.display()),
+                );
+                repos.push(RepoStatus::from_config(repo_config, &self.repo_defaults));
+                self.selected = repos.len() - 1;
+                Ok(None)
+            }
+            PromptAction::BranchName { repo, commit } => {
+                if input.is_empty() || !git2::Branch::name_is_valid(input)? {
+                    anyhow::bail!("Not a valid branch name: \"{}\"", input);
+                }
+                let repos = self.repos.lock().unwrap();
+                let status = repos.get(*repo).context("Repository is gone")?;
+                let git_repo = Repository::open(&status.path)?;
+                let target = git_repo.revparse_single(commit)?.peel_to_commit()?;
+                git_repo.branch(input, &target, false)?;
+                log_console(&self.console_messages, &status.name, "GitOp", EventKind::Action, format!("Created branch {} at {:.8}", input, target.id()));
+                Ok(Some(Popup::CheckoutBranch { repo: *repo, name: input.to_string() }))
+            }
+            PromptAction::SearchCommits { repo } => {
+                if input.is_empty() {
+                    anyhow::bail!("Type part of a message, an author or a hash");
+                }
+                let repos = self.repos.lock().unwrap();
+                let status = repos.get(*repo).context("Repository is gone")?;
+                let hits = search_commits(*repo, &status.path, &status.remote, input, self.search_depth, &status.commits)?;
+                if hits.is_empty() {
+                    anyhow::bail!("Nothing matches in the last {} commits of HEAD and {}/{}", self.search_depth, status.remote, status.current_branch);
+                }
+                Ok(Some(Popup::CommitSearch(CommitSearch { repo: Some(*repo), query: input.to_string(), hits, selected: 0, searching: false })))
+            }
+            PromptAction::SearchEverywhere => {
+                if input.is_empty() {
+                    anyhow::bail!("Type part of a message, an author or a hash");
+                }
+                let targets: Vec<(usize, String, PathBuf, String, CommitCache)> = self
+                    .repos
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, repo)| repo.host.is_none() && repo.remote_refs.is_none())
+                    .map(|(index, repo)| (index, repo.name.clone(), repo.path.clone(), repo.remote.clone(), repo.commits.clone()))
+                    .collect();
+                
+                // The popup opens right away and collect_search fills it in when every repository was searched.
+                // One thread per repository; each opens its own handle, git2 repositories aren't shared across threads.
+                let depth = self.search_depth;
+                let query = input.to_string();
+                let console_messages = self.console_messages.clone();
+                let search_results = self.search_results.clone();
+                tokio::task::spawn_blocking(move || {
+                    let results: Vec<(String, Result<Vec<SearchHit>>)> = std::thread::scope(|scope| {
+                        let searches: Vec<_> = targets
+                            .iter()
+                            .map(|(index, name, path, remote, cache)| {
+                                (name, scope.spawn(|| search_commits(*index, path, remote, &query, depth, cache)))
+                            })
+                            .collect();
+                        searches
+                            .into_iter()
+                            .map(|(name, search)| (name.clone(), search.join().unwrap_or_else(|_| Err(anyhow::anyhow!("search panicked")))))
+                            .collect()
+                    });
+                    
+                    let mut hits = Vec::new();
+                    for (name, result) in results {
+                        match result {
+                            Ok(found) => hits.extend(found),
+                            Err(err) => log_console(&console_messages, &name, "System", EventKind::Action, format!("Search failed: {}", err)),
+                        }
+                    }
+                    *search_results.lock().unwrap() = Some(CommitSearch { repo: None, query, hits, selected: 0, searching: false });
+                });
+                Ok(Some(Popup::CommitSearch(CommitSearch { repo: None, query: input.to_string(), hits: Vec::new(), selected: 0, searching: true })))
+            }
+        }
+    }
+
+    fn suspend_command(&self, action: &SuspendAction) -> Result<std::process::Command> {
+        match action {
+            SuspendAction::EditConfig => {
+                if !self.config_path.exists() {
+                    create_default_config(&self.config_path)?;
+                }
+                
+                let editor = std::env::var("VISUAL")
+                    .or_else(|_| std::env::var("EDITOR"))
+                    .unwrap_or_else(|_| if cfg!(windows) { "notepad" } else { "vi" }.to_string());
+                // Allow editors with arguments, e.g. EDITOR="code --wait"
+                let mut parts = editor.split_whitespace();
+                let mut command = std::process::Command::new(parts.next().unwrap_or("vi"));
+                command.args(parts).arg(&self.config_path);
+                Ok(command)
+            }
+            SuspendAction::Rebase { repo, upstream } => {
+                let repos = self.repos.lock().unwrap();
+                let path = &repos.get(*repo).context("Repository is gone")?.path;
+                let template = self.rebase_command.as_deref().unwrap_or("git rebase -i {upstream}");
+                let words = shell_words(template).context("rebase_command has an unclosed quote")?;
+                let mut parts = words.iter().map(|part| part.replace("{upstream}", upstream));
+                let mut command = std::process::Command::new(parts.next().context("rebase_command is empty")?);
+                command.args(parts).current_dir(path);
+                Ok(command)
+            }
+        }
+    }
+
+    fn resume(&mut self, action: SuspendAction, status: Result<std::process::ExitStatus>) {
+        match (action, status) {
+            (SuspendAction::EditConfig, Ok(_)) => self.reload_config(),
+            (SuspendAction::EditConfig, Err(err)) => {
+                log_console(&self.console_messages, "System", "System", EventKind::Action, format!("Could not run editor: {}", err));
+            }
+            (SuspendAction::Rebase { repo: repo_index, upstream }, status) => {
+                let mut repos = self.repos.lock().unwrap();
+                let Some(repo) = repos.get_mut(repo_index) else {
+                    return;
+                };
+                let message = match status {
+                    Err(err) => format!("Could not run rebase: {}", err),
+                    // A rebase stopped for conflicts or `edit` leaves the repository mid-rebase
+                    Ok(_) if Repository::open(&repo.path).is_ok_and(|git_repo| git_repo.state() != RepositoryState::Clean) => {
+                        "Rebase still in progress, finish it with `git rebase --continue` or `--abort`".to_string()
+                    }
+                    Ok(status) if status.success() => format!("Rebased onto {}", upstream),
+                    Ok(status) => format!("Rebase exited with {}", status),
+                };
+                log_console(&self.console_messages, &repo.name, "GitOp", EventKind::Action, message);
+                
+                if repo.expanded {
+                    repo.recent_commits = get_recent_commits(&repo.path, self.max_commits, &repo.commits);
+                }
+                repo.next_check = Instant::now();
+                self.wake_monitor.notify_one();
+            }
+        }
+    }
+
+    fn reload_config(&mut self) {
+        let config = match load_config(Some(self.config_path.clone())) {
+            Ok(config) => config,
+            Err(err) => {
+                // Keep running with the previous configuration
+                log_console(&self.console_messages, "System", "System", EventKind::Action, format!("Config error, not reloaded: {}", err));
+                return;
+            }
+        };
+        
+        // Metered mode follows the connection, which the config doesn't know about
+        let metered = self.refresh.borrow().metered;
+        self.refresh.send_replace(RefreshPolicy { metered, ..RefreshPolicy::from_config(&config) });
+        self.repo_defaults = RepoDefaults::from_config(&config);
+        self.notifier.reconfigure(config.notifications.clone().unwrap_or_default(), config.mute_labels.clone().unwrap_or_default());
+        let mut repos = self.repos.lock().unwrap();
+        let previous = std::mem::take(&mut *repos);
+        for repo_config in config.repositories {
+            let mut repo = RepoStatus::from_config(repo_config, &self.repo_defaults);
+            // Carry over runtime state for repositories that are still configured
+            // Remote-only repositories have no path and are told apart by their url
+            if let Some(prev) = previous.iter().find(|prev| prev.path == repo.path && (repo.remote_refs.is_none() || prev.url == repo.url)) {
+                repo.ahead = prev.ahead;
+                repo.behind = prev.behind;
+                repo.dirty = prev.dirty;
+                repo.diffstat = prev.diffstat;
+                repo.operation = prev.operation.clone();
+                repo.conflicts = prev.conflicts.clone();
+                repo.current_branch = prev.current_branch.clone();
+                repo.counted = prev.counted;
+                repo.default_branch = prev.default_branch.clone();
+                repo.upstream_gone = prev.upstream_gone.clone();
+                repo.renamed_default = prev.renamed_default.clone();
+                repo.rename_asked = prev.rename_asked.clone();
+                repo.last_update = prev.last_update;
+                repo.expanded = prev.expanded;
+                repo.recent_commits = prev.recent_commits.clone();
+                repo.commits = prev.commits.clone();
+                repo.marked = prev.marked;
+                repo.muted |= prev.muted;
+                repo.pinned |= prev.pinned;
+                repo.paused = prev.paused;
+                repo.hidden = prev.hidden;
+                repo.last_activity = prev.last_activity;
+                repo.last_commit = prev.last_commit;
+                repo.changed_at = prev.changed_at;
+                repo.fetch_times = prev.fetch_times.clone();
+                repo.transfer = prev.transfer.clone();
+                if repo.remote_refs.is_some() {
+                    repo.remote_refs = prev.remote_refs.clone();
+                }
+                repo.poll_interval = prev.poll_interval;
+                repo.next_check = prev.next_check;
+                repo.checking = prev.checking;
+                repo.incoming_tip = prev.incoming_tip.clone();
+                repo.wrong_identity = prev.wrong_identity.clone();
+                repo.push_warnings = prev.push_warnings.clone();
+                repo.outgoing_tip = prev.outgoing_tip;
+                repo.incoming_large = prev.incoming_large.clone();
+                repo.incoming_checked = prev.incoming_checked;
+                repo.last_error = prev.last_error.clone();
+                repo.stats = prev.stats.clone();
+                repo.stats_checked = prev.stats_checked;
+                repo.stats_running = prev.stats_running;
+                if let (Some(backup), Some(prev_backup)) = (&mut repo.backup, &prev.backup) {
+                    backup.last_attempt = prev_backup.last_attempt;
+                    backup.last_success = prev_backup.last_success;
+                    backup.lag = prev_backup.lag;
+                    backup.error = prev_backup.error.clone();
+                }
+                for pull_request in &mut repo.pull_requests {
+                    if let Some(prev_pr) = prev.pull_requests.iter().find(|prev_pr| prev_pr.number == pull_request.number) {
+                        *pull_request = prev_pr.clone();
+                    }
+                }
+                if let (Some(maintenance), Some(prev_maintenance)) = (&mut repo.maintenance, &prev.maintenance) {
+                    maintenance.last_run = prev_maintenance.last_run;
+                    maintenance.running = prev_maintenance.running;
+                    maintenance.last_finished = prev_maintenance.last_finished;
+                    maintenance.error = prev_maintenance.error.clone();
+                }
+                if let (Some(policy), Some(prev_policy)) = (&mut repo.policy, &prev.policy) {
+                    policy.tips = prev_policy.tips.clone();
+                    policy.violations = prev_policy.violations;
+                    policy.direct_pushes = prev_policy.direct_pushes;
+                    policy.alerts = prev_policy.alerts.clone();
+                }
+            }
+            repos.push(repo);
+        }
+        // Repositories reported by agents aren't in the config
+        repos.extend(previous.into_iter().filter(|repo| repo.host.is_some()));
+        
+        self.max_commits = config.max_commits;
+        self.colors = Palette::from_config(config.colors.as_ref());
+        self.show_descriptions = config.show_descriptions.unwrap_or(false);
+        self.show_size = config.show_size.unwrap_or(false);
+        self.show_fetch_time = config.show_fetch_time.unwrap_or(false);
+        self.show_diffstat = config.show_diffstat.unwrap_or(false);
+        self.search_depth = config.search_depth.unwrap_or(SEARCH_DEPTH);
+        self.author_colors = config.author_colors.unwrap_or(false);
+        self.console_format = config.console_format.unwrap_or_default();
+        self.highlights = config.highlights.unwrap_or_default();
+        self.flash = config.flash.as_ref().map(flash_settings);
+        self.rebase_command = config.rebase_command;
+        self.worktree_dir = config.worktree_dir;
+        
+        log_console(
+            &self.console_messages,
+            "System",
+            "GitOp",
+            EventKind::Action,
+            format!("Reloaded config: {} repositories", repos.len()),
+        );
+        drop(repos);
+        self.set_layout(config.layout.as_deref().and_then(LayoutMode::from_name).unwrap_or_default());
+    }
+
+    fn next(&mut self) {
+        let repos = self.repos.lock().unwrap();
+        let order = self.display_order(&repos);
+        if order.is_empty() {
+            return;
+        }
+        
+        let position = self.selected_position(&order);
+        let next_position = if position >= order.len() - 1 {
+            0
+        } else {
+            position + 1
+        };
+        self.selected = order[next_position];
+    }
+
+    fn previous(&mut self) {
+        let repos = self.repos.lock().unwrap();
+        let order = self.display_order(&repos);
+        if order.is_empty() {
+            return;
+        }
+        
+        let position = self.selected_position(&order);
+        let prev_position = if position == 0 {
+            order.len() - 1
+        } else {
+            position - 1
+        };
+        self.selected = order[prev_position];
+    }
+
+    fn page_down(&mut self) {
+        let repos = self.repos.lock().unwrap();
+        let order = self.display_order(&repos);
+        if order.is_empty() {
+            return;
+        }
+        
+        let position = self.selected_position(&order);
+        let target_line = calculate_table_line(&repos, &order, position, self.show_descriptions) + self.page_size;
+        
+        // Pick the last repository whose row is still within one page, skipping commit and header rows
+        let mut next_position = position;
+        for i in position..order.len() {
+            if calculate_table_line(&repos, &order, i, self.show_descriptions) > target_line {
+                break;
+            }
+            next_position = i;
+        }
+        
+        // Always move at least one repository, even if its commits fill the page
+        if next_position == position {
+            next_position = (position + 1).min(order.len() - 1);
+        }
+        
+        self.selected = order[next_position];
+    }
+
+    fn page_up(&mut self) {
+        let repos = self.repos.lock().unwrap();
+        let order = self.display_order(&repos);
+        if order.is_empty() {
+            return;
+        }
+        
+        let position = self.selected_position(&order);
+        let target_line = calculate_table_line(&repos, &order, position, self.show_descriptions).saturating_sub(self.page_size);
+        
+        // Pick the first repository whose row is at or below the target, skipping commit rows
+        let mut prev_position = position;
+        for i in 0..position {
+            if calculate_table_line(&repos, &order, i, self.show_descriptions) >= target_line {
+                prev_position = i;
+                break;
+            }
+        }
+        
+        if prev_position == position {
+            prev_position = position.saturating_sub(1);
+        }
+        
+        self.selected = order[prev_position];
+    }
+
+    fn first(&mut self) {
+        let repos = self.repos.lock().unwrap();
+        if let Some(&repo_index) = self.display_order(&repos).first() {
+            self.selected = repo_index;
+        }
+    }
+
+    fn last(&mut self) {
+        let repos = self.repos.lock().unwrap();
+        if let Some(&repo_index) = self.display_order(&repos).last() {
+            self.selected = repo_index;
+        }
+    }
+    
+    // Repository indices in the order they are displayed, after filtering and sorting
+    fn display_order(&self, repos: &[RepoStatus]) -> Vec<usize> {
+        let mut order: Vec<usize> = repos
+            .iter()
+            .enumerate()
+            .filter(|(_, repo)| self.show_hidden || !repo.hidden)
+            .filter(|(_, repo)| self.show_archived || !repo.archived)
+            .filter(|(_, repo)| match &self.label_filter {
+                Some(label) => repo.labels.contains(label),
+                None => true,
+            })
+            .filter(|(_, repo)| match &self.name_filter {
+                Some(text) => {
+                    let text = text.to_lowercase();
+                    repo.name.to_lowercase().contains(&text) || repo.labels.iter().any(|label| label.to_lowercase().contains(&text))
+                }
+                None => true,
+            })
+            .map(|(i, _)| i)
+            .collect();
+        
+        // Ties keep the config order, also when reversed
+        order.sort_by(|&a, &b| {
+            let ordering = self.sort_key.compare(&repos[a], &repos[b]);
+            match (self.sort_reversed, self.sort_key) {
+                (true, SortKey::Config) => b.cmp(&a),
+                (true, _) => ordering.reverse(),
+                (false, _) => ordering,
+            }
+        });
+        
+        // Pinned repositories stay on top regardless of sort order (stable sort keeps their order)
+        order.sort_by_key(|&i| !repos[i].pinned);
+        // Local repositories first, then one group per agent host
+        order.sort_by(|&a, &b| repos[a].host.cmp(&repos[b].host));
+        // Archived repositories below everything else
+        order.sort_by_key(|&i| repos[i].archived);
+        order
+    }
+    
+    // Position of the selected repository in the display order, or the top if it is filtered out
+    fn selected_position(&self, order: &[usize]) -> usize {
+        order.iter().position(|&i| i == self.selected).unwrap_or(0)
+    }
+    
+    fn get_selected_repo_index(&self, repos: &[RepoStatus]) -> Option<usize> {
+        let order = self.display_order(repos);
+        order.get(self.selected_position(&order)).copied()
+    }
+
+    fn toggle_expand(&mut self) {
+        let mut repos = self.repos.lock().unwrap();
+        let Some(repo_index) = self.get_selected_repo_index(&repos) else {
+            return;
+        };
+        
+        let repo = &mut repos[repo_index];
+        if !on_this_machine(repo, &self.console_messages) {
+            return;
+        }
+        repo.expanded = !repo.expanded;
+        if repo.expanded && repo.remote_refs.is_some() {
+            repo.next_check = Instant::now();
+            self.wake_monitor.notify_one();
+        } else if repo.expanded {
+            // Fetch recent commits when expanding, and refresh the status ahead of schedule
+            repo.recent_commits = get_recent_commits(&repo.path, self.max_commits, &repo.commits);
+            repo.next_check = Instant::now();
+            self.wake_monitor.notify_one();
+        }
+        self.selected = repo_index;
+    }
+
+    // Rows never expand in the split layout, so switching to it folds them all
+    fn set_layout(&mut self, layout: LayoutMode) {
+        if layout == LayoutMode::Split {
+            for repo in self.repos.lock().unwrap().iter_mut() {
+                repo.expanded = false;
+            }
+        }
+        self.layout = layout;
+        self.side_pane = None;
+    }
+
+    // Called every UI tick: once the selection has settled, move its refresh to the front of the queue
+    fn update_focus(&mut self) {
+        let mut repos = self.repos.lock().unwrap();
+        let selected = self.get_selected_repo_index(&repos);
+        for (index, repo) in repos.iter_mut().enumerate() {
+            repo.focused = Some(index) == selected;
+        }
+        
+        let Some(selected) = selected else {
+            return;
+        };
+        if self.layout == LayoutMode::Split {
+            let activity = repos[selected].last_activity;
+            if self.side_pane.as_ref().is_none_or(|pane| pane.repo != selected || pane.activity != activity) {
+                let repo = &mut repos[selected];
+                let local = repo.remote_refs.is_none() && repo.host.is_none();
+                if local {
+                    repo.recent_commits = get_recent_commits(&repo.path, self.max_commits, &repo.commits);
+                }
+                let remotes = if local { remote_urls(&repo.path) } else { Vec::new() };
+                self.side_pane = Some(SidePane { repo: selected, activity, remotes });
+            }
+        }
+        match self.focus {
+            Some((index, Some(since))) if index == selected && since.elapsed() >= FOCUS_SETTLE => {
+                repos[selected].next_check = Instant::now();
+                self.wake_monitor.notify_one();
+                self.focus = Some((index, None));
+            }
+            Some((index, _)) if index == selected => {}
+            _ => self.focus = Some((selected, Some(Instant::now()))),
+        }
+    }
+
+    fn toggle_mark(&mut self) {
+        let mut repos = self.repos.lock().unwrap();
+        if let Some(repo_index) = self.get_selected_repo_index(&repos) {
+            repos[repo_index].marked = !repos[repo_index].marked;
+        }
+    }
+
+    fn toggle_pin(&mut self) {
+        let mut repos = self.repos.lock().unwrap();
+        if let Some(repo_index) = self.get_selected_repo_index(&repos) {
+            repos[repo_index].pinned = !repos[repo_index].pinned;
+        }
+    }
+
+    fn toggle_hidden(&mut self) {
+        let mut repos = self.repos.lock().unwrap();
+        let order = self.display_order(&repos);
+        if order.is_empty() {
+            return;
+        }
+        
+        let position = self.selected_position(&order);
+        let repo_index = order[position];
+        repos[repo_index].hidden = !repos[repo_index].hidden;
+        
+        // Keep the cursor in place by moving to the neighbour when the row disappears
+        if repos[repo_index].hidden && !self.show_hidden {
+            if let Some(&next) = order.get(position + 1) {
+                self.selected = next;
+            } else if position > 0 {
+                self.selected = order[position - 1];
+            }
+        }
+    }
+
+    fn toggle_show_hidden(&mut self) {
+        self.show_hidden = !self.show_hidden;
+    }
+
+    // Archiving is saved to the config, unlike hiding
+    fn toggle_archived(&mut self) {
+        let mut repos = self.repos.lock().unwrap();
+        let order = self.display_order(&repos);
+        if order.is_empty() {
+            return;
+        }
+        
+        let position = self.selected_position(&order);
+        let repo_index = order[position];
+        let repo = &mut repos[repo_index];
+        if repo.host.is_some() {
+            log_console(&self.console_messages, &console_name(repo), "GitOp", EventKind::Action, "Archive it in the config of the agent that reports it".to_string());
+            return;
+        }
+        
+        let archived = !repo.archived;
+        // Unarchiving removes the key rather than writing "archived = false"
+        if let Err(err) = set_repo_config_value(&self.config_path, &repo.name, "archived", archived.then(|| toml_edit::value(true))) {
+            log_console(&self.console_messages, &repo.name, "System", EventKind::Action, format!("Could not save to {}: {}", self.config_path.display(), err));
+            return;
+        }
+        repo.archived = archived;
+        let message = if archived {
+            format!("Archived, no longer checked; saved to {}", self.config_path.display())
+        } else {
+            // Checked right away, its status is as old as the archive
+            repo.next_check = Instant::now();
+            self.wake_monitor.notify_one();
+            format!("Unarchived, saved to {}", self.config_path.display())
+        };
+        log_console(&self.console_messages, &repo.name, "GitOp", EventKind::Action, message);
+        
+        // Keep the cursor in place by moving to the neighbour when the row disappears
+        if archived && !self.show_archived {
+            if let Some(&next) = order.get(position + 1) {
+                self.selected = next;
+            } else if position > 0 {
+                self.selected = order[position - 1];
+            }
+        }
+    }
+
+    fn toggle_show_archived(&mut self) {
+        self.show_archived = !self.show_archived;
+    }
+
+    fn step_refresh_interval(&mut self, slower: bool) {
+        let current = self.refresh.borrow().interval.as_secs();
+        let next = if slower {
+            REFRESH_STEPS.iter().copied().find(|&secs| secs > current).unwrap_or(current)
+        } else {
+            REFRESH_STEPS.iter().copied().rev().find(|&secs| secs < current).unwrap_or(current)
+        };
+        if next != current {
+            self.refresh.send_modify(|policy| policy.interval = Duration::from_secs(next));
+        }
+    }
+
+    // Some(on) sets metered mode, None flips it
+    fn set_metered(&mut self, on: Option<bool>) {
+        let metered = on.unwrap_or(!self.refresh.borrow().metered);
+        self.refresh.send_modify(|policy| policy.metered = metered);
+        let message = if metered {
+            "Metered mode on: checking less often with ls-remote and fetching only checked-out branches"
+        } else {
+            "Metered mode off"
+        };
+        log_console(&self.console_messages, "System", "GitOp", EventKind::Action, message.to_string());
+    }
+
+    fn save_refresh_interval(&mut self) {
+        let secs = self.refresh.borrow().interval.as_secs();
+        match set_config_value(&self.config_path, "refresh_interval", toml_edit::value(secs as i64)) {
+            Ok(()) => log_console(
+                &self.console_messages,
+                "System",
+                "GitOp",
+                EventKind::Action,
+                format!("Saved refresh_interval = {} to {}", secs, self.config_path.display()),
+            ),
+            Err(err) => log_console(&self.console_messages, "System", "System", EventKind::Action, format!("Could not save refresh interval: {}", err)),
+        }
+    }
+
+    fn clear_marks(&mut self) {
+        let mut repos = self.repos.lock().unwrap();
+        for repo in repos.iter_mut() {
+            repo.marked = false;
+        }
+    }
+
+    fn cycle_label_filter(&mut self) {
+        let repos = self.repos.lock().unwrap();
+        let mut labels: Vec<&String> = repos.iter().flat_map(|repo| &repo.labels).collect();
+        labels.sort();
+        labels.dedup();
+        
+        // None -> first label -> ... -> last label -> None
+        let next = match &self.label_filter {
+            None => labels.first(),
+            Some(current) => labels
+                .iter()
+                .position(|label| *label == current)
+                .and_then(|i| labels.get(i + 1)),
+        };
+        self.label_filter = next.map(|label| label.to_string());
+    }
+
+    // The table's columns in order, with what picking each sorts by
+    fn table_columns(&self) -> Vec<(&'static str, SortKey)> {
+        let mut columns = vec![("Repository", SortKey::Name), ("Ahead", SortKey::Ahead), ("Behind", SortKey::Behind)];
+        for (shown, title, key) in [
+            (self.show_size, "Size", SortKey::Size),
+            (self.show_fetch_time, "Fetch", SortKey::FetchTime),
+            (self.show_diffstat, "Diff", SortKey::Diff),
+        ] {
+            if shown {
+                columns.push((title, key));
+            }
+        }
+        columns.push(("Branch", SortKey::Branch));
+        columns
+    }
+
+    // A column sorts by itself first, and picking it again flips the direction
+    fn sort_by_column(&mut self, key: SortKey) {
+        if self.sort_key == key {
+            self.sort_reversed = !self.sort_reversed;
+        } else {
+            self.sort_key = key;
+            self.sort_reversed = false;
+        }
+    }
+
+    fn cycle_sort(&mut self) {
+        self.sort_reversed = false;
+        self.sort_key = match self.sort_key {
+            SortKey::Config => SortKey::Name,
+            SortKey::Name => SortKey::Label,
+            SortKey::Label => SortKey::Behind,
+            SortKey::Behind => SortKey::Ahead,
+            SortKey::Ahead => SortKey::Attention,
+            // Columns only the header sorts by lead back to the start
+            SortKey::Attention | SortKey::Size | SortKey::FetchTime | SortKey::Diff | SortKey::Branch => SortKey::Config,
+        };
+    }
+
+    // Visible marked repositories, or the selected one if nothing is marked
+    fn action_targets(&self, repos: &[RepoStatus]) -> Vec<usize> {
+        let order = self.display_order(repos);
+        let marked: Vec<usize> = order
+            .iter()
+            .copied()
+            .filter(|&i| repos[i].marked)
+            .collect();
+        
+        if !marked.is_empty() {
+            marked
+        } else {
+            self.get_selected_repo_index(repos).into_iter().collect()
+        }
+    }
+
+    fn run_batch_action(&mut self, action: BatchAction) {
+        let mut repos = self.repos.lock().unwrap();
+        let targets = self.action_targets(&repos);
+        self.run_action(&mut repos, action, targets);
+    }
+
+    fn run_action(&self, repos: &mut [RepoStatus], action: BatchAction, targets: Vec<usize>) {
+        for repo_index in targets {
+            let repo = &mut repos[repo_index];
+            
+            if let BatchAction::Mute = action {
+                repo.muted = !repo.muted;
+                let message = if repo.muted { "Muted notifications" } else { "Unmuted notifications" };
+                log_console(&self.console_messages, &repo.name, "GitOp", EventKind::Action, message.to_string());
+                continue;
+            }
+            
+            if let Some(host) = &repo.host {
+                log_console(&self.console_messages, &repo.name, "System", EventKind::Action, format!("{:?} skipped: repository is on {}", action, host));
+                continue;
+            }
+            
+            if repo.remote_refs.is_some() {
+                let result = match (action, repo.url.as_deref()) {
+                    (BatchAction::Open, Some(url)) => open_path(url).map(|_| format!("Opened {}", url)),
+                    _ => Err(anyhow::anyhow!("repository is watched without a clone")),
+                };
+                match result {
+                    Ok(message) => log_console(&self.console_messages, &repo.name, "GitOp", EventKind::Action, message),
+                    Err(err) => log_console(&self.console_messages, &repo.name, "System", EventKind::Action, format!("{:?} skipped: {}", action, err)),
+                }
+                continue;
+            }
+            
+            if matches!(action, BatchAction::Pull) && !repo.conflicts.is_empty() {
+                log_console(&self.console_messages, &repo.name, "System", EventKind::Action, "Pull skipped: unresolved conflicts".to_string());
+                continue;
+            }
+            
+            // Git and process work runs off the UI thread; results land in the console
+            let name = repo.name.clone();
+            let path = repo.path.clone();
+            let remote = repo.remote.clone();
+            let refspecs = repo.refspecs.clone();
+            let prune = repo.prune;
+            let depth = repo.fetch_depth;
+            let transfer = repo.transfer.clone();
+            let logins = repo.logins.clone();
+            let console_messages = self.console_messages.clone();
+            tokio::task::spawn_blocking(move || {
+                let result = match action {
+                    BatchAction::Fetch => {
+                        fetch_repo(&path, &remote, &refspecs, prune, depth, Some(&transfer), &logins).map(|_| format!("Fetched from {}", remote))
+                    }
+                    BatchAction::Pull => pull_repo(&path, &remote, &refspecs, prune, depth, Some(&transfer), &logins),
+                    BatchAction::Open => open_path(&path).map(|_| format!("Opened {}", path.display())),
+                    BatchAction::Mute => unreachable!(),
+                };
+                match result {
+                    Ok(message) => log_console(&console_messages, &name, "GitOp", EventKind::Action, message),
+                    Err(err) => log_console(&console_messages, &name, "System", EventKind::Action, format!("{:?} failed: {}", action, err)),
+                }
+            });
+        }
+    }
+
+    fn show_detail(&mut self) {
+        let repos = self.repos.lock().unwrap();
+        if let Some(repo_index) = self.get_selected_repo_index(&repos) {
+            let repo = &repos[repo_index];
+            let local = repo.remote_refs.is_none() && repo.host.is_none();
+            let release = local.then(|| suggest_release(&repo.path)).flatten();
+            let identity = local.then(|| commit_identity(&repo.path)).flatten();
+            self.popup = Some(Popup::Detail { repo: repo_index, release, identity });
+        }
+    }
+
+    // Newest commit of the selected repository; Up/Down in the popup move through the recent commits
+    fn show_commit(&mut self) {
+        let mut repos = self.repos.lock().unwrap();
+        let Some(repo_index) = self.get_selected_repo_index(&repos) else {
+            return;
+        };
+        let repo = &mut repos[repo_index];
+        if !on_this_machine(repo, &self.console_messages) {
+            return;
+        }
+        if repo.remote_refs.is_some() {
+            log_console(&self.console_messages, &repo.name, "System", EventKind::Action, "Commits can't be shown without a clone".to_string());
+            return;
+        }
+        if !repo.expanded {
+            repo.recent_commits = get_recent_commits(&repo.path, self.max_commits, &repo.commits);
+        }
+        if let Some(detail) = load_commit_detail(repo, repo_index, 0) {
+            self.popup = Some(Popup::Commit(detail));
+        }
+    }
+
+    fn prompt_search(&mut self, repo_index: usize) {
+        let repos = self.repos.lock().unwrap();
+        let Some(repo) = repos.get(repo_index) else {
+            return;
+        };
+        if !on_this_machine(repo, &self.console_messages) {
+            return;
+        }
+        let title = format!("Search {} - Message, Author or Hash", repo.name);
+        self.popup = Some(Popup::Prompt(Prompt::new(&title, String::new(), PromptAction::SearchCommits { repo: repo_index })));
+    }
+    
+    fn prompt_search_everywhere(&mut self) {
+        let prompt = Prompt::new("Search All Repositories - Message, Author or Hash", String::new(), PromptAction::SearchEverywhere);
+        self.popup = Some(Popup::Prompt(prompt));
+    }
+    
+    // The selected search result in the commit view; the results stay open if it can't be loaded
+    // Swaps a finished search of every repository into its popup, or drops it if the popup was closed meanwhile
+    fn collect_search(&mut self) {
+        let Some(done) = self.search_results.lock().unwrap().take() else {
+            return;
+        };
+        if let Some(Popup::CommitSearch(search)) = &self.popup
+            && search.searching
+            && search.query == done.query
+        {
+            self.popup = Some(Popup::CommitSearch(done));
+        }
+    }
+
+    fn show_search_hit(&mut self, search: CommitSearch) {
+        let repos = self.repos.lock().unwrap();
+        let detail = search
+            .hits
+            .get(search.selected)
+            .and_then(|hit| Some((hit, repos.get(hit.repo)?)))
+            .and_then(|(hit, repo)| commit_detail(repo, hit.repo, 0, &hit.hash));
+        self.popup = Some(match detail {
+            Some(detail) => Popup::Commit(CommitDetail { search: Some(Box::new(search)), ..detail }),
+            None => Popup::CommitSearch(search),
+        });
+    }
+    
+    // Blame the file selected in the commit view; on failure the commit view stays open
+    fn show_blame(&mut self, detail: CommitDetail) {
+        let repos = self.repos.lock().unwrap();
+        let (Some(repo), Some((file, _, _))) = (repos.get(detail.repo), detail.files.get(detail.selected_file)) else {
+            drop(repos);
+            self.popup = Some(Popup::Commit(detail));
+            return;
+        };
+        match blame_lines(&repo.path, &detail.hash, file) {
+            Ok((lines, first_changed)) => {
+                let file = file.clone();
+                // A few lines of context above the first change
+                let scroll = first_changed.saturating_sub(5);
+                drop(repos);
+                self.popup = Some(Popup::Blame { commit: detail, file, lines, scroll });
+            }
+            Err(err) => {
+                log_console(&self.console_messages, &repo.name, "System", EventKind::Action, format!("Blame of {} failed: {}", file, err));
+                drop(repos);
+                self.popup = Some(Popup::Commit(detail));
+            }
+        }
+    }
+
+    // Runs off the UI thread like the batch actions; conflicts are left in the work tree for the user to resolve
+    fn apply_commit(&self, repo_index: usize, action: CommitAction, hash: String) {
+        let Some((name, path)) = self
+            .repos
+            .lock()
+            .unwrap()
+            .get(repo_index)
+            .filter(|repo| on_this_machine(repo, &self.console_messages))
+            .map(|repo| (repo.name.clone(), repo.path.clone()))
+        else {
+            return;
+        };
+        let repos = self.repos.clone();
+        let console_messages = self.console_messages.clone();
+        let wake_monitor = self.wake_monitor.clone();
+        tokio::task::spawn_blocking(move || {
+            let result = match action {
+                CommitAction::CherryPick => cherry_pick_commit(&path, &hash),
+                CommitAction::Revert => revert_commit(&path, &hash),
+            };
+            match result {
+                Ok(message) => log_console(&console_messages, &name, "GitOp", EventKind::Action, message),
+                Err(err) => log_console(&console_messages, &name, "System", EventKind::Action, format!("{:?} of {} failed: {}", action, hash, err)),
+            }
+            // Show the new ahead/behind counts right away
+            if let Some(repo) = repos.lock().unwrap().get_mut(repo_index) {
+                repo.next_check = Instant::now();
+            }
+            wake_monitor.notify_one();
+        });
+    }
+    
+    fn prompt_branch(&mut self, repo_index: usize, commit: String) {
+        if !self.repos.lock().unwrap().get(repo_index).is_some_and(|repo| on_this_machine(repo, &self.console_messages)) {
+            return;
+        }
+        let prompt = Prompt::new(&format!("New Branch at {:.8} - Name", commit), String::new(), PromptAction::BranchName { repo: repo_index, commit });
+        self.popup = Some(Popup::Prompt(prompt));
+    }
+    
+    fn checkout_branch(&mut self, repo_index: usize, branch: String) {
+        let mut repos = self.repos.lock().unwrap();
+        let Some(repo) = repos.get_mut(repo_index) else {
+            return;
+        };
+        if !on_this_machine(repo, &self.console_messages) {
+            return;
+        }
+        match switch_branch(&repo.path, &branch) {
+            Ok(()) => {
+                log_console(&self.console_messages, &repo.name, "GitOp", EventKind::Action, format!("Switched to {}", branch));
+                if repo.expanded {
+                    repo.recent_commits = get_recent_commits(&repo.path, self.max_commits, &repo.commits);
+                }
+                repo.next_check = Instant::now();
+                self.wake_monitor.notify_one();
+            }
+            Err(err) => log_console(&self.console_messages, &repo.name, "System", EventKind::Action, format!("Checkout of {} failed: {}", branch, err)),
+        }
+    }
+    
+    fn confirm_retarget_upstream(&mut self) {
+        let repos = self.repos.lock().unwrap();
+        let Some(repo_index) = self.get_selected_repo_index(&repos) else {
+            return;
+        };
+        let repo = &repos[repo_index];
+        match &repo.renamed_default {
+            Some((old, new)) => self.popup = Some(Popup::RetargetUpstream { repo: repo_index, old: old.clone(), new: new.clone() }),
+            None => log_console(&self.console_messages, &repo.name, "GitOp", EventKind::Action, "No renamed default branch to retarget".to_string()),
+        }
+    }
+    
+    fn retarget_upstream(&mut self, repo_index: usize, old: String, new: String) {
+        let mut repos = self.repos.lock().unwrap();
+        let Some(repo) = repos.get_mut(repo_index) else {
+            return;
+        };
+        if !on_this_machine(repo, &self.console_messages) {
+            return;
+        }
+        match retarget_upstream(&repo.path, &repo.remote, &old, &new) {
+            Ok(branches) => {
+                let message = match branches.len() {
+                    0 => format!("{}/HEAD now points to {}", repo.remote, new),
+                    1 => format!("{} now tracks {}/{}", branches[0], repo.remote, new),
+                    _ => format!("{} now track {}/{}", branches.join(", "), repo.remote, new),
+                };
+                log_console(&self.console_messages, &repo.name, "GitOp", EventKind::Action, message);
+                repo.renamed_default = None;
+                repo.next_check = Instant::now();
+                self.wake_monitor.notify_one();
+            }
+            Err(err) => log_console(&self.console_messages, &repo.name, "System", EventKind::Action, format!("Retargeting failed: {}", err)),
+        }
+    }
+    
+    fn confirm_cleanup_branches(&mut self) {
+        let repos = self.repos.lock().unwrap();
+        let Some(repo_index) = self.get_selected_repo_index(&repos) else {
+            return;
+        };
+        let repo = &repos[repo_index];
+        if repo.host.is_some() || repo.remote_refs.is_some() {
+            log_console(&self.console_messages, &repo.name, "System", EventKind::Action, "Branch cleanup only works on local repositories".to_string());
+            return;
+        }
+        match stale_branches(&repo.path, &repo.remote) {
+            Ok((_, branches)) if branches.is_empty() => {
+                log_console(&self.console_messages, &repo.name, "GitOp", EventKind::Action, "No merged branches to clean up".to_string());
+            }
+            Ok((default_branch, branches)) => self.popup = Some(Popup::CleanupBranches { repo: repo_index, default_branch, branches }),
+            Err(err) => log_console(&self.console_messages, &repo.name, "System", EventKind::Action, format!("Branch cleanup failed: {}", err)),
+        }
+    }
+    
+    fn cleanup_branches(&mut self, repo_index: usize, branches: Vec<StaleBranch>) {
+        let repos = self.repos.lock().unwrap();
+        let Some(repo) = repos.get(repo_index) else {
+            return;
+        };
+        let Ok(git_repo) = Repository::open(&repo.path) else {
+            return;
+        };
+        for branch in branches {
+            let result = git_repo.find_branch(&branch.name, git2::BranchType::Local).and_then(|mut local| {
+                if local.get().target() != Some(branch.tip) {
+                    return Ok(false);
+                }
+                local.delete().map(|()| true)
+            });
+            let (author, message) = match result {
+                Ok(true) => ("GitOp", format!("Deleted {} (was {:.8})", branch.name, branch.tip)),
+                Ok(false) => ("System", format!("Kept {}, it moved since the list was shown", branch.name)),
+                Err(err) => ("System", format!("Could not delete {}: {}", branch.name, err.message())),
+            };
+            log_console(&self.console_messages, &repo.name, author, EventKind::Action, message);
+        }
+    }
+    
+    fn prompt_worktree(&mut self) {
+        let repos = self.repos.lock().unwrap();
+        let Some(repo_index) = self.get_selected_repo_index(&repos) else {
+            return;
+        };
+        let repo = &repos[repo_index];
+        if repo.host.is_some() || repo.remote_refs.is_some() {
+            log_console(&self.console_messages, &repo.name, "System", EventKind::Action, "Worktrees can only be created for local repositories".to_string());
+            return;
+        }
+        let prompt = Prompt::new(&format!("New Worktree of {} - Branch", repo.name), String::new(), PromptAction::WorktreeBranch { repo: repo_index });
+        self.popup = Some(Popup::Prompt(prompt));
+    }
+    
+    fn show_divergence(&mut self) {
+        let repos = self.repos.lock().unwrap();
+        let Some(repo_index) = self.get_selected_repo_index(&repos) else {
+            return;
+        };
+        let repo = &repos[repo_index];
+        if !on_this_machine(repo, &self.console_messages) {
+            return;
+        }
+        if repo.remote_refs.is_some() || repo.ahead == 0 || repo.behind == 0 {
+            log_console(&self.console_messages, &repo.name, "GitOp", EventKind::Action, format!("{} has not diverged from {}", repo.current_branch, repo.remote));
+            return;
+        }
+        self.popup = Some(Popup::Diverged(repo_index));
+    }
+    
+    fn show_branches(&mut self) {
+        let repos = self.repos.lock().unwrap();
+        let Some(repo_index) = self.get_selected_repo_index(&repos) else {
+            return;
+        };
+        let repo = &repos[repo_index];
+        if !on_this_machine(repo, &self.console_messages) || repo.remote_refs.is_some() {
+            return;
+        }
+        match branch_overview(&repo.path, &repo.remote) {
+            Ok((default_branch, branches)) => self.popup = Some(Popup::Branches { repo: repo_index, default_branch, branches }),
+            Err(err) => log_console(&self.console_messages, &repo.name, "System", EventKind::Action, format!("Branch overview failed: {}", err)),
+        }
+    }
+    
+    fn show_graph(&mut self) {
+        let repos = self.repos.lock().unwrap();
+        let Some(repo_index) = self.get_selected_repo_index(&repos) else {
+            return;
+        };
+        let repo = &repos[repo_index];
+        if !on_this_machine(repo, &self.console_messages) || repo.remote_refs.is_some() {
+            return;
+        }
+        match commit_graph(&repo.path, &repo.remote, GRAPH_COMMITS) {
+            Ok(lines) => self.popup = Some(Popup::Graph { repo: repo_index, lines, scroll: 0 }),
+            Err(err) => log_console(&self.console_messages, &repo.name, "System", EventKind::Action, format!("Commit graph failed: {}", err)),
+        }
+    }
+    
+    fn show_work_tree(&mut self) {
+        let repos = self.repos.lock().unwrap();
+        let Some(repo_index) = self.get_selected_repo_index(&repos) else {
+            return;
+        };
+        let repo = &repos[repo_index];
+        if !on_this_machine(repo, &self.console_messages) || repo.remote_refs.is_some() {
+            return;
+        }
+        match short_status(&repo.path) {
+            Ok(lines) if lines.is_empty() => {
+                log_console(&self.console_messages, &repo.name, "GitOp", EventKind::Action, "Working tree clean".to_string());
+            }
+            Ok(lines) => self.popup = Some(Popup::WorkTree { repo: repo_index, lines, scroll: 0 }),
+            Err(err) => log_console(&self.console_messages, &repo.name, "System", EventKind::Action, format!("Status failed: {}", err)),
+        }
+    }
+    
+    fn show_remote_matrix(&mut self) {
+        let repos = self.repos.lock().unwrap();
+        let Some(repo_index) = self.get_selected_repo_index(&repos) else {
+            return;
+        };
+        let repo = &repos[repo_index];
+        if !on_this_machine(repo, &self.console_messages) || repo.remote_refs.is_some() {
+            return;
+        }
+        match remote_matrix(&repo.path, &repo.remote) {
+            Ok(lines) => self.popup = Some(Popup::RemoteMatrix { repo: repo_index, lines }),
+            Err(err) => log_console(&self.console_messages, &repo.name, "System", EventKind::Action, format!("Remote comparison failed: {}", err)),
+        }
+    }
+    
+    // Reads the index again rather than trusting the last poll, conflicts are often resolved between checks
+    fn show_conflicts(&mut self) {
+        let mut repos = self.repos.lock().unwrap();
+        let Some(repo_index) = self.get_selected_repo_index(&repos) else {
+            return;
+        };
+        let repo = &mut repos[repo_index];
+        if !on_this_machine(repo, &self.console_messages) || repo.remote_refs.is_some() {
+            return;
+        }
+        repo.conflicts = conflicted_files(&repo.path);
+        if repo.conflicts.is_empty() {
+            log_console(&self.console_messages, &repo.name, "GitOp", EventKind::Action, "No conflicts".to_string());
+            return;
+        }
+        
+        let width = repo.conflicts.iter().map(|(_, kind)| kind.len()).max().unwrap_or(0);
+        let mut lines: Vec<String> = repo.conflicts.iter().map(|(path, kind)| format!("{:<width$}  {}", kind, path)).collect();
+        lines.push(String::new());
+        lines.push(format!(
+            "{} unresolved{}. Fix them, `git add` each file, then commit or continue.",
+            repo.conflicts.len(),
+            repo.operation.as_deref().map(|operation| format!(" while {}", operation)).unwrap_or_default(),
+        ));
+        self.popup = Some(Popup::Conflicts { repo: repo_index, lines, scroll: 0 });
+    }
+    
+    // HEAD's tree; u in the view switches to the upstream branch and back
+    fn show_tree(&mut self) {
+        let repos = self.repos.lock().unwrap();
+        let Some(repo_index) = self.get_selected_repo_index(&repos) else {
+            return;
+        };
+        let repo = &repos[repo_index];
+        if !on_this_machine(repo, &self.console_messages) || repo.remote_refs.is_some() {
+            return;
+        }
+        let upstream = format!("{}/{}", repo.remote, repo.current_branch);
+        match open_tree(repo, repo_index, "HEAD".to_string(), Some(upstream)) {
+            Ok(tree) => self.popup = Some(Popup::Tree(tree)),
+            Err(err) => log_console(&self.console_messages, &repo.name, "System", EventKind::Action, format!("Tree view failed: {}", err)),
+        }
+    }
+    
+    fn open_tree_entry(&mut self, tree: TreeView) {
+        let Some((name, is_dir)) = tree.entries.get(tree.selected).cloned() else {
+            self.popup = Some(Popup::Tree(tree));
+            return;
+        };
+        if is_dir {
+            let dir = format!("{}{}/", tree.dir, name);
+            let rev = tree.rev.clone();
+            self.load_tree_dir(tree, rev, dir, None);
+            return;
+        }
+        let repos = self.repos.lock().unwrap();
+        let Some(repo) = repos.get(tree.repo) else {
+            return;
+        };
+        let file = format!("{}{}", tree.dir, name);
+        match preview_file(&repo.path, &tree.rev, &file) {
+            Ok(lines) => {
+                drop(repos);
+                self.popup = Some(Popup::FilePreview { tree, file, lines, scroll: 0 });
+            }
+            Err(err) => {
+                log_console(&self.console_messages, &repo.name, "System", EventKind::Action, format!("Preview of {} failed: {}", file, err));
+                drop(repos);
+                self.popup = Some(Popup::Tree(tree));
+            }
+        }
+    }
+    
+    // Show `dir` of `rev`, selecting `select` if it's there. A directory that doesn't exist in another revision
+    // (after switching with u) falls back to the root; on errors the view stays as it was.
+    fn load_tree_dir(&mut self, mut tree: TreeView, rev: String, dir: String, select: Option<&str>) {
+        let repos = self.repos.lock().unwrap();
+        let Some(repo) = repos.get(tree.repo) else {
+            return;
+        };
+        let result = tree_entries(&repo.path, &rev, &dir).map(|entries| (dir, entries)).or_else(|err| {
+            tree_entries(&repo.path, &rev, "").map(|entries| (String::new(), entries)).map_err(|_| err)
+        });
+        match result {
+            Ok((dir, entries)) => {
+                if rev != tree.rev {
+                    tree.other_rev = Some(std::mem::replace(&mut tree.rev, rev));
+                }
+                tree.selected = select.and_then(|name| entries.iter().position(|(entry, _)| entry == name)).unwrap_or(0);
+                tree.dir = dir;
+                tree.entries = entries;
+            }
+            Err(err) => log_console(&self.console_messages, &repo.name, "System", EventKind::Action, format!("Tree view of {} failed: {}", rev, err)),
+        }
+        drop(repos);
+        self.popup = Some(Popup::Tree(tree));
+    }
+    
+    fn show_pull_preview(&mut self) {
+        let repos = self.repos.lock().unwrap();
+        let Some(repo_index) = self.get_selected_repo_index(&repos) else {
+            return;
+        };
+        let repo = &repos[repo_index];
+        if !on_this_machine(repo, &self.console_messages) || repo.remote_refs.is_some() {
+            return;
+        }
+        match preview_pull(&repo.path, &repo.remote) {
+            Ok(preview) => self.popup = Some(Popup::PullPreview { repo: repo_index, preview }),
+            Err(err) => log_console(&self.console_messages, &repo.name, "System", EventKind::Action, format!("Pull preview failed: {}", err)),
+        }
+    }
+    
+    // Annotated tag on HEAD, named after the suggested release when there is one
+    fn prompt_tag(&mut self) {
+        let repos = self.repos.lock().unwrap();
+        let Some(repo_index) = self.get_selected_repo_index(&repos) else {
+            return;
+        };
+        let repo = &repos[repo_index];
+        if repo.host.is_some() || repo.remote_refs.is_some() {
+            log_console(&self.console_messages, &repo.name, "System", EventKind::Action, "Tags can only be created in local repositories".to_string());
+            return;
+        }
+        let suggestion = suggest_release(&repo.path).and_then(|release| release.next).unwrap_or_default();
+        let prompt = Prompt::new(&format!("Create Tag on {} - Name", repo.name), suggestion, PromptAction::TagName { repo: repo_index });
+        self.popup = Some(Popup::Prompt(prompt));
+    }
+    
+    fn push_tag(&self, repo_index: usize, tag: String) {
+        let repos = self.repos.lock().unwrap();
+        let Some(repo) = repos.get(repo_index) else {
+            return;
+        };
+        let name = repo.name.clone();
+        let path = repo.path.clone();
+        let remote = repo.remote.clone();
+        let logins = repo.logins.clone();
+        let console_messages = self.console_messages.clone();
+        tokio::task::spawn_blocking(move || match push_refs(&path, &remote, &[format!("refs/tags/{}:refs/tags/{}", tag, tag)], &logins) {
+            Ok(()) => log_console(&console_messages, &name, "GitOp", EventKind::Action, format!("Pushed tag {} to {}", tag, remote)),
+            Err(err) => log_console(&console_messages, &name, "System", EventKind::Action, format!("Pushing tag {} failed: {}", tag, err)),
+        });
+    }
+
+    fn show_changelog(&mut self, outgoing: bool) {
+        let repos = self.repos.lock().unwrap();
+        let Some(repo_index) = self.get_selected_repo_index(&repos) else {
+            return;
+        };
+        let repo = &repos[repo_index];
+        if !on_this_machine(repo, &self.console_messages) {
+            return;
+        }
+        if repo.remote_refs.is_some() {
+            log_console(&self.console_messages, &repo.name, "System", EventKind::Action, "Changelogs can't be shown without a clone".to_string());
+            return;
+        }
+        match range_commits(&repo.path, &repo.remote, outgoing, &repo.commits) {
+            Ok((_, commits)) if commits.is_empty() => {
+                let direction = if outgoing { "outgoing" } else { "incoming" };
+                log_console(&self.console_messages, &repo.name, "GitOp", EventKind::Action, format!("No {} commits", direction));
+            }
+            Ok((range, commits)) => {
+                self.popup = Some(Popup::Changelog(Changelog { repo: repo_index, outgoing, range, commits, by_author: false, selected: 0 }));
+            }
+            Err(err) => log_console(&self.console_messages, &repo.name, "System", EventKind::Action, format!("Changelog failed: {}", err)),
+        }
+    }
+    
+    // Written to the working directory so it's easy to find next to wherever gitop was started
+    fn export_changelog(&self, changelog: &Changelog) {
+        let repos = self.repos.lock().unwrap();
+        let Some(repo) = repos.get(changelog.repo) else {
+            return;
+        };
+        let file_name: String = repo
+            .name
+            .chars()
+            .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '-' })
+            .collect();
+        let path = PathBuf::from(format!("{}-{}.md", file_name, changelog.direction()));
+        match std::fs::write(&path, changelog.markdown(&repo.name)) {
+            Ok(()) => log_console(&self.console_messages, &repo.name, "GitOp", EventKind::Action, format!("Changelog written to {}", path.display())),
+            Err(err) => log_console(&self.console_messages, &repo.name, "System", EventKind::Action, format!("Could not write {}: {}", path.display(), err)),
+        }
+    }
+
+    fn confirm_pull_all(&mut self) {
+        let repos = self.repos.lock().unwrap();
+        let mut targets = Vec::new();
+        let mut skipped = Vec::new();
+        
+        for (i, repo) in repos.iter().enumerate() {
+            if repo.behind == 0 || repo.host.is_some() || repo.archived {
+                continue;
+            }
+            if repo.dirty > 0 {
+                skipped.push(repo.name.clone());
+            } else {
+                targets.push(i);
+            }
+        }
+        
+        if targets.is_empty() && skipped.is_empty() {
+            log_console(&self.console_messages, "System", "GitOp", EventKind::Action, "Nothing to pull, all repositories are up to date".to_string());
+            return;
+        }
+        
+        self.popup = Some(Popup::PullAll { targets, skipped });
+    }
+
+    fn request_clone_missing(&mut self) {
+        if !self.confirm_clone_missing() {
+            log_console(&self.console_messages, "System", "GitOp", EventKind::Action, "No missing repositories with a url to clone".to_string());
+        }
+    }
+
+    // Returns false when there is nothing to clone
+    fn confirm_clone_missing(&mut self) -> bool {
+        let repos = self.repos.lock().unwrap();
+        let targets: Vec<usize> = repos
+            .iter()
+            .enumerate()
+            .filter(|(_, repo)| repo.url.is_some() && repo.remote_refs.is_none() && !repo.path.exists())
+            .map(|(i, _)| i)
+            .collect();
+        
+        if targets.is_empty() {
+            return false;
+        }
+        self.popup = Some(Popup::CloneMissing(targets));
+        true
+    }
+
+    fn clone_missing(&mut self, targets: Vec<usize>) {
+        let repos = self.repos.lock().unwrap();
+        for repo in targets.iter().filter_map(|&i| repos.get(i)) {
+            let Some(url) = repo.url.clone() else {
+                continue;
+            };
+            let name = repo.name.clone();
+            let path = repo.path.clone();
+            let remote = repo.remote.clone();
+            let depth = repo.fetch_depth;
+            let logins = repo.logins.clone();
+            let console_messages = self.console_messages.clone();
+            
+            // Monitoring picks the clone up on the next refresh once the path exists
+            log_console(&console_messages, &name, "GitOp", EventKind::Action, format!("Cloning {}...", url));
+            tokio::task::spawn_blocking(move || match clone_repo(&url, &path, &remote, depth, &logins) {
+                Ok(()) => log_console(&console_messages, &name, "GitOp", EventKind::Action, format!("Cloned into {}", path.display())),
+                Err(err) => log_console(&console_messages, &name, "System", EventKind::Action, format!("Clone failed: {}", err)),
+            });
+        }
+    }
+
+    fn pull_all(&mut self, targets: Vec<usize>) {
+        let repos = self.repos.lock().unwrap();
+        let jobs: Vec<RepoStatus> = targets.iter().filter_map(|&i| repos.get(i)).cloned().collect();
+        let console_messages = self.console_messages.clone();
+        
+        // Pull sequentially in one pass so the console reads top to bottom
+        tokio::task::spawn_blocking(move || {
+            let total = jobs.len();
+            let mut pulled = 0;
+            for repo in jobs {
+                // Re-check right before pulling, the tree may have changed since confirmation
+                if !conflicted_files(&repo.path).is_empty() {
+                    log_console(&console_messages, &repo.name, "System", EventKind::Action, "Skipped pull: unresolved conflicts".to_string());
+                    continue;
+                }
+                if count_dirty_files(&repo.path, !repo.ignore_untracked) > 0 {
+                    log_console(&console_messages, &repo.name, "System", EventKind::Action, "Skipped pull: uncommitted changes".to_string());
+                    continue;
+                }
+                match pull_repo(&repo.path, &repo.remote, &repo.refspecs, repo.prune, repo.fetch_depth, Some(&repo.transfer), &repo.logins) {
+                    Ok(message) => {
+                        pulled += 1;
+                        log_console(&console_messages, &repo.name, "GitOp", EventKind::Action, message);
+                    }
+                    Err(err) => log_console(&console_messages, &repo.name, "System", EventKind::Action, format!("Pull failed: {}", err)),
+                }
+            }
+            log_console(&console_messages, "System", "GitOp", EventKind::Action, format!("Pulled {} of {} repositories", pulled, total));
+        });
+    }
+}
+
+// Split a command line into words like a POSIX shell: quotes keep spaces in a word and backslashes escape the
+// next character, except inside single quotes. None for an unclosed quote.
+fn shell_words(line: &str) -> Option<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                let word = word.get_or_insert_default();
+                loop {
+                    match chars.next()? {
+                        '\'' => break,
+                        c => word.push(c),
+                    }
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_default();
+                loop {
+                    match chars.next()? {
+                        '"' => break,
+                        '\\' => match chars.next()? {
+                            c @ ('"' | '\\' | '$' | '`') => word.push(c),
+                            c => word.extend(['\\', c]),
+                        },
+                        c => word.push(c),
+                    }
+                }
+            }
+            '\\' => word.get_or_insert_default().extend(chars.next()),
+            c if c.is_whitespace() => words.extend(word.take()),
+            c => word.get_or_insert_default().push(c),
+        }
+    }
+    words.extend(word);
+    Some(words)
+}
+
+fn table_rows_for(repo: &RepoStatus) -> usize {
+    match (&repo.remote_refs, repo.expanded) {
+        (Some(refs), true) => 1 + refs.branches.len(), // Repository row plus a row per branch
+        (None, true) => 1 + repo.recent_commits.len(), // Repository row plus commit rows
+        (_, false) => 1,
+    }
+}
+
+// Whether a host header row goes above this position; headers only appear once an agent has reported
+fn starts_group(repos: &[RepoStatus], order: &[usize], position: usize) -> bool {
+    let group = |index: usize| (repos[index].archived, &repos[index].host);
+    match position {
+        0 => repos.iter().any(|repo| repo.host.is_some()) || repos[order[0]].archived,
+        _ => group(order[position - 1]) != group(order[position]),
+    }
+}
+
+fn calculate_table_row(repos: &[RepoStatus], order: &[usize], position: usize) -> usize {
+    let repo_rows: usize = order[..position].iter().map(|&i| table_rows_for(&repos[i])).sum();
+    let header_rows = (0..=position.min(order.len().saturating_sub(1)))
+        .filter(|&i| starts_group(repos, order, i))
+        .count();
+    repo_rows + header_rows
+}
+
+// Terminal lines above the repository at this position, for paging; a description makes a row two lines tall
+fn calculate_table_line(repos: &[RepoStatus], order: &[usize], position: usize, show_descriptions: bool) -> usize {
+    let descriptions = order[..position].iter().filter(|&&i| show_descriptions && repos[i].description.is_some()).count();
+    calculate_table_row(repos, order, position) + descriptions
+}
+
+// Repositories reported by an agent live on another machine, so their path means nothing here. Says so for
+// them, and local git views and actions bail out.
+fn on_this_machine(repo: &RepoStatus, console_messages: &Arc<Mutex<Vec<ConsoleMessage>>>) -> bool {
+    match &repo.host {
+        Some(host) => {
+            log_console(console_messages, &console_name(repo), "System", EventKind::Action, format!("Only the status is known here, the repository is on {}", host));
+            false
+        }
+        None => true,
+    }
+}
+
+// Name console messages use for a repository; messages relayed from agents are prefixed with their host
+fn console_name(repo: &RepoStatus) -> String {
+    match &repo.host {
+        Some(host) => format!("{}:{}", host, repo.name),
+        None => repo.name.clone(),
+    }
+}
+
+fn log_console(console_messages: &Arc<Mutex<Vec<ConsoleMessage>>>, repo: &str, author: &str, kind: EventKind, message: String) {
+    let mut console_guard = console_messages.lock().unwrap();
+    console_guard.push(ConsoleMessage {
+        timestamp: Utc::now(),
+        repo: repo.to_string(),
+        author: author.to_string(),
+        kind,
+        message,
+    });
+    // Keep only last 50 messages
+    let len = console_guard.len();
+    if len > 50 {
+        console_guard.drain(0..len - 50);
+    }
+}
+
+fn get_config_path(custom_path: Option<PathBuf>) -> PathBuf {
+    // Use custom path if provided
+    if let Some(path) = custom_path {
+        return path;
+    }
+    
+    // Try multiple locations in order of preference:
+    
+    // 1. Current directory (project-specific config) - check but don't prefer
+    let local_config = PathBuf::from("gitop.toml");
+    
+    // 2. User config directory (Linux/macOS: ~/.config/gitop/gitop.toml, Windows: %APPDATA%\gitop\gitop.toml)
+    if let Some(config_dir) = config_dir() {
+        let user_config = config_dir.join("gitop").join("gitop.toml");
+        
+        // Prefer global config, but fall back to local if global doesn't exist and local does
+        if user_config.exists() || !local_config.exists() {
+            return user_config;
+        }
+    }
+    
+    // 3. Fallback to current directory
+    local_config
+}
+
+fn create_default_config(config_path: &PathBuf) -> Result<()> {
+    write_config(config_path, &Config::default())?;
+    
+    println!("Created default config at: {}", config_path.display());
+    Ok(())
+}
+
+fn write_config(config_path: &PathBuf, config: &Config) -> Result<()> {
+    // Create parent directory if it doesn't exist
+    if let Some(parent) = config_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    
+    let config_content = toml::to_string_pretty(config)?;
     std::fs::write(config_path, config_content)?;
+    Ok(())
+}
+
+fn ask(question: &str, default: &str) -> Result<String> {
+    use std::io::Write;
+    
+    if default.is_empty() {
+        print!("{}: ", question);
+    } else {
+        print!("{} [{}]: ", question, default);
+    }
+    io::stdout().flush()?;
+    
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    let answer = answer.trim();
+    Ok(if answer.is_empty() { default.to_string() } else { answer.to_string() })
+}
+
+// Like `ask`, without echoing what's typed when stdin is a terminal
+fn ask_secret(question: &str) -> Result<String> {
+    use std::io::{IsTerminal, Write};
+    
+    if !io::stdin().is_terminal() {
+        let mut answer = String::new();
+        io::stdin().read_line(&mut answer)?;
+        return Ok(answer.trim_end_matches(['\r', '\n']).to_string());
+    }
+    
+    print!("{}: ", question);
+    io::stdout().flush()?;
+    enable_raw_mode()?;
+    let mut answer = String::new();
+    let result = loop {
+        match event::read() {
+            Ok(Event::Key(key)) => match key.code {
+                KeyCode::Enter => break Ok(()),
+                KeyCode::Esc => break Err(anyhow::anyhow!("Cancelled")),
+                KeyCode::Char('c') if key.modifiers.contains(event::KeyModifiers::CONTROL) => break Err(anyhow::anyhow!("Cancelled")),
+                KeyCode::Char(c) => answer.push(c),
+                KeyCode::Backspace => {
+                    answer.pop();
+                }
+                _ => {}
+            },
+            Ok(_) => {}
+            Err(err) => break Err(err.into()),
+        }
+    };
+    disable_raw_mode()?;
+    println!();
+    result.map(|_| answer)
+}
+
+fn split_list(answer: &str) -> Vec<String> {
+    answer
+        .split(',')
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+fn run_init_wizard(max_depth: usize) -> Result<Config> {
+    println!("GitOp setup - press Enter to accept the [default]\n");
+    
+    let refresh_interval = loop {
+        match ask("Refresh interval in seconds", "5")?.parse::<u64>() {
+            Ok(seconds) if seconds > 0 => break seconds,
+            _ => println!("  Please enter a whole number of seconds above 0"),
+        }
+    };
+    let max_commits = loop {
+        match ask("Commits to show when a repository is expanded", "5")?.parse::<usize>() {
+            Ok(count) => break count,
+            _ => println!("  Please enter a whole number"),
+        }
+    };
+    
+    // Repository discovery
+    let mut repositories = Vec::new();
+    let roots = split_list(&ask("Directories to scan for repositories (comma separated)", ".")?);
+    for root in roots {
+        let root = expand_path(&root);
+        let found = scan_for_repositories(&root, max_depth);
+        println!("  Found {} repositories under {}", found.len(), root.display());
+        for repo in found {
+            if !repositories.iter().any(|existing: &RepoConfig| existing.path == repo.path) {
+                repositories.push(repo);
+            }
+        }
+    }
+    if repositories.is_empty() {
+        println!("  No repositories found, adding the current directory");
+        repositories = Config::default().repositories;
+    }
+    
+    // Notification preferences
+    let mute_labels = split_list(&ask("Labels whose repositories are never notified about (comma separated)", "")?);
+    let show_descriptions = ask("Show repository descriptions under names? (y/n)", "n")?
+        .to_lowercase()
+        .starts_with('y');
+    
+    // Theme
+    println!("\nColor themes:");
+    println!("  1) default        - yellow ahead, cyan behind");
+    println!("  2) high-contrast  - light red ahead, light green behind, bright names");
+    println!("  3) monochrome     - terminal default colors");
+    let theme = match ask("Theme", "1")?.as_str() {
+        "2" | "high-contrast" => "high-contrast",
+        "3" | "monochrome" => "monochrome",
+        _ => "default",
+    };
+    
+    Ok(Config {
+        repositories,
+        refresh_interval,
+        max_commits,
+        colors: Some(ColorConfig {
+            theme: Some(theme.to_string()),
+            ..Default::default()
+        }),
+        show_descriptions: show_descriptions.then_some(true),
+        show_size: None,
+        show_fetch_time: None,
+        show_diffstat: None,
+        persist_commit_cache: None,
+        search_depth: None,
+        author_colors: None,
+        console_format: None,
+        highlights: None,
+        flash: None,
+        mute_labels: (!mute_labels.is_empty()).then_some(mute_labels),
+        adaptive_refresh: None,
+        metered: None,
+        focus_interval: None,
+        agent_listen: None,
+        webhook_listen: None,
+        agent_token: None,
+        rebase_command: None,
+        worktree_dir: None,
+        prune: None,
+        maintenance_interval: None,
+        size_warning: None,
+        expected_email: None,
+        large_file_warning: None,
+        credentials: None,
+        notifications: None,
+        icons: None,
+        layout: None,
+    })
+}
+
+// Find git repositories under root, without descending into the repositories themselves
+fn scan_for_repositories(root: &Path, max_depth: usize) -> Vec<RepoConfig> {
+    let mut found = Vec::new();
+    let mut pending = vec![(root.to_path_buf(), 0)];
+    
+    while let Some((dir, depth)) = pending.pop() {
+        if dir.join(".git").exists() {
+            found.push(dir);
+            continue;
+        }
+        if depth >= max_depth {
+            continue;
+        }
+        
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let hidden = entry.file_name().to_string_lossy().starts_with('.');
+            if !hidden && entry.file_type().is_ok_and(|t| t.is_dir()) {
+                pending.push((path, depth + 1));
+            }
+        }
+    }
+    found.sort();
+    
+    let mut repositories: Vec<RepoConfig> = Vec::new();
+    for path in found {
+        let mut name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.display().to_string());
+        // Disambiguate same-named checkouts by their path relative to the scan root
+        if repositories.iter().any(|repo| repo.name == name) {
+            name = path.strip_prefix(root).unwrap_or(&path).display().to_string();
+        }
+        
+        repositories.push(RepoConfig {
+            name,
+            path: display_path(&path),
+            remote: Some(detect_remote(&path)),
+            ..Default::default()
+        });
+    }
+    repositories
+}
+
+// Absolute path for the config file, written with "~/" when it lives under the home directory
+fn display_path(path: &Path) -> String {
+    let home = home_dir();
+    let absolute = path.canonicalize().map(strip_verbatim).unwrap_or_else(|_| path.to_path_buf());
+    match home.as_ref().and_then(|home| absolute.strip_prefix(home).ok()) {
+        Some(relative) => format!("~/{}", relative.display()),
+        None => absolute.display().to_string(),
+    }
+}
+
+// Prefer origin, otherwise whatever remote the repository has
+fn detect_remote(path: &Path) -> String {
+    Repository::open(path)
+        .ok()
+        .and_then(|repo| {
+            let remotes = repo.remotes().ok()?;
+            if remotes.iter().flatten().any(|r| r == "origin") {
+                Some("origin".to_string())
+            } else {
+                remotes.get(0).map(str::to_string)
+            }
+        })
+        .unwrap_or_else(|| "origin".to_string())
+}
+
+fn remote_urls(path: &Path) -> Vec<(String, String)> {
+    let Ok(repo) = Repository::open(path) else {
+        return Vec::new();
+    };
+    let Ok(remotes) = repo.remotes() else {
+        return Vec::new();
+    };
+    remotes
+        .iter()
+        .flatten()
+        .filter_map(|name| {
+            let remote = repo.find_remote(name).ok()?;
+            Some((name.to_string(), remote.url()?.to_string()))
+        })
+        .collect()
+}
+
+type SyncResult = (Vec<RepoConfig>, Vec<forge::ForgeRepo>, Option<forge::RateLimit>);
+
+fn sync_github(config: &Config, owner: &forge::GithubOwner, root: &Path, max_depth: usize) -> Result<SyncResult> {
+    let token = match config.credentials.as_ref().and_then(|credentials| credentials.github_token.as_deref()) {
+        Some(reference) => Some(secrets::resolve(reference).context("Failed to read credentials.github_token")?),
+        None => forge::github_token(),
+    };
+    let (forge_repos, rate_limit) = forge::list_github_repos(owner, token.as_deref(), |limit| {
+        println!("GitHub API rate limit nearly used up ({}), waiting", limit.describe());
+    })?;
+    
+    // Local clones keyed by the GitHub slug of each of their remotes
+    let mut clones: Vec<(String, String, RepoConfig)> = Vec::new();
+    for repo in scan_for_repositories(root, max_depth) {
+        for (remote, url) in remote_urls(&expand_path(&repo.path)) {
+            if let Some(slug) = forge::github_slug(&url) {
+                clones.push((slug, remote, repo.clone()));
+            }
+        }
+    }
+    
+    let configured: Vec<PathBuf> = config
+        .repositories
+        .iter()
+        .map(|repo| expand_path(&repo.path).canonicalize().unwrap_or_else(|_| expand_path(&repo.path)))
+        .collect();
+    
+    let mut added: Vec<RepoConfig> = Vec::new();
+    let mut missing = Vec::new();
+    for forge_repo in forge_repos {
+        let slug = forge_repo.full_name.to_lowercase();
+        let Some((_, remote, clone)) = clones.iter().find(|(clone_slug, _, _)| *clone_slug == slug) else {
+            missing.push(forge_repo);
+            continue;
+        };
+        
+        let path = expand_path(&clone.path);
+        let path = path.canonicalize().unwrap_or(path);
+        if configured.contains(&path) || added.iter().any(|repo| repo.path == clone.path) {
+            continue;
+        }
+        added.push(RepoConfig {
+            name: forge_repo.name.clone(),
+            remote: Some(remote.clone()),
+            ..clone.clone()
+        });
+    }
+    Ok((added, missing, rate_limit))
+}
+
+// XDG_CONFIG_HOME, %APPDATA% on Windows, otherwise ~/.config
+fn config_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .or_else(|| if cfg!(windows) { std::env::var_os("APPDATA") } else { None })
+        .map(PathBuf::from)
+        .or_else(|| home_dir().map(|home| home.join(".config")))
+}
+
+// ~/.cache/gitop (%LOCALAPPDATA%\gitop on Windows), for what can be rebuilt and is safe to delete
+fn cache_dir() -> Option<PathBuf> {
+    let cache = std::env::var_os("XDG_CACHE_HOME")
+        .or_else(|| if cfg!(windows) { std::env::var_os("LOCALAPPDATA") } else { None })
+        .map(PathBuf::from)
+        .or_else(|| home_dir().map(|home| home.join(".cache")))?;
+    Some(cache.join("gitop"))
+}
+
+// ~/.local/state/gitop (%LOCALAPPDATA%\gitop on Windows), for what a running gitop leaves for other commands
+fn state_dir() -> Option<PathBuf> {
+    let state = std::env::var_os("XDG_STATE_HOME")
+        .or_else(|| if cfg!(windows) { std::env::var_os("LOCALAPPDATA") } else { None })
+        .map(PathBuf::from)
+        .or_else(|| home_dir().map(|home| home.join(".local").join("state")))?;
+    Some(state.join("gitop"))
+}
+
+// `gitop paths`: every file and directory gitop reads or writes, whether or not it exists yet
+fn print_paths(config_path: &Path, persist_commit_cache: bool) {
+    let show = |path: Option<PathBuf>| path.map_or_else(|| "(no home directory)".to_string(), |path| display_path(&path));
+    let commit_cache = if persist_commit_cache { "" } else { "  (unused, persist_commit_cache is off)" };
+    println!("config        {}", display_path(config_path));
+    println!("state         {}", show(prompt::state_path()));
+    println!("commit cache  {}{}", show(commits::cache_root()), commit_cache);
+    println!("control       {}", show(control::socket_path()));
+}
+
+// canonicalize() on Windows returns extended-length paths ("\\?\C:\..."), which never start with the
+// home directory or a configured path and look odd in the UI; ones with a drive letter drop the prefix
+fn strip_verbatim(path: PathBuf) -> PathBuf {
+    match path.to_str().and_then(|text| text.strip_prefix(r"\\?\")) {
+        Some(rest) if rest.as_bytes().get(1) == Some(&b':') => PathBuf::from(rest),
+        _ => path,
+    }
+}
+
+// Read another tool's registry and turn it into (name, path) pairs
+fn import_repositories(source: ImportSource, file: Option<PathBuf>) -> Result<Vec<RepoConfig>> {
+    let read = |default: Option<PathBuf>| -> Result<(PathBuf, String)> {
+        let path = file
+            .clone()
+            .or(default)
+            .ok_or_else(|| anyhow::anyhow!("No registry file given and no default location for {:?}", source))?;
+        let content = std::fs::read_to_string(&path)
+            .map_err(|err| anyhow::anyhow!("Could not read {}: {}", path.display(), err))?;
+        Ok((path, content))
+    };
+    
+    let mut entries: Vec<(Option<String>, PathBuf)> = Vec::new();
+    match source {
+        ImportSource::Ghq => {
+            let content = match &file {
+                Some(_) => read(None)?.1,
+                None => {
+                    let output = std::process::Command::new("ghq").args(["list", "--full-path"]).output()
+                        .map_err(|err| anyhow::anyhow!("Could not run ghq: {}", err))?;
+                    if !output.status.success() {
+                        anyhow::bail!("ghq list failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+                    }
+                    String::from_utf8_lossy(&output.stdout).to_string()
+                }
+            };
+            entries.extend(content.lines().map(str::trim).filter(|l| !l.is_empty()).map(|l| (None, PathBuf::from(l))));
+        }
+        ImportSource::Gita => {
+            // Each line is "path,name,flags,..."
+            let (_, content) = read(config_dir().map(|dir| dir.join("gita").join("repos.csv")))?;
+            for line in content.lines().filter(|l| !l.trim().is_empty()) {
+                let mut fields = line.split(',');
+                let path = fields.next().unwrap_or_default().trim();
+                let name = fields.next().map(str::trim).filter(|name| !name.is_empty());
+                entries.push((name.map(str::to_string), PathBuf::from(path)));
+            }
+        }
+        ImportSource::MuRepo => {
+            // "repo=<path>" lines, relative to the directory holding .mu_repo
+            let (path, content) = read(Some(PathBuf::from(".mu_repo")))?;
+            let base = path.parent().map(Path::to_path_buf).unwrap_or_default();
+            for line in content.lines() {
+                if let Some(repo) = line.trim().strip_prefix("repo=") {
+                    entries.push((None, base.join(repo.trim())));
+                }
+            }
+        }
+        ImportSource::Vcsh => {
+            let repo_dir = match &file {
+                Some(dir) => dir.clone(),
+                None => config_dir()
+                    .map(|dir| dir.join("vcsh").join("repo.d"))
+                    .ok_or_else(|| anyhow::anyhow!("Could not determine the vcsh repo.d directory"))?,
+            };
+            for entry in std::fs::read_dir(&repo_dir)
+                .map_err(|err| anyhow::anyhow!("Could not read {}: {}", repo_dir.display(), err))?
+                .flatten()
+            {
+                let file_name = entry.file_name().to_string_lossy().to_string();
+                if let Some(name) = file_name.strip_suffix(".git") {
+                    entries.push((Some(name.to_string()), entry.path()));
+                }
+            }
+        }
+        ImportSource::Paths => {
+            if file.is_none() {
+                anyhow::bail!("--from paths needs a file with one repository path per line");
+            }
+            let (_, content) = read(None)?;
+            entries.extend(
+                content
+                    .lines()
+                    .map(str::trim)
+                    .filter(|l| !l.is_empty() && !l.starts_with('#'))
+                    .map(|l| (None, expand_path(l))),
+            );
+        }
+    }
+    
+    let mut repositories: Vec<RepoConfig> = Vec::new();
+    for (name, path) in entries {
+        if Repository::open(&path).is_err() {
+            eprintln!("  Skipping {}: not a git repository", path.display());
+            continue;
+        }
+        let name = name.unwrap_or_else(|| {
+            path.file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| path.display().to_string())
+        });
+        repositories.push(RepoConfig {
+            name,
+            path: display_path(&path),
+            remote: Some(detect_remote(&path)),
+            ..Default::default()
+        });
+    }
+    Ok(repositories)
+}
+
+// Append [[repositories]] entries, keeping the rest of the file (comments included) intact
+fn append_repos_to_config(config_path: &PathBuf, repo_configs: &[RepoConfig]) -> Result<()> {
+    if !config_path.exists() {
+        create_default_config(config_path)?;
+    }
+    
+    let content = std::fs::read_to_string(config_path)?;
+    let mut document = content.parse::<toml_edit::DocumentMut>()?;
+    let repositories = document["repositories"]
+        .or_insert(toml_edit::Item::ArrayOfTables(toml_edit::ArrayOfTables::new()))
+        .as_array_of_tables_mut()
+        .ok_or_else(|| anyhow::anyhow!("\"repositories\" in {} is not an array of tables", config_path.display()))?;
+    
+    for repo_config in repo_configs {
+        // Round-trip through serde so every RepoConfig field is written
+        let entry = toml::to_string(repo_config)?.parse::<toml_edit::DocumentMut>()?;
+        repositories.push(entry.as_table().clone());
+    }
+    
+    std::fs::write(config_path, document.to_string())?;
+    Ok(())
+}
+
+// Set or, with None, remove a key of the [[repositories]] entry named `name` in place
+fn set_repo_config_value(config_path: &PathBuf, name: &str, key: &str, value: Option<toml_edit::Item>) -> Result<()> {
+    let content = std::fs::read_to_string(config_path)?;
+    let mut document = content.parse::<toml_edit::DocumentMut>()?;
+    let entry = document
+        .get_mut("repositories")
+        .and_then(|repositories| repositories.as_array_of_tables_mut())
+        .and_then(|repositories| repositories.iter_mut().find(|entry| entry.get("name").and_then(|item| item.as_str()) == Some(name)))
+        .ok_or_else(|| anyhow::anyhow!("no [[repositories]] entry named \"{}\" in {}", name, config_path.display()))?;
+    match value {
+        Some(value) => entry[key] = value,
+        None => {
+            entry.remove(key);
+        }
+    }
+    std::fs::write(config_path, document.to_string())?;
+    Ok(())
+}
+
+// Set a top-level key in place, keeping the rest of the file's formatting and comments
+fn set_config_value(config_path: &PathBuf, key: &str, value: toml_edit::Item) -> Result<()> {
+    if !config_path.exists() {
+        create_default_config(config_path)?;
+    }
+    
+    let content = std::fs::read_to_string(config_path)?;
+    let mut document = content.parse::<toml_edit::DocumentMut>()?;
+    document[key] = value;
+    std::fs::write(config_path, document.to_string())?;
+    Ok(())
+}
+
+fn load_config(config_path: Option<PathBuf>) -> Result<Config> {
+    let config_path = get_config_path(config_path);
+    
+    if config_path.exists() {
+        let content = std::fs::read_to_string(&config_path)?;
+        Ok(toml::from_str(&content)?)
+    } else {
+        // Return default config without creating file
+        Ok(Config::default())
+    }
+}
+
+// Credentials from ssh-agent, the config's HTTPS logins, git credential helpers or the platform default
+fn remote_callbacks(logins: &[Login]) -> RemoteCallbacks<'_> {
+    let mut callbacks = RemoteCallbacks::new();
+    callbacks.credentials(|url, username, allowed| {
+        if allowed.contains(CredentialType::SSH_KEY) {
+            return Cred::ssh_key_from_agent(username.unwrap_or("git"));
+        }
+        if allowed.contains(CredentialType::USER_PASS_PLAINTEXT) {
+            if let Some(login) = logins.iter().find(|login| login.matches(url)) {
+                return Cred::userpass_plaintext(&login.username, &login.password);
+            }
+            let config = git2::Config::open_default()?;
+            return Cred::credential_helper(&config, url, username);
+        }
+        Cred::default()
+    });
+    callbacks
+}
+
+fn clone_repo(url: &str, path: &Path, remote: &str, depth: Option<u32>, logins: &[Login]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    
+    let mut fetch_options = fetch_options(false, depth);
+    fetch_options.remote_callbacks(remote_callbacks(logins));
+    RepoBuilder::new()
+        .fetch_options(fetch_options)
+        .remote_create(|repo, _, url| repo.remote(remote, url))
+        .clone(url, path)?;
+    Ok(())
+}
+
+fn conflicted_paths(index: &git2::Index) -> Result<Vec<String>> {
+    Ok(index
+        .conflicts()?
+        .flatten()
+        .filter_map(|conflict| conflict.our.or(conflict.their))
+        .map(|entry| String::from_utf8_lossy(&entry.path).to_string())
+        .collect())
+}
+
+// Apply a commit on top of HEAD, keeping its author; refuses to touch a tree with uncommitted changes
+fn cherry_pick_commit(path: &PathBuf, hash: &str) -> Result<String> {
+    if count_dirty_files(path, true) > 0 {
+        anyhow::bail!("uncommitted changes, commit or stash them first");
+    }
+    let repo = Repository::open(path)?;
+    let commit = repo.revparse_single(hash)?.peel_to_commit()?;
+    repo.cherrypick(&commit, None)?;
+    
+    let mut index = repo.index()?;
+    if index.has_conflicts() {
+        anyhow::bail!("conflicts in {}, resolve them and commit, or run `git cherry-pick --abort`", conflicted_paths(&index)?.join(", "));
+    }
+    
+    let tree = repo.find_tree(index.write_tree()?)?;
+    let head = repo.head()?.peel_to_commit()?;
+    let committer = repo.signature().context("Set user.name and user.email to cherry-pick")?;
+    let oid = repo.commit(Some("HEAD"), &commit.author(), &committer, commit.message().unwrap_or(""), &tree, &[&head])?;
+    repo.cleanup_state()?;
+    Ok(format!("Cherry-picked {} as {:.8}: {}", hash, oid, commit.summary().unwrap_or("")))
+}
+
+// Commit the inverse of a commit on top of HEAD, with git's usual "Revert" message
+fn revert_commit(path: &PathBuf, hash: &str) -> Result<String> {
+    if count_dirty_files(path, true) > 0 {
+        anyhow::bail!("uncommitted changes, commit or stash them first");
+    }
+    let repo = Repository::open(path)?;
+    let commit = repo.revparse_single(hash)?.peel_to_commit()?;
+    repo.revert(&commit, None)?;
+    
+    let mut index = repo.index()?;
+    if index.has_conflicts() {
+        anyhow::bail!("conflicts in {}, resolve them and commit, or run `git revert --abort`", conflicted_paths(&index)?.join(", "));
+    }
+    
+    let tree = repo.find_tree(index.write_tree()?)?;
+    let head = repo.head()?.peel_to_commit()?;
+    let signature = repo.signature().context("Set user.name and user.email to revert")?;
+    let summary = commit.summary().unwrap_or("");
+    let message = format!("Revert \"{}\"\n\nThis reverts commit {}.\n", summary, commit.id());
+    let oid = repo.commit(Some("HEAD"), &signature, &signature, &message, &tree, &[&head])?;
+    repo.cleanup_state()?;
+    Ok(format!("Reverted {} as {:.8}: {}", hash, oid, summary))
+}
+
+// The remote's default branch as a remote-tracking ref, falling back to a local main or master
+fn default_branch(repo: &Repository, remote: &str) -> Option<(String, git2::Oid)> {
+    let remote_head = repo
+        .find_reference(&format!("refs/remotes/{}/HEAD", remote))
+        .ok()
+        .and_then(|head| head.symbolic_target().map(str::to_string));
+    let candidates = remote_head.into_iter().chain(
+        ["main", "master"]
+            .iter()
+            .flat_map(|name| [format!("refs/remotes/{}/{}", remote, name), format!("refs/heads/{}", name)]),
+    );
+    for name in candidates {
+        if let Ok(reference) = repo.find_reference(&name)
+            && let Ok(commit) = reference.peel_to_commit()
+        {
+            return Some((reference.shorthand().unwrap_or(&name).to_string(), commit.id()));
+        }
+    }
+    None
+}
+
+// For fork workflows: the current branch against the same branch and the default branch on every remote, as
+// of each remote's last fetch. Only the monitored remote is fetched by gitop, so the rest may be stale.
+fn remote_matrix(path: &PathBuf, monitored: &str) -> Result<Vec<String>> {
+    let repo = Repository::open(path)?;
+    let head = repo.head()?;
+    if !head.is_branch() {
+        anyhow::bail!("HEAD is detached");
+    }
+    let branch = head.shorthand().unwrap_or("HEAD").to_string();
+    let tip = head.peel_to_commit()?.id();
+    
+    let compare = |reference: &str| -> Result<Option<(usize, usize)>> {
+        match repo.find_reference(reference).and_then(|reference| reference.peel_to_commit()) {
+            Ok(commit) => Ok(Some(repo.graph_ahead_behind(tip, commit.id())?)),
+            Err(_) => Ok(None),
+        }
+    };
+    let cell = |counts: Option<(usize, usize)>| match counts {
+        None => "-".to_string(),
+        Some((0, 0)) => "in sync".to_string(),
+        Some((ahead, behind)) => format!("↑{} ↓{}", ahead, behind),
+    };
+    
+    let remotes = repo.remotes()?;
+    let width = remotes.iter().flatten().map(|remote| remote.width() + 2).max().unwrap_or(0).max("Remote".len());
+    let mut lines = vec![format!("{}  {}  {}", pad_width("Remote", width, '<'), pad_width(&branch, 14, '<'), "Default branch")];
+    let mut sync_hint = None;
+    for remote in remotes.iter().flatten() {
+        let same = compare(&format!("refs/remotes/{}/{}", remote, branch))?;
+        // The remote's HEAD when it was cloned or `git remote set-head` ran, otherwise main or master
+        let remote_head = repo
+            .find_reference(&format!("refs/remotes/{}/HEAD", remote))
+            .ok()
+            .and_then(|head| head.symbolic_target().map(str::to_string));
+        let default = remote_head
+            .into_iter()
+            .chain(["main", "master"].iter().map(|name| format!("refs/remotes/{}/{}", remote, name)))
+            .find(|name| repo.find_reference(name).is_ok());
+        let default_cell = match &default {
+            Some(name) => {
+                let counts = compare(name)?;
+                let short = name.trim_start_matches("refs/remotes/");
+                if remote != monitored
+                    && let Some((_, behind)) = counts
+                    && behind > 0
+                {
+                    sync_hint.get_or_insert_with(|| format!("{} is {} commits behind {}, the fork needs a sync", branch, behind, short));
+                }
+                format!("{}: {}", short.trim_start_matches(&format!("{}/", remote)), cell(counts))
+            }
+            None => "-".to_string(),
+        };
+        let marker = if remote == monitored { " *" } else { "" };
+        lines.push(format!("{}  {}  {}", pad_width(&format!("{}{}", remote, marker), width, '<'), pad_width(&cell(same), 14, '<'), default_cell));
+    }
+    lines.push(String::new());
+    lines.push(sync_hint.unwrap_or_else(|| "Nothing to sync from the other remotes".to_string()));
+    lines.push("* monitored; the other remotes are as of their last `git fetch`".to_string());
+    Ok(lines)
+}
+
+// Local branches merged into the default branch or whose upstream was deleted, never the checked-out ones
+fn stale_branches(path: &PathBuf, remote: &str) -> Result<(String, Vec<StaleBranch>)> {
+    let repo = Repository::open(path)?;
+    let (default_name, default_tip) = default_branch(&repo, remote).context("no main or master branch to compare with")?;
+    let default_local = default_name.strip_prefix(&format!("{}/", remote)).unwrap_or(&default_name).to_string();
+    
+    // Branches checked out here or in a linked worktree can't be deleted
+    let mut checked_out: Vec<String> = repo.head().ok().and_then(|head| head.shorthand().map(str::to_string)).into_iter().collect();
+    for name in repo.worktrees()?.iter().flatten() {
+        if let Ok(worktree) = repo.find_worktree(name)
+            && let Ok(worktree_repo) = Repository::open_from_worktree(&worktree)
+            && let Ok(head) = worktree_repo.head()
+        {
+            checked_out.extend(head.shorthand().map(str::to_string));
+        }
+    }
+    
+    let mut stale = Vec::new();
+    for branch in repo.branches(Some(git2::BranchType::Local))? {
+        let (branch, _) = branch?;
+        let Some(name) = branch.name()?.map(str::to_string) else {
+            continue;
+        };
+        if name == default_local || checked_out.contains(&name) {
+            continue;
+        }
+        let Some(tip) = branch.get().target() else {
+            continue;
+        };
+        // Unmerged branches may hold the only copy of their commits, even when their upstream is gone
+        if tip != default_tip && !repo.graph_descendant_of(default_tip, tip)? {
+            continue;
+        }
+        // An upstream that's configured but has no remote-tracking ref was deleted on the server
+        let upstream_gone = repo.branch_upstream_name(&format!("refs/heads/{}", name)).is_ok() && branch.upstream().is_err();
+        stale.push(StaleBranch { name, tip, upstream_gone });
+    }
+    Ok((default_name, stale))
+}
+
+// All local branches, most recently committed first
+fn branch_overview(path: &PathBuf, remote: &str) -> Result<(Option<String>, Vec<BranchInfo>)> {
+    let repo = Repository::open(path)?;
+    let default = default_branch(&repo, remote);
+    let head = repo.head().ok().filter(|head| head.is_branch()).and_then(|head| head.shorthand().map(str::to_string));
+    
+    let mut branches = Vec::new();
+    for branch in repo.branches(Some(git2::BranchType::Local))? {
+        let (branch, _) = branch?;
+        let Some(name) = branch.name()?.map(str::to_string) else {
+            continue;
+        };
+        let commit = branch.get().peel_to_commit()?;
+        let tip = commit.id();
+        
+        let configured = repo.branch_upstream_name(&format!("refs/heads/{}", name)).ok();
+        let (upstream, upstream_gone, ahead, behind) = match branch.upstream() {
+            Ok(upstream) => {
+                let upstream_name = upstream.name()?.unwrap_or("?").to_string();
+                let (ahead, behind) = match upstream.get().target() {
+                    Some(upstream_tip) => repo.graph_ahead_behind(tip, upstream_tip)?,
+                    None => (0, 0),
+                };
+                (Some(upstream_name), false, ahead, behind)
+            }
+            Err(_) => {
+                // Still name the upstream that was deleted on the server
+                let gone = configured.as_ref().and_then(|name| name.as_str()).map(|name| name.trim_start_matches("refs/remotes/").to_string());
+                (gone.clone(), gone.is_some(), 0, 0)
+            }
+        };
+        let base = match &default {
+            Some((_, default_tip)) => Some(repo.graph_ahead_behind(tip, *default_tip)?),
+            None => None,
+        };
+        
+        branches.push(BranchInfo {
+            current: head.as_deref() == Some(name.as_str()),
+            name,
+            upstream,
+            upstream_gone,
+            ahead,
+            behind,
+            last_commit: DateTime::from_timestamp(commit.time().seconds(), 0).unwrap_or_else(Utc::now),
+            base,
+        });
+    }
+    branches.sort_by_key(|branch| std::cmp::Reverse(branch.last_commit));
+    Ok((default.map(|(name, _)| name), branches))
+}
+
+// Like `git switch`, but only with a clean work tree so nothing has to be merged
+fn switch_branch(path: &PathBuf, branch: &str) -> Result<()> {
+    if count_dirty_files(path, true) > 0 {
+        anyhow::bail!("uncommitted changes, commit or stash them first");
+    }
+    let repo = Repository::open(path)?;
+    let reference = repo.find_branch(branch, git2::BranchType::Local)?.into_reference();
+    let name = reference.name().context("branch name is not UTF-8")?;
+    repo.checkout_tree(&reference.peel(git2::ObjectType::Commit)?, Some(CheckoutBuilder::new().safe()))?;
+    repo.set_head(name)?;
+    Ok(())
+}
+
+// Check out a branch in a new worktree; a branch that only exists on the remote gets a local branch tracking it
+fn create_worktree(path: &PathBuf, remote: &str, branch: &str, target: &Path) -> Result<()> {
+    if target.exists() {
+        anyhow::bail!("{} already exists", target.display());
+    }
+    let repo = Repository::open(path)?;
+    let local = match repo.find_branch(branch, git2::BranchType::Local) {
+        Ok(local) => local,
+        Err(_) => {
+            let upstream = format!("{}/{}", remote, branch);
+            let commit = repo
+                .find_branch(&upstream, git2::BranchType::Remote)
+                .map_err(|_| anyhow::anyhow!("No branch {} or {}", branch, upstream))?
+                .get()
+                .peel_to_commit()?;
+            let mut local = repo.branch(branch, &commit, false)?;
+            local.set_upstream(Some(&upstream))?;
+            local
+        }
+    };
+    
+    // Worktree names can't contain slashes, unlike branch names
+    let name = branch.replace('/', "-");
+    let mut options = git2::WorktreeAddOptions::new();
+    options.reference(Some(local.get()));
+    repo.worktree(&name, target, Some(&options))?;
+    Ok(())
+}
+
+// Annotated tag on HEAD signed off by the repository's configured identity
+fn create_tag(path: &PathBuf, name: &str, message: &str) -> Result<git2::Oid> {
+    let repo = Repository::open(path)?;
+    let target = repo.head()?.peel(git2::ObjectType::Commit)?;
+    let tagger = repo.signature().context("Set user.name and user.email to create tags")?;
+    repo.tag(name, &target, &tagger, message, false)?;
+    Ok(target.id())
+}
+
+// Push refspecs as given, failing if the remote rejects any of them
+fn push_refs(path: &PathBuf, remote: &str, refspecs: &[String], logins: &[Login]) -> Result<()> {
+    let repo = Repository::open(path)?;
+    let rejected = std::cell::RefCell::new(Vec::new());
+    let mut callbacks = remote_callbacks(logins);
+    callbacks.push_update_reference(|refname, status| {
+        if let Some(status) = status {
+            rejected.borrow_mut().push(format!("{} ({})", refname, status));
+        }
+        Ok(())
+    });
+    let mut push_options = PushOptions::new();
+    push_options.remote_callbacks(callbacks);
+    repo.find_remote(remote)?.push(refspecs, Some(&mut push_options))?;
+    drop(push_options);
+    
+    let rejected = rejected.into_inner();
+    if !rejected.is_empty() {
+        anyhow::bail!("rejected: {}", rejected.join(", "));
+    }
+    Ok(())
+}
+
+// Force-push every local branch and tag to the backup remote, returning how many refs were pushed
+fn push_backup(path: &PathBuf, remote: &str, logins: &[Login]) -> Result<usize> {
+    let repo = Repository::open(path)?;
+    let mut refspecs = Vec::new();
+    for reference in repo.references()? {
+        let reference = reference?;
+        if let Some(name) = reference.name()
+            && (name.starts_with("refs/heads/") || name.starts_with("refs/tags/"))
+        {
+            refspecs.push(format!("+{}:{}", name, name));
+        }
+    }
+    push_refs(path, remote, &refspecs, logins)?;
+    Ok(refspecs.len())
+}
+
+// Local branches whose tip differs from the backup remote's copy
+fn backup_lag(path: &PathBuf, remote: &str) -> Result<usize> {
+    let repo = Repository::open(path)?;
+    let mut lag = 0;
+    for branch in repo.branches(Some(BranchType::Local))? {
+        let (branch, _) = branch?;
+        let Some(name) = branch.name()? else {
+            continue;
+        };
+        let backed_up = repo
+            .find_reference(&format!("refs/remotes/{}/{}", remote, name))
+            .ok()
+            .and_then(|reference| reference.target());
+        if backed_up != branch.get().target() {
+            lag += 1;
+        }
+    }
+    Ok(lag)
+}
+
+fn run_backup(repo: &mut RepoStatus, console_messages: &Arc<Mutex<Vec<ConsoleMessage>>>) {
+    let Some(backup) = repo.backup.as_mut() else {
+        return;
+    };
+    
+    let due = backup.last_attempt.is_none_or(|last| last.elapsed() >= backup.interval);
+    if due {
+        backup.last_attempt = Some(Instant::now());
+        match push_backup(&repo.path, &backup.remote, &repo.logins) {
+            Ok(_) => {
+                backup.last_success = Some(Utc::now());
+                backup.error = None;
+            }
+            Err(err) => {
+                if !repo.muted {
+                    log_console(console_messages, &repo.name, "System", EventKind::Error, format!("Backup to {} failed: {}", backup.remote, err));
+                }
+                backup.error = Some(err.to_string());
+            }
+        }
+    }
+    
+    if let Ok(lag) = backup_lag(&repo.path, &backup.remote) {
+        backup.lag = lag;
+    }
+}
+
+// Recompute the repository statistics on a blocking thread once they're older than STATS_INTERVAL
+fn refresh_stats(repo: &mut RepoStatus, repos: &Arc<Mutex<Vec<RepoStatus>>>, console_messages: &Arc<Mutex<Vec<ConsoleMessage>>>) {
+    if repo.stats_running || repo.stats_checked.is_some_and(|checked| checked.elapsed() < STATS_INTERVAL) {
+        return;
+    }
+    repo.stats_running = true;
+    repo.stats_checked = Some(Instant::now());
+    
+    let name = repo.name.clone();
+    let path = repo.path.clone();
+    let repos = repos.clone();
+    let console_messages = console_messages.clone();
+    tokio::task::spawn_blocking(move || {
+        let stats = repo_stats(&path);
+        let mut repos = repos.lock().unwrap();
+        let Some(repo) = repos.iter_mut().find(|repo| repo.path == path) else {
+            return;
+        };
+        repo.stats_running = false;
+        let Ok(stats) = stats else {
+            return;
+        };
+        // Only announce crossing the threshold, not every recomputation above it
+        let was_large = repo.stats.as_ref().is_some_and(|old| repo.size_warning.is_some_and(|limit| old.git_size > limit));
+        if let Some(limit) = repo.size_warning
+            && stats.git_size > limit
+            && !was_large
+            && !repo.muted
+        {
+            log_console(&console_messages, &name, "System", EventKind::Error, format!("Repository is {}, over the {} size warning", format_size(stats.git_size), format_size(limit)));
+        }
+        repo.stats = Some(stats);
+    });
+}
+
+fn repo_stats(path: &PathBuf) -> Result<RepoStats> {
+    let repo = Repository::open(path)?;
+    // Linked worktrees keep their objects in the main repository's git directory
+    let git_dir = match std::fs::read_to_string(repo.path().join("commondir")) {
+        Ok(common) => repo.path().join(common.trim()),
+        Err(_) => repo.path().to_path_buf(),
+    };
+    let objects = git_dir.join("objects");
+    
+    let (mut loose_objects, mut loose_size) = (0, 0);
+    for entry in std::fs::read_dir(&objects)?.flatten() {
+        let name = entry.file_name();
+        if name.len() == 2 && name.to_string_lossy().chars().all(|c| c.is_ascii_hexdigit()) {
+            for object in std::fs::read_dir(entry.path())?.flatten() {
+                loose_objects += 1;
+                loose_size += object.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+            }
+        }
+    }
+    
+    let (mut packed_objects, mut packs, mut pack_size) = (0, 0, 0);
+    for entry in std::fs::read_dir(objects.join("pack")).into_iter().flatten().flatten() {
+        let path = entry.path();
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("idx") => {
+                packs += 1;
+                packed_objects += pack_index_count(&path).unwrap_or(0);
+            }
+            Some("pack") => pack_size += entry.metadata().map(|metadata| metadata.len()).unwrap_or(0),
+            _ => {}
+        }
+    }
+    
+    let mut largest_files = Vec::new();
+    if let Ok(tree) = repo.head().and_then(|head| head.peel_to_tree()) {
+        let odb = repo.odb()?;
+        tree.walk(git2::TreeWalkMode::PreOrder, |dir, entry| {
+            if entry.kind() == Some(git2::ObjectType::Blob)
+                && let Ok((size, _)) = odb.read_header(entry.id())
+            {
+                largest_files.push((format!("{}{}", dir, entry.name().unwrap_or("?")), size as u64));
+            }
+            git2::TreeWalkResult::Ok
+        })?;
+        largest_files.sort_by_key(|(_, size)| std::cmp::Reverse(*size));
+        largest_files.truncate(5);
+    }
+    
+    let git_size = dir_size(&git_dir);
+    // A linked worktree's objects live with the main repository, so only its own files count here
+    let disk_usage = match repo.workdir() {
+        Some(workdir) if workdir.join(".git").is_dir() => dir_size(workdir),
+        Some(workdir) => dir_size(workdir) + dir_size(repo.path()),
+        None => git_size,
+    };
+    
+    Ok(RepoStats { git_size, disk_usage, loose_objects, loose_size, packed_objects, packs, pack_size, largest_files })
+}
+
+// Object count from a version 2 pack index: the last entry of its fanout table
+fn pack_index_count(path: &Path) -> Option<usize> {
+    use std::io::Read;
+    // Header and fanout table only; the rest of the index can run to hundreds of megabytes
+    let mut data = [0; 8 + 256 * 4];
+    std::fs::File::open(path).ok()?.read_exact(&mut data).ok()?;
+    if data[..8] != *b"\xfftOc\x00\x00\x00\x02" {
+        return None;
+    }
+    let last = &data[8 + 255 * 4..];
+    Some(u32::from_be_bytes(last.try_into().ok()?) as usize)
+}
+
+// Apparent size of everything below `path`, without following symlinks
+fn dir_size(path: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() => dir_size(&entry.path()),
+            Ok(file_type) if file_type.is_file() => entry.metadata().map(|metadata| metadata.len()).unwrap_or(0),
+            _ => 0,
+        })
+        .sum()
+}
+
+// FNV-1a of the name, so an author keeps their color across runs and machines
+fn author_color(author: &str) -> Option<Color> {
+    if APP_AUTHORS.contains(&author) {
+        return None;
+    }
+    let hash = author.bytes().fold(0xcbf29ce484222325u64, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3));
+    Some(AUTHOR_PALETTE[(hash % AUTHOR_PALETTE.len() as u64) as usize])
+}
+
+// Compact age like "5m", "3h", "2d", "6w" or "1y"
+fn format_age(timestamp: DateTime<Utc>) -> String {
+    let seconds = (Utc::now() - timestamp).num_seconds().max(0);
+    match seconds {
+        0..60 => format!("{}s", seconds),
+        60..3600 => format!("{}m", seconds / 60),
+        3600..86400 => format!("{}h", seconds / 3600),
+        86400..1209600 => format!("{}d", seconds / 86400),
+        1209600..31536000 => format!("{}w", seconds / 604800),
+        _ => format!("{}y", seconds / 31536000),
+    }
+}
+
+// Padded to `width` terminal columns, aligned by '<', '>' or '^' as in format strings. format!'s own width counts
+// characters, so CJK and emoji, which take two columns each, would push everything after them out of line.
+fn pad_width(text: &str, width: usize, align: char) -> String {
+    let fill = width.saturating_sub(text.width());
+    let (left, right) = match align {
+        '>' => (fill, 0),
+        '^' => (fill / 2, fill - fill / 2),
+        _ => (0, fill),
+    };
+    format!("{}{}{}", " ".repeat(left), text, " ".repeat(right))
+}
+
+// The longest start of `text` that fits in `width` columns, never half a wide character
+fn truncate_width(text: &str, width: usize) -> &str {
+    let mut used = 0;
+    for (index, c) in text.char_indices() {
+        used += c.width().unwrap_or(0);
+        if used > width {
+            return &text[..index];
+        }
+    }
+    text
+}
+
+// "850ms" under a second, "2.4s" above
+fn format_duration(duration: Duration) -> String {
+    if duration < Duration::from_secs(1) {
+        format!("{}ms", duration.as_millis())
+    } else {
+        format!("{:.1}s", duration.as_secs_f64())
+    }
+}
+
+// `partial` extended to the longest prefix all candidates starting with it share, with a space after a
+// single match
+fn complete(partial: &str, mut candidates: Vec<String>) -> String {
+    candidates.retain(|candidate| candidate.starts_with(partial));
+    candidates.sort();
+    candidates.dedup();
+    match candidates.as_slice() {
+        [] => partial.to_string(),
+        [only] => format!("{} ", only),
+        [first, rest @ ..] => {
+            let shared = rest.iter().fold(first.chars().count(), |shared, candidate| {
+                first.chars().zip(candidate.chars()).take(shared).take_while(|(a, b)| a == b).count()
+            });
+            first.chars().take(shared).collect()
+        }
+    }
+}
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+// Start `git maintenance` when it's due; it runs on a blocking thread so polling continues meanwhile
+fn run_maintenance(repo: &mut RepoStatus, repos: &Arc<Mutex<Vec<RepoStatus>>>, console_messages: &Arc<Mutex<Vec<ConsoleMessage>>>) {
+    let Some(maintenance) = repo.maintenance.as_mut() else {
+        return;
+    };
+    if maintenance.running || maintenance.last_run.is_some_and(|last| last.elapsed() < maintenance.interval) {
+        return;
+    }
+    maintenance.running = true;
+    maintenance.last_run = Some(Instant::now());
+    
+    let name = repo.name.clone();
+    let path = repo.path.clone();
+    let muted = repo.muted;
+    let repos = repos.clone();
+    let console_messages = console_messages.clone();
+    tokio::task::spawn_blocking(move || {
+        let result = git_maintenance(&path);
+        if !muted {
+            match &result {
+                Ok(elapsed) => log_console(&console_messages, &name, "GitOp", EventKind::Status, format!("Maintenance finished in {:.1}s", elapsed.as_secs_f64())),
+                Err(err) => log_console(&console_messages, &name, "System", EventKind::Error, format!("Maintenance failed: {}", err)),
+            }
+        }
+        let mut repos = repos.lock().unwrap();
+        if let Some(maintenance) = repos.iter_mut().find(|repo| repo.path == path).and_then(|repo| repo.maintenance.as_mut()) {
+            maintenance.running = false;
+            maintenance.last_finished = Some(Utc::now());
+            maintenance.error = result.err().map(|err| err.to_string());
+        }
+    });
+}
+
+// The commit-graph, loose-object and repack tasks of `git maintenance`, or `git gc --auto` before git 2.29
+fn git_maintenance(path: &Path) -> Result<Duration> {
+    let started = Instant::now();
+    let maintenance = std::process::Command::new("git")
+        .args(["maintenance", "run", "--task=commit-graph", "--task=loose-objects", "--task=incremental-repack", "--quiet"])
+        .current_dir(path)
+        .output()?;
+    if !maintenance.status.success() {
+        // Captured like maintenance's, anything gc printed would land on the TUI
+        let gc = std::process::Command::new("git").args(["gc", "--auto", "--quiet"]).current_dir(path).output()?;
+        if !gc.status.success() {
+            // Report why maintenance failed, gc only ran as a fallback
+            anyhow::bail!("{}", String::from_utf8_lossy(&maintenance.stderr).trim());
+        }
+    }
+    Ok(started.elapsed())
+}
+
+// Check commits that arrived on protected branches since the last fetch against the repo's policy
+// user.name and user.email as a commit here would get them, each with the config file it comes from
+#[derive(Debug, Clone)]
+struct Identity {
+    name: Option<(String, &'static str)>,
+    email: Option<(String, &'static str)>,
+}
+
+fn commit_identity(path: &PathBuf) -> Option<Identity> {
+    let config = Repository::open(path).ok()?.config().ok()?.snapshot().ok()?;
+    let lookup = |key: &str| {
+        let entry = config.get_entry(key).ok()?;
+        let level = match entry.level() {
+            git2::ConfigLevel::Local => "repository",
+            git2::ConfigLevel::Global | git2::ConfigLevel::XDG => "global",
+            git2::ConfigLevel::System | git2::ConfigLevel::ProgramData => "system",
+            _ => "command line",
+        };
+        Some((entry.value()?.to_string(), level))
+    };
+    Some(Identity { name: lookup("user.name"), email: lookup("user.email") })
+}
+
+// An address, or "@example.com" for any address at that domain
+fn email_matches(email: &str, expected: &str) -> bool {
+    if expected.starts_with('@') {
+        email.to_lowercase().ends_with(&expected.to_lowercase())
+    } else {
+        email.eq_ignore_ascii_case(expected)
+    }
+}
+
+// The user.email commits would get when it isn't the expected one, empty when none is set
+fn wrong_identity(repo: &RepoStatus) -> Option<String> {
+    let expected = repo.expected_email.as_ref()?;
+    let email = commit_identity(&repo.path).and_then(|identity| identity.email).map(|(email, _)| email).unwrap_or_default();
+    (!email_matches(&email, expected)).then_some(email)
+}
+
+// Catches "committed with my personal address at work" before anything is pushed; warns once until it's fixed
+fn check_identity(repo: &mut RepoStatus, console_messages: &Arc<Mutex<Vec<ConsoleMessage>>>) {
+    let wrong = wrong_identity(repo);
+    if let (Some(email), Some(expected)) = (&wrong, &repo.expected_email)
+        && repo.wrong_identity != wrong
+        && !repo.muted
+    {
+        let current = if email.is_empty() { "no user.email" } else { email.as_str() };
+        log_console(console_messages, &repo.name, "Policy", EventKind::Policy, format!("Commits here would use {}, expected {}", current, expected));
+    }
+    repo.wrong_identity = wrong;
+}
+
+fn check_policy(repo: &mut RepoStatus, console_messages: &Arc<Mutex<Vec<ConsoleMessage>>>) {
+    let Some(policy) = repo.policy.as_mut() else {
+        return;
+    };
+    let Ok(git_repo) = Repository::open(&repo.path) else {
+        return;
+    };
+    
+    for branch in &policy.protected_branches {
+        let Some(tip) = git_repo
+            .find_reference(&format!("refs/remotes/{}/{}", repo.remote, branch))
+            .ok()
+            .and_then(|reference| reference.target())
+        else {
+            continue;
+        };
+        // The first tip we see is the baseline; only later arrivals are checked
+        let Some(previous) = policy.tips.insert(branch.clone(), tip) else {
+            continue;
+        };
+        if previous == tip {
+            continue;
+        }
+        
+        let mut alerts = Vec::new();
+        if policy.require_signed {
+            for oid in new_commits(&git_repo, tip, previous, false) {
+                if git_repo.extract_signature(&oid, None).is_err() {
+                    alerts.push((PolicyKind::UnsignedCommit, oid));
+                }
+            }
+        }
+        // Only the branch's own first-parent history counts; commits brought in by a merge were reviewed with it
+        if policy.detect_direct_pushes {
+            for oid in new_commits(&git_repo, tip, previous, true) {
+                if git_repo.find_commit(oid).is_ok_and(|commit| commit.parent_count() < 2) {
+                    alerts.push((PolicyKind::DirectPush, oid));
+                }
+            }
+        }
+        
+        for (kind, oid) in alerts {
+            let Ok(commit) = git_repo.find_commit(oid) else {
+                continue;
+            };
+            let alert = PolicyAlert {
+                timestamp: Utc::now(),
+                kind,
+                remote: repo.remote.clone(),
+                branch: branch.clone(),
+                commit: format!("{:.8}", oid),
+                author: commit.author().name().unwrap_or("Unknown").to_string(),
+                summary: commit.summary().unwrap_or("").to_string(),
+            };
+            if !repo.muted {
+                log_console(console_messages, &repo.name, "Policy", EventKind::Policy, alert.describe());
+            }
+            policy.violations += 1;
+            if kind == PolicyKind::DirectPush {
+                policy.direct_pushes += 1;
+            }
+            policy.alerts.push(alert);
+        }
+        let len = policy.alerts.len();
+        if len > 50 {
+            policy.alerts.drain(0..len - 50);
+        }
+    }
+}
+
+// Source ref on the remote and local tracking ref for a pull request (GitHub) or merge request (GitLab)
+fn pull_request_refs(forge: Option<&str>, url: &str, remote: &str, number: u64) -> (String, String) {
+    let gitlab = match forge {
+        Some(forge) => forge.eq_ignore_ascii_case("gitlab"),
+        None => url.contains("gitlab"),
+    };
+    if gitlab {
+        (format!("refs/merge-requests/{}/head", number), format!("refs/remotes/{}/mr/{}", remote, number))
+    } else {
+        (format!("refs/pull/{}/head", number), format!("refs/remotes/{}/pr/{}", remote, number))
+    }
+}
+
+// Fetch the tracked pull request heads and report commits that arrived since the last check
+fn check_pull_requests(repo: &mut RepoStatus, console_messages: &Arc<Mutex<Vec<ConsoleMessage>>>) {
+    if repo.pull_requests.is_empty() {
+        return;
+    }
+    let Ok(git_repo) = Repository::open(&repo.path) else {
+        return;
+    };
+    let Ok(mut remote) = git_repo.find_remote(&repo.remote) else {
+        return;
+    };
+    let url = remote.url().unwrap_or("").to_string();
+    
+    let refs: Vec<(String, String)> = repo
+        .pull_requests
+        .iter()
+        .map(|pull_request| pull_request_refs(repo.forge.as_deref(), &url, &repo.remote, pull_request.number))
+        .collect();
+    let refspecs: Vec<String> = refs.iter().map(|(src, dst)| format!("+{}:{}", src, dst)).collect();
+    let fetched = keep_shallow_roots(&git_repo, repo.fetch_depth, || fetch_with_progress(&mut remote, &refspecs, false, repo.fetch_depth, None, &repo.logins));
+    if let Err(err) = fetched {
+        if !repo.muted {
+            log_console(console_messages, &repo.name, "System", EventKind::Error, format!("Fetching pull requests failed: {}", err));
+        }
+        return;
+    }
+    
+    for (pull_request, (_, tracking_ref)) in repo.pull_requests.iter_mut().zip(&refs) {
+        let Some(tip) = git_repo.find_reference(tracking_ref).ok().and_then(|reference| reference.target()) else {
+            continue;
+        };
+        let previous = pull_request.tip.replace(tip);
+        if previous == Some(tip) {
+            continue;
+        }
+        
+        let commits = match previous {
+            Some(previous) => new_commits(&git_repo, tip, previous, false),
+            None => vec![tip], // first sighting, only describe the head
+        };
+        if let Some(commit) = commits.first().and_then(|&oid| git_repo.find_commit(oid).ok()) {
+            pull_request.last_commit = Some(format!("{:.8} {}", tip, commit.summary().unwrap_or("")));
+            pull_request.updated = Some(Utc::now());
+        }
+        if previous.is_none() || repo.muted {
+            continue;
+        }
+        for &oid in commits.iter().take(5) {
+            if let Ok(commit) = git_repo.find_commit(oid) {
+                log_console(
+                    console_messages,
+                    &repo.name,
+                    commit.author().name().unwrap_or("Unknown"),
+                    EventKind::Commits,
+                    format!("PR #{}: {}", pull_request.number, commit.summary().unwrap_or("")),
+                );
+            }
+        }
+    }
+}
+
+fn suggest_release(path: &PathBuf) -> Option<ReleaseSuggestion> {
+    let repo = Repository::open(path).ok()?;
+    let head = repo.head().ok()?.peel_to_commit().ok()?.id();
+    let last_tag = repo
+        .describe(git2::DescribeOptions::new().describe_tags())
+        .and_then(|describe| describe.format(Some(git2::DescribeFormatOptions::new().abbreviated_size(0))))
+        .ok();
+    
+    let mut revwalk = repo.revwalk().ok()?;
+    revwalk.push(head).ok()?;
+    if let Some(tag) = &last_tag {
+        revwalk.hide(repo.revparse_single(tag).ok()?.peel_to_commit().ok()?.id()).ok()?;
+    }
+    let mut commits = 0;
+    let mut bump = Bump::Patch;
+    for oid in revwalk.flatten() {
+        let Ok(commit) = repo.find_commit(oid) else {
+            continue;
+        };
+        commits += 1;
+        let message = commit.message().unwrap_or("");
+        let commit_bump = match conventional_commit(commit.summary().unwrap_or("")) {
+            _ if message.contains("BREAKING CHANGE:") || message.contains("BREAKING-CHANGE:") => Bump::Major,
+            Some((_, _, true, _)) => Bump::Major,
+            Some((kind, _, _, _)) if kind == "feat" => Bump::Minor,
+            _ => Bump::Patch,
+        };
+        bump = bump.max(commit_bump);
+    }
+    
+    let next = last_tag.as_deref().and_then(|tag| bump_version(tag, bump));
+    Some(ReleaseSuggestion { last_tag, commits, bump, next })
+}
+
+// Non-merge commits on the current branch's remote-tracking branch that HEAD doesn't have yet, or with `outgoing`
+// the other way round, with the range they span
+fn range_commits(path: &PathBuf, remote: &str, outgoing: bool, cache: &CommitCache) -> Result<(String, Vec<CommitInfo>)> {
+    let repo = Repository::open(path)?;
+    let head = repo.head()?;
+    let branch = head.shorthand().unwrap_or("HEAD").to_string();
+    let upstream = format!("{}/{}", remote, branch);
+    let upstream_tip = repo
+        .find_reference(&format!("refs/remotes/{}", upstream))
+        .with_context(|| format!("no remote-tracking branch {}", upstream))?
+        .peel_to_commit()?
+        .id();
+    let head_tip = head.peel_to_commit()?.id();
+    let (tip, base, range) = if outgoing {
+        (head_tip, upstream_tip, format!("{}..{}", upstream, branch))
+    } else {
+        (upstream_tip, head_tip, format!("{}..{}", branch, upstream))
+    };
+    
+    let mut cache = cache.lock().unwrap();
+    let mut commits = Vec::new();
+    for oid in cache.walk(&repo, tip, Some(base), usize::MAX) {
+        let commit = cache.commit(&repo, oid).with_context(|| format!("commit {} not found", oid))?;
+        if commit.parents > 1 {
+            continue;
+        }
+        commits.push(CommitInfo {
+            hash: format!("{:.8}", oid),
+            author: commit.author,
+            message: commit.summary,
+            branch: if outgoing { branch.clone() } else { upstream.clone() },
+            timestamp: DateTime::from_timestamp(commit.time, 0).unwrap_or_else(Utc::now),
+        });
+    }
+    cache.save();
+    Ok((range, commits))
+}
+
+// Uses whatever the last fetch brought in, so it's as current as the behind count
+fn preview_pull(path: &PathBuf, remote: &str) -> Result<PullPreview> {
+    let repo = Repository::open(path)?;
+    let head = repo.head()?;
+    let branch = head.shorthand().unwrap_or("HEAD").to_string();
+    let upstream = format!("{}/{}", remote, branch);
+    let upstream_commit = repo
+        .find_reference(&format!("refs/remotes/{}", upstream))
+        .with_context(|| format!("no remote-tracking branch {}", upstream))?
+        .peel_to_commit()?;
+    let head_commit = head.peel_to_commit()?;
+    
+    let (analysis, _) = repo.merge_analysis(&[&repo.find_annotated_commit(upstream_commit.id())?])?;
+    let outcome = if analysis.is_up_to_date() {
+        PullOutcome::UpToDate
+    } else if analysis.is_fast_forward() {
+        PullOutcome::FastForward
+    } else {
+        let index = repo.merge_commits(&head_commit, &upstream_commit, None)?;
+        if index.has_conflicts() {
+            PullOutcome::Conflicts(conflicted_paths(&index)?)
+        } else {
+            PullOutcome::Merge
+        }
+    };
+    let commits = new_commits(&repo, upstream_commit.id(), head_commit.id(), false).len();
+    
+    // Diffstat of what the upstream side changed since the histories split
+    let base = repo.merge_base(head_commit.id(), upstream_commit.id())?;
+    let diff = repo.diff_tree_to_tree(Some(&repo.find_commit(base)?.tree()?), Some(&upstream_commit.tree()?), None)?;
+    let mut files = Vec::new();
+    for index in 0..diff.deltas().len() {
+        let Some(patch) = git2::Patch::from_diff(&diff, index)? else {
+            continue;
+        };
+        let delta = patch.delta();
+        let file = delta.new_file().path().or(delta.old_file().path()).map(|path| path.display().to_string()).unwrap_or_default();
+        let (_, insertions, deletions) = patch.line_stats()?;
+        files.push((file, insertions, deletions));
+    }
+    let stats = diff.stats()?;
+    
+    let mut options = StatusOptions::new();
+    options.include_untracked(true).include_ignored(false);
+    let blocked = repo
+        .statuses(Some(&mut options))?
+        .iter()
+        .filter_map(|entry| entry.path().map(str::to_string))
+        .filter(|path| files.iter().any(|(file, _, _)| file == path))
+        .collect();
+    
+    Ok(PullPreview { branch, upstream, outcome, commits, files, insertions: stats.insertions(), deletions: stats.deletions(), blocked })
+}
+
+// Local branches, the remote's branches and HEAD drawn the way `git log --graph --oneline` does,
+// with one column per line of history that is still open
+fn commit_graph(path: &PathBuf, remote: &str, limit: usize) -> Result<Vec<String>> {
+    let repo = Repository::open(path)?;
+    
+    let mut decorations: std::collections::HashMap<git2::Oid, Vec<String>> = std::collections::HashMap::new();
+    let mut head_branch = None;
+    if let Ok(head) = repo.head()
+        && let Some(oid) = head.target()
+    {
+        let name = if head.is_branch() {
+            head_branch = head.shorthand().map(str::to_string);
+            format!("HEAD -> {}", head.shorthand().unwrap_or("?"))
+        } else {
+            "HEAD".to_string()
+        };
+        decorations.entry(oid).or_default().push(name);
+    }
+    for reference in repo.references()?.flatten() {
+        let (Some(name), Ok(commit)) = (reference.name(), reference.peel_to_commit()) else {
+            continue;
+        };
+        let label = if let Some(branch) = name.strip_prefix("refs/heads/") {
+            if head_branch.as_deref() == Some(branch) {
+                continue; // already shown as HEAD -> branch
+            }
+            branch.to_string()
+        } else if let Some(tag) = name.strip_prefix("refs/tags/") {
+            format!("tag: {}", tag)
+        } else if let Some(remote_branch) = name.strip_prefix("refs/remotes/") {
+            remote_branch.to_string()
+        } else {
+            continue;
+        };
+        decorations.entry(commit.id()).or_default().push(label);
+    }
+    
+    let mut revwalk = repo.revwalk()?;
+    revwalk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)?;
+    let _ = revwalk.push_head();
+    revwalk.push_glob("refs/heads")?;
+    revwalk.push_glob(&format!("refs/remotes/{}", remote))?;
+    
+    let mut lines = Vec::new();
+    let mut columns: Vec<git2::Oid> = Vec::new();
+    // Merge and close connectors get lines of their own, so commits are counted separately
+    let mut commits = 0;
+    for oid in revwalk.take(limit) {
+        let oid = oid?;
+        commits += 1;
+        let commit = repo.find_commit(oid)?;
+        let column = match columns.iter().position(|&open| open == oid) {
+            Some(column) => column,
+            None => {
+                columns.push(oid);
+                columns.len() - 1
+            }
+        };
+        
+        let mut line: String = (0..columns.len()).map(|i| if i == column { "* " } else { "| " }).collect();
+        line.push_str(&format!("{:.7}", oid));
+        if let Some(names) = decorations.get(&oid) {
+            line.push_str(&format!(" ({})", names.join(", ")));
+        }
+        line.push_str(&format!(" {}", commit.summary().unwrap_or("")));
+        lines.push(line);
+        
+        let parents: Vec<git2::Oid> = commit.parent_ids().collect();
+        let merged: Vec<git2::Oid> = parents.iter().skip(1).filter(|parent| !columns.contains(parent)).copied().collect();
+        match parents.first() {
+            Some(&parent) if !columns.contains(&parent) => {
+                columns[column] = parent;
+                // Merged-in parents open new columns right after this one
+                if !merged.is_empty() {
+                    lines.push(graph_merge(columns.len(), column, merged.len()));
+                    columns.splice(column + 1..column + 1, merged);
+                }
+            }
+            // The first parent is already in another column, or this is a root commit: this column ends here
+            first => {
+                let target = first.and_then(|parent| columns[..column].iter().position(|open| open == parent));
+                // With merged-in parents the column lives on as the first of them
+                let keep = !merged.is_empty();
+                if let Some(line) = graph_close(columns.len(), column, target, keep) {
+                    lines.push(line);
+                }
+                columns.splice(column..column + 1, merged);
+            }
+        }
+    }
+    if commits == limit {
+        lines.push(format!("(first {} commits)", limit));
+    }
+    Ok(lines)
+}
+
+// "|\" under a merge, with the columns to its right bending outwards to make room
+fn graph_merge(columns: usize, column: usize, merged: usize) -> String {
+    let mut line = vec![' '; 2 * (columns + merged)];
+    for j in 0..=column {
+        line[2 * j] = '|';
+    }
+    for i in 0..merged {
+        line[2 * column + 1 + 2 * i] = '\\';
+    }
+    for j in column + 1..columns {
+        line[2 * j + 2 * merged - 1] = '\\';
+    }
+    line.into_iter().collect::<String>().trim_end().to_string()
+}
+
+// "|/" where a column joins the `target` column to its left, "|_|/" when it has to cross others, with the columns
+// to its right moving inwards unless it's kept open; None when nothing moves
+fn graph_close(columns: usize, column: usize, target: Option<usize>, keep: bool) -> Option<String> {
+    let mut line = vec![' '; 2 * columns];
+    for j in 0..column {
+        line[2 * j] = '|';
+    }
+    if let Some(target) = target {
+        line[2 * column - 1] = '/';
+        for j in target..column - 1 {
+            line[2 * j + 1] = '_';
+        }
+    }
+    if keep {
+        for j in column..columns {
+            line[2 * j] = '|';
+        }
+    } else {
+        for j in column + 1..columns {
+            line[2 * j - 1] = '/';
+        }
+    }
+    let line = line.into_iter().collect::<String>().trim_end().to_string();
+    line.contains('/').then_some(line)
+}
+
+// Commits reachable from `tip` but not from `previous`, newest first
+fn new_commits(repo: &Repository, tip: git2::Oid, previous: git2::Oid, first_parent: bool) -> Vec<git2::Oid> {
+    let Ok(mut revwalk) = repo.revwalk() else {
+        return Vec::new();
+    };
+    if revwalk.push(tip).is_err() {
+        return Vec::new();
+    }
+    if first_parent {
+        let _ = revwalk.simplify_first_parent();
+    }
+    // A force-push can make the old tip unreachable; then everything reachable is checked
+    let _ = revwalk.hide(previous);
+    revwalk.flatten().collect()
+}
+
+// Reference advertisement of a remote, the equivalent of `git ls-remote`
+fn list_remote_refs(remote: &mut git2::Remote, logins: &[Login]) -> Result<Vec<(String, git2::Oid)>> {
+    let connection = remote.connect_auth(git2::Direction::Fetch, Some(remote_callbacks(logins)), None)?;
+    let refs = connection
+        .list()?
+        .iter()
+        .map(|head| (head.name().to_string(), head.oid()))
+        .collect();
+    Ok(refs)
+}
+
+struct HealthReport {
+    name: String,
+    remote: String,
+    url: Option<String>,
+    result: Result<(Duration, usize)>, // latency and advertised ref count
+}
+
+fn check_health(repo_config: &RepoConfig, logins: &[Login]) -> HealthReport {
+    let remote_name = repo_config.remote.clone().unwrap_or_else(|| "origin".to_string());
+    let mut url = None;
+    let result = (|| {
+        // Remote-only repositories are checked at their url
+        if repo_config.path.is_empty()
+            && let Some(repo_url) = &repo_config.url
+        {
+            url = Some(repo_url.clone());
+            let started = Instant::now();
+            let (_, refs) = ls_remote(repo_url, logins)?;
+            return Ok((started.elapsed(), refs.len()));
+        }
+        let path = expand_path(&repo_config.path);
+        if !path.exists() {
+            anyhow::bail!("path does not exist: {}", path.display());
+        }
+        let repo = Repository::open(&path).map_err(|_| anyhow::anyhow!("not a git repository: {}", path.display()))?;
+        let mut remote = repo
+            .find_remote(&remote_name)
+            .map_err(|_| anyhow::anyhow!("remote \"{}\" is not configured", remote_name))?;
+        url = remote.url().map(str::to_string);
+        if url.is_none() {
+            anyhow::bail!("remote \"{}\" has no url", remote_name);
+        }
+        
+        let started = Instant::now();
+        let refs = list_remote_refs(&mut remote, logins)?;
+        Ok((started.elapsed(), refs.len()))
+    })();
     
-    println!("Created default config at: {}", config_path.display());
+    HealthReport {
+        name: repo_config.name.clone(),
+        remote: remote_name,
+        url,
+        result,
+    }
+}
+
+// Without `prune`, the remote's own fetch.prune/remote.<name>.prune setting still applies. A depth makes the
+// clone shallow, as `git fetch --depth` does.
+fn fetch_options(prune: bool, depth: Option<u32>) -> FetchOptions<'static> {
+    let mut options = FetchOptions::new();
+    if prune {
+        options.prune(git2::FetchPrune::On);
+    }
+    if let Some(depth) = depth {
+        options.depth(depth.min(i32::MAX as u32) as i32);
+    }
+    options
+}
+
+// Report received objects into `transfer` while the fetch runs, and clear it when it's done. Fetches only
+// answer HTTPS logins from the config; everything else authenticates as the remote allows without asking.
+fn fetch_with_progress(
+    remote: &mut git2::Remote,
+    refspecs: &[String],
+    prune: bool,
+    depth: Option<u32>,
+    transfer: Option<&Transfer>,
+    logins: &[Login],
+) -> Result<(), git2::Error> {
+    let mut options = fetch_options(prune, depth);
+    let mut callbacks = RemoteCallbacks::new();
+    if !logins.is_empty() {
+        // libgit2 asks again after a rejected password, so only offer it once
+        let offered = std::cell::Cell::new(false);
+        callbacks.credentials(move |url, _, allowed| match logins.iter().find(|login| login.matches(url)) {
+            Some(login) if allowed.contains(CredentialType::USER_PASS_PLAINTEXT) && !offered.replace(true) => {
+                Cred::userpass_plaintext(&login.username, &login.password)
+            }
+            _ => Err(git2::Error::from_str("no credentials configured for this remote")),
+        });
+    }
+    if let Some(transfer) = transfer {
+        let progress = transfer.clone();
+        callbacks.transfer_progress(move |stats| {
+            // Fetches with nothing to download aren't worth showing
+            if stats.total_objects() > 0 {
+                *progress.lock().unwrap() = Some(TransferProgress {
+                    received_objects: stats.received_objects(),
+                    total_objects: stats.total_objects(),
+                    received_bytes: stats.received_bytes(),
+                    indexed_deltas: stats.indexed_deltas(),
+                    total_deltas: stats.total_deltas(),
+                });
+            }
+            true
+        });
+    }
+    options.remote_callbacks(callbacks);
+    let result = remote.fetch(refspecs, Some(&mut options), None);
+    if let Some(transfer) = transfer {
+        *transfer.lock().unwrap() = None;
+    }
+    result
+}
+
+// libgit2 rewrites .git/shallow with only the cut-off of the latest depth-limited fetch, leaving the commits an
+// earlier one cut off pointing at parents that aren't there. Git keeps every cut-off, so the dropped ones are put
+// back.
+fn keep_shallow_roots<T>(repo: &Repository, depth: Option<u32>, fetch: impl FnOnce() -> T) -> T {
+    let file = repo.path().join("shallow");
+    let before = if depth.is_some() { std::fs::read_to_string(&file).unwrap_or_default() } else { String::new() };
+    let result = fetch();
+    if let Ok(after) = std::fs::read_to_string(&file) {
+        let mut roots: Vec<&str> = after.lines().collect();
+        let kept = roots.len();
+        roots.extend(before.lines().filter(|line| !after.lines().any(|root| root == *line)));
+        if roots.len() > kept {
+            let _ = std::fs::write(&file, roots.join("\n") + "\n");
+        }
+    }
+    result
+}
+
+// An empty refspec list fetches the remote's configured refspecs
+fn fetch_repo(path: &PathBuf, remote: &str, refspecs: &[String], prune: bool, depth: Option<u32>, transfer: Option<&Transfer>, logins: &[Login]) -> Result<()> {
+    let repo = Repository::open(path)?;
+    let mut remote_ref = repo.find_remote(remote)?;
+    keep_shallow_roots(&repo, depth, || fetch_with_progress(&mut remote_ref, refspecs, prune, depth, transfer, logins))?;
+    Ok(())
+}
+
+// Fetch and fast-forward the current branch; anything else is left for the user
+fn pull_repo(path: &PathBuf, remote: &str, refspecs: &[String], prune: bool, depth: Option<u32>, transfer: Option<&Transfer>, logins: &[Login]) -> Result<String> {
+    fetch_repo(path, remote, refspecs, prune, depth, transfer, logins)?;
+    
+    let repo = Repository::open(path)?;
+    let head = repo.head()?;
+    let branch = head.shorthand().unwrap_or("unknown").to_string();
+    let head_name = head.name().unwrap_or("HEAD").to_string();
+    
+    let remote_ref = repo.find_reference(&format!("refs/remotes/{}/{}", remote, branch))?;
+    let upstream = repo.reference_to_annotated_commit(&remote_ref)?;
+    let (analysis, _) = repo.merge_analysis(&[&upstream])?;
+    
+    if analysis.is_up_to_date() {
+        return Ok("Already up to date".to_string());
+    }
+    if !analysis.is_fast_forward() {
+        anyhow::bail!("{}/{} cannot be fast-forwarded, pull manually", remote, branch);
+    }
+    
+    let target = upstream.id();
+    repo.checkout_tree(&repo.find_object(target, None)?, Some(CheckoutBuilder::new().safe()))?;
+    repo.find_reference(&head_name)?
+        .set_target(target, &format!("gitop: fast-forward to {}/{}", remote, branch))?;
+    
+    Ok(format!("Fast-forwarded {} to {:.8}", branch, target))
+}
+
+// Open a path or URL with the platform's default handler
+fn open_path(path: impl AsRef<std::ffi::OsStr>) -> Result<()> {
+    let opener = if cfg!(target_os = "windows") {
+        "explorer"
+    } else if cfg!(target_os = "macos") {
+        "open"
+    } else {
+        "xdg-open"
+    };
+    
+    std::process::Command::new(opener)
+        .arg(path)
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()?;
     Ok(())
 }
 
-fn load_config(config_path: Option<PathBuf>) -> Result<Config> {
-    let config_path = get_config_path(config_path);
+// Changed, staged and untracked files, minus what .gitignore, .git/info/exclude and core.excludesFile ignore, the
+// same as `git status`. Untracked files are left out for repositories with ignore_untracked.
+fn count_dirty_files(path: &PathBuf, untracked: bool) -> usize {
+    let Ok(repo) = Repository::open(path) else {
+        return 0;
+    };
+    
+    let mut options = StatusOptions::new();
+    options.include_untracked(untracked).include_ignored(false);
+    repo.statuses(Some(&mut options)).map(|statuses| statuses.len()).unwrap_or(0)
+}
+
+// Unmerged index entries, described like `git status` from which sides still have the file
+fn conflicted_files(path: &PathBuf) -> Vec<(String, &'static str)> {
+    let Ok(index) = Repository::open(path).and_then(|repo| repo.index()) else {
+        return Vec::new();
+    };
+    let Ok(conflicts) = index.conflicts() else {
+        return Vec::new();
+    };
+    conflicts
+        .flatten()
+        .filter_map(|conflict| {
+            let kind = match (conflict.ancestor.is_some(), conflict.our.is_some(), conflict.their.is_some()) {
+                (true, true, true) => "both modified",
+                (false, true, true) => "both added",
+                (true, true, false) => "deleted by them",
+                (true, false, true) => "deleted by us",
+                (false, true, false) => "added by us",
+                (false, false, true) => "added by them",
+                (true, false, false) => "both deleted",
+                (false, false, false) => return None,
+            };
+            let entry = conflict.our.or(conflict.their).or(conflict.ancestor)?;
+            Some((String::from_utf8_lossy(&entry.path).into_owned(), kind))
+        })
+        .collect()
+}
+
+// `git status --short`: the index and work tree columns, then the path ("old -> new" for renames), followed
+// by a summary line
+fn short_status(path: &PathBuf) -> Result<Vec<String>> {
+    let repo = Repository::open(path)?;
+    let mut options = StatusOptions::new();
+    options
+        .include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .include_ignored(false)
+        .renames_head_to_index(true)
+        .renames_index_to_workdir(true);
+    let statuses = repo.statuses(Some(&mut options))?;
+    
+    let (mut staged, mut unstaged, mut untracked, mut conflicted) = (0, 0, 0, 0);
+    let mut lines = Vec::new();
+    for entry in statuses.iter() {
+        let status = entry.status();
+        let (index, worktree) = if status.is_conflicted() {
+            conflicted += 1;
+            ('U', 'U')
+        } else if status.is_wt_new() {
+            untracked += 1;
+            ('?', '?')
+        } else {
+            let index = match status {
+                s if s.is_index_new() => 'A',
+                s if s.is_index_modified() => 'M',
+                s if s.is_index_deleted() => 'D',
+                s if s.is_index_renamed() => 'R',
+                s if s.is_index_typechange() => 'T',
+                _ => ' ',
+            };
+            let worktree = match status {
+                s if s.is_wt_modified() => 'M',
+                s if s.is_wt_deleted() => 'D',
+                s if s.is_wt_renamed() => 'R',
+                s if s.is_wt_typechange() => 'T',
+                _ => ' ',
+            };
+            staged += usize::from(index != ' ');
+            unstaged += usize::from(worktree != ' ');
+            (index, worktree)
+        };
+        let renamed = entry.head_to_index().or(entry.index_to_workdir()).filter(|_| status.is_index_renamed() || status.is_wt_renamed());
+        let file = match renamed.and_then(|delta| Some((delta.old_file().path()?, delta.new_file().path()?))) {
+            Some((old, new)) => format!("{} -> {}", old.display(), new.display()),
+            None => entry.path().unwrap_or("?").to_string(),
+        };
+        lines.push(format!("{}{} {}", index, worktree, file));
+    }
+    if !lines.is_empty() {
+        lines.push(String::new());
+        lines.push(format!("{} staged, {} unstaged, {} untracked, {} conflicted", staged, unstaged, untracked, conflicted));
+    }
+    Ok(lines)
+}
+
+// Lines added and removed by uncommitted changes, staged or not, like `git diff --shortstat HEAD`. Untracked
+// files aren't counted, they only show in the dirty count.
+fn local_diffstat(path: &PathBuf) -> (usize, usize) {
+    let stats = (|| {
+        let repo = Repository::open(path).ok()?;
+        let head = repo.head().ok()?.peel_to_tree().ok()?;
+        repo.diff_tree_to_workdir_with_index(Some(&head), None).ok()?.stats().ok()
+    })();
+    stats.map_or((0, 0), |stats| (stats.insertions(), stats.deletions()))
+}
+
+// The operation the repository is in the middle of, from the state files in .git (MERGE_HEAD,
+// rebase-merge/, BISECT_LOG, ...), None when it's clean
+fn repo_operation(path: &PathBuf) -> Option<String> {
+    let operation = match Repository::open(path).ok()?.state() {
+        RepositoryState::Clean => return None,
+        RepositoryState::Merge => "merging",
+        RepositoryState::Revert | RepositoryState::RevertSequence => "reverting",
+        RepositoryState::CherryPick | RepositoryState::CherryPickSequence => "cherry-picking",
+        RepositoryState::Bisect => "bisecting",
+        RepositoryState::Rebase | RepositoryState::RebaseInteractive | RepositoryState::RebaseMerge => "rebasing",
+        RepositoryState::ApplyMailbox | RepositoryState::ApplyMailboxOrRebase => "applying patches",
+    };
+    Some(operation.to_string())
+}
+
+// The current branch's upstream when it's configured but its remote-tracking ref no longer exists, which is
+// what a pruning fetch leaves behind once the branch was deleted on the remote (usually after merging)
+fn upstream_gone(path: &PathBuf) -> Option<String> {
+    let repo = Repository::open(path).ok()?;
+    let head = repo.head().ok().filter(|head| head.is_branch())?;
+    let configured = repo.branch_upstream_name(head.name()?).ok()?;
+    let configured = configured.as_str()?;
+    repo.find_reference(configured)
+        .is_err()
+        .then(|| configured.trim_start_matches("refs/remotes/").to_string())
+}
+
+// A branch that may have been the remote's default before a rename: what refs/remotes/<remote>/HEAD still points
+// to after the branch was pruned, or a gone main/master upstream when there is no HEAD ref
+fn default_branch_candidate(path: &PathBuf, remote: &str, upstream_gone: Option<&str>) -> Option<String> {
+    let repo = Repository::open(path).ok()?;
+    let prefix = format!("refs/remotes/{}/", remote);
+    match repo.find_reference(&format!("{}HEAD", prefix)) {
+        Ok(head) => {
+            let target = head.symbolic_target()?;
+            repo.find_reference(target).is_err().then(|| target.trim_start_matches(&prefix).to_string())
+        }
+        Err(_) => upstream_gone?
+            .strip_prefix(&format!("{}/", remote))
+            .filter(|branch| ["main", "master"].contains(branch))
+            .map(str::to_string),
+    }
+}
+
+// Changes whenever a fetch adds, moves or prunes one of the remote's branches
+fn remote_branches_fingerprint(path: &PathBuf, remote: &str) -> Option<u64> {
+    use std::hash::{Hash, Hasher};
+    let repo = Repository::open(path).ok()?;
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    for reference in repo.references_glob(&format!("refs/remotes/{}/*", remote)).ok()?.flatten() {
+        reference.name_bytes().hash(&mut hasher);
+        reference.target().hash(&mut hasher);
+    }
+    Some(hasher.finish())
+}
+
+// Old and new name when the server's default branch is no longer `old` and the new one has been fetched
+fn default_branch_rename(path: &PathBuf, remote: &str, old: &str, logins: &[Login]) -> Option<(String, String)> {
+    let repo = Repository::open(path).ok()?;
+    let url = repo.find_remote(remote).ok()?.url()?.to_string();
+    let (head, _) = ls_remote(&url, logins).ok()?;
+    let new = head.filter(|head| head != old)?;
+    repo.find_reference(&format!("refs/remotes/{}/{}", remote, new)).ok()?;
+    Some((old.to_string(), new))
+}
+
+// Point every local branch tracking <remote>/<old> and the remote's HEAD ref at <remote>/<new>, like
+// `git branch -u` on each plus `git remote set-head`. Returns the branches that were retargeted.
+fn retarget_upstream(path: &PathBuf, remote: &str, old: &str, new: &str) -> Result<Vec<String>> {
+    let repo = Repository::open(path)?;
+    let old_ref = format!("refs/remotes/{}/{}", remote, old);
+    let mut retargeted = Vec::new();
+    for branch in repo.branches(Some(BranchType::Local))? {
+        let (mut branch, _) = branch?;
+        let Some(name) = branch.name()?.map(str::to_string) else {
+            continue;
+        };
+        let upstream = repo.branch_upstream_name(&format!("refs/heads/{}", name));
+        if upstream.ok().as_ref().and_then(|upstream| upstream.as_str()) == Some(old_ref.as_str()) {
+            branch.set_upstream(Some(&format!("{}/{}", remote, new)))?;
+            retargeted.push(name);
+        }
+    }
+    repo.reference_symbolic(
+        &format!("refs/remotes/{}/HEAD", remote),
+        &format!("refs/remotes/{}/{}", remote, new),
+        true,
+        "gitop: default branch renamed",
+    )?;
+    Ok(retargeted)
+}
+
+// Ahead, behind, current branch and how long the fetch took (None without the remote or when the fetch failed);
+// updates `status.counted`.
+// Metered, only the checked-out branch is fetched and only when ls-remote says it moved.
+fn get_repo_status(status: &mut RepoStatus, metered: bool) -> Result<(usize, usize, String, Option<Duration>)> {
+    let RepoStatus { path, remote, refspecs, prune, fetch_depth, light_check, transfer, logins, counted, .. } = status;
+    let repo = Repository::open(&*path)?;
+    
+    // Get current branch
+    let head = repo.head()?;
+    let current_branch = head.shorthand().unwrap_or("unknown").to_string();
+    let tracking = format!("refs/remotes/{}/{}", remote, current_branch);
+    
+    // A detached HEAD has no branch of its own to watch, so the remote's default branch stands in. Without one
+    // either, light and metered checks just fetch.
+    let watched = if head.is_branch() {
+        Some(current_branch.clone())
+    } else {
+        default_branch(&repo, remote).map(|(name, _)| name.trim_start_matches(&format!("{}/", remote)).to_string())
+    };
+    
+    // Try to fetch from remote (ignore errors for offline/network issues)
+    let mut fetch_time = None;
+    if let Ok(mut remote_ref) = repo.find_remote(remote)
+        && (!(*light_check || metered) || watched.as_ref().is_none_or(|branch| remote_tip_moved(&repo, &mut remote_ref, branch, logins)))
+    {
+        let started = Instant::now();
+        let fetched = keep_shallow_roots(&repo, *fetch_depth, || {
+            if metered && let Some(branch) = &watched {
+                let branch_only = format!("+refs/heads/{}:refs/remotes/{}/{}", branch, remote, branch);
+                fetch_with_progress(&mut remote_ref, &[branch_only], false, *fetch_depth, Some(transfer), logins)
+            } else {
+                fetch_with_progress(&mut remote_ref, refspecs, *prune, *fetch_depth, Some(transfer), logins)
+            }
+        });
+        // A failed fetch, often a timeout, says nothing about how long fetching takes
+        if fetched.is_ok() {
+            fetch_time = Some(started.elapsed());
+        }
+    }
+    
+    let local_oid = head.target().unwrap();
+    drop(head);
+    // Still has the cut-offs libgit2 loaded while fetching, without the ones keep_shallow_roots put back
+    let repo = if fetch_depth.is_some() { Repository::open(&*path)? } else { repo };
+    
+    // Try to find remote branch, if it doesn't exist, assume 0 ahead/behind
+    if let Ok(remote_ref) = repo.find_reference(&tracking)
+        && let Some(remote_oid) = remote_ref.target()
+    {
+        // Calculate ahead/behind, unless neither tip moved since the last time
+        if let Some(tips) = counted
+            && tips.local == local_oid
+            && tips.upstream == remote_oid
+        {
+            return Ok((tips.ahead, tips.behind, current_branch, fetch_time));
+        }
+        let (mut ahead, behind) = repo.graph_ahead_behind(local_oid, remote_oid)?;
+        // In a shallow clone the upstream side may run into the cut-off before it meets HEAD. Every upstream
+        // commit in the window is then missing locally, but there may be more past it. The window alone can't
+        // tell local commits from history shared with the upstream, so the tips its reflog went through are left
+        // out of ahead too.
+        let partial = repo.is_shallow() && repo.merge_base(local_oid, remote_oid).is_err();
+        if partial {
+            let mut walk = repo.revwalk()?;
+            walk.push(local_oid)?;
+            let _ = walk.hide(remote_oid);
+            for entry in repo.reflog(&tracking).iter().flat_map(|reflog| reflog.iter()) {
+                let _ = walk.hide(entry.id_old());
+                let _ = walk.hide(entry.id_new());
+            }
+            ahead = walk.count();
+        }
+        *counted = Some(CountedTips { local: local_oid, upstream: remote_oid, ahead, behind, partial });
+        return Ok((ahead, behind, current_branch, fetch_time));
+    }
+    
+    // If no remote branch found, just return 0/0
+    *counted = None;
+    Ok((0, 0, current_branch, fetch_time))
+}
+
+// Whether the remote advertises a different tip for `branch` than its remote-tracking branch, so a fetch would
+// bring something in. When the remote can't be asked, the fetch decides.
+fn remote_tip_moved(repo: &Repository, remote: &mut git2::Remote, branch: &str, logins: &[Login]) -> bool {
+    let Ok(refs) = list_remote_refs(remote, logins) else {
+        return true;
+    };
+    let advertised = refs.iter().find(|(name, _)| *name == format!("refs/heads/{}", branch)).map(|(_, oid)| *oid);
+    let tracking = remote
+        .name()
+        .and_then(|name| repo.find_reference(&format!("refs/remotes/{}/{}", name, branch)).ok())
+        .and_then(|reference| reference.target());
+    advertised != tracking
+}
+
+// Newest commit time on HEAD or its remote-tracking branch
+fn last_commit_time(path: &PathBuf, remote: &str) -> Option<DateTime<Utc>> {
+    let repo = Repository::open(path).ok()?;
+    let head = repo.head().ok()?;
+    let branch = head.shorthand().unwrap_or("HEAD").to_string();
+    let mut times = vec![head.peel_to_commit().ok()?.time()];
+    if let Ok(reference) = repo.find_reference(&format!("refs/remotes/{}/{}", remote, branch))
+        && let Ok(commit) = reference.peel_to_commit()
+    {
+        times.push(commit.time());
+    }
+    times.iter().filter_map(|time| DateTime::from_timestamp(time.seconds(), 0)).max()
+}
+
+fn get_recent_commits(path: &PathBuf, count: usize, cache: &CommitCache) -> Vec<CommitInfo> {
+    let mut commits = Vec::new();
+    
+    if let Ok(repo) = Repository::open(path) {
+        // Get current branch name
+        let current_branch = if let Ok(head) = repo.head() {
+            head.shorthand().unwrap_or("unknown").to_string()
+        } else {
+            "unknown".to_string()
+        };
+        
+        if let Ok(head) = repo.head().and_then(|head| head.peel_to_commit()) {
+            let mut cache = cache.lock().unwrap();
+            for oid in cache.walk(&repo, head.id(), None, count) {
+                if let Some(commit) = cache.commit(&repo, oid) {
+                    commits.push(CommitInfo {
+                        hash: format!("{:.8}", oid),
+                        author: commit.author,
+                        message: commit.summary,
+                        branch: current_branch.clone(),
+                        timestamp: DateTime::from_timestamp(commit.time, 0).unwrap_or_else(Utc::now),
+                    });
+                }
+            }
+            cache.save();
+        }
+    }
+    
+    commits
+}
+
+// Commits within `depth` of HEAD or of the current branch's upstream whose message or author contains `query`,
+// ignoring case, or whose hash starts with it. Answers "has the fix landed upstream yet?" as well as "is it here?".
+fn search_commits(repo_index: usize, path: &PathBuf, remote: &str, query: &str, depth: usize, cache: &CommitCache) -> Result<Vec<SearchHit>> {
+    let repo = Repository::open(path)?;
+    let head = repo.head()?;
+    let head_tip = head.peel_to_commit()?.id();
+    let upstream_tip = head
+        .shorthand()
+        .and_then(|branch| repo.find_reference(&format!("refs/remotes/{}/{}", remote, branch)).ok())
+        .and_then(|reference| reference.target());
+    
+    let mut cache = cache.lock().unwrap();
+    let mut oids = cache.walk(&repo, head_tip, None, depth);
+    if let Some(upstream_tip) = upstream_tip {
+        let local: std::collections::HashSet<git2::Oid> = oids.iter().copied().collect();
+        oids.extend(cache.walk(&repo, upstream_tip, None, depth).into_iter().filter(|oid| !local.contains(oid)));
+    }
+    cache.save();
+    drop(cache);
+    
+    let needle = query.to_lowercase();
+    let mut hits = Vec::new();
+    for oid in oids {
+        let commit = repo.find_commit(oid)?;
+        let author = commit.author();
+        let matched = oid.to_string().starts_with(&needle)
+            || commit.message().unwrap_or("").to_lowercase().contains(&needle)
+            || author.name().unwrap_or("").to_lowercase().contains(&needle)
+            || author.email().unwrap_or("").to_lowercase().contains(&needle);
+        if !matched {
+            continue;
+        }
+        // Whether the other side has it regardless of depth, the windows may not overlap
+        let reachable = |tip: git2::Oid| tip == oid || repo.graph_descendant_of(tip, oid).unwrap_or(false);
+        hits.push(SearchHit {
+            repo: repo_index,
+            hash: oid.to_string(),
+            author: author.name().unwrap_or("Unknown").to_string(),
+            summary: commit.summary().unwrap_or("").to_string(),
+            timestamp: DateTime::from_timestamp(commit.time().seconds(), 0).unwrap_or_else(Utc::now),
+            local: reachable(head_tip),
+            upstream: upstream_tip.is_some_and(reachable),
+        });
+        if hits.len() == SEARCH_RESULTS {
+            break;
+        }
+    }
+    hits.sort_by_key(|hit| std::cmp::Reverse(hit.timestamp));
+    Ok(hits)
+}
+
+fn load_commit_detail(repo: &RepoStatus, repo_index: usize, index: usize) -> Option<CommitDetail> {
+    let info = repo.recent_commits.get(index)?;
+    commit_detail(repo, repo_index, index, &info.hash)
+}
+
+fn commit_detail(repo: &RepoStatus, repo_index: usize, index: usize, hash: &str) -> Option<CommitDetail> {
+    let git_repo = Repository::open(&repo.path).ok()?;
+    let commit = git_repo.revparse_single(hash).ok()?.peel_to_commit().ok()?;
+    let author = commit.author();
+    let notes = if repo.notes {
+        git_repo.find_note(None, commit.id()).ok().and_then(|note| note.message().map(|message| message.trim_end().to_string()))
+    } else {
+        None
+    };
+    let message = commit.message().unwrap_or("").trim_end();
+    let trailers = commit_trailers(message);
+    // Trailers are always the last paragraph, so drop it from the message shown above them
+    let message = match message.rsplit_once("\n\n") {
+        Some((body, _)) if !trailers.is_empty() => body,
+        _ => message,
+    };
+    Some(CommitDetail {
+        repo: repo_index,
+        index,
+        hash: commit.id().to_string(),
+        author: format!("{} <{}>", author.name().unwrap_or("Unknown"), author.email().unwrap_or("")),
+        timestamp: DateTime::from_timestamp(commit.time().seconds(), 0).unwrap_or_else(Utc::now),
+        message: message.to_string(),
+        trailers,
+        notes,
+        files: commit_files(&git_repo, &commit).unwrap_or_default(),
+        selected_file: 0,
+        search: None,
+    })
+}
+
+fn open_tree(repo: &RepoStatus, repo_index: usize, rev: String, other_rev: Option<String>) -> Result<TreeView> {
+    let entries = tree_entries(&repo.path, &rev, "")?;
+    Ok(TreeView { repo: repo_index, rev, other_rev, dir: String::new(), entries, selected: 0 })
+}
+
+// Entries of a directory ("" or ending in "/") in a revision's tree, directories first
+fn tree_entries(path: &PathBuf, rev: &str, dir: &str) -> Result<Vec<(String, bool)>> {
+    let repo = Repository::open(path)?;
+    let root = repo.revparse_single(rev)?.peel_to_tree()?;
+    let tree = match dir.strip_suffix('/') {
+        Some(dir) => root.get_path(Path::new(dir))?.to_object(&repo)?.peel_to_tree()?,
+        None => root,
+    };
+    let mut entries: Vec<(String, bool)> = tree
+        .iter()
+        .map(|entry| (entry.name().unwrap_or("?").to_string(), entry.kind() == Some(git2::ObjectType::Tree)))
+        .collect();
+    entries.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    Ok(entries)
+}
+
+// A file's contents in a revision, numbered, cut off after PREVIEW_LINES
+fn preview_file(path: &PathBuf, rev: &str, file: &str) -> Result<Vec<String>> {
+    let repo = Repository::open(path)?;
+    let entry = repo.revparse_single(rev)?.peel_to_tree()?.get_path(Path::new(file))?;
+    if entry.kind() == Some(git2::ObjectType::Commit) {
+        anyhow::bail!("{} is a submodule", file);
+    }
+    let blob = entry.to_object(&repo)?.peel_to_blob()?;
+    if blob.is_binary() {
+        anyhow::bail!("{} is a binary file ({})", file, format_size(blob.size() as u64));
+    }
+    let content = String::from_utf8_lossy(blob.content());
+    let mut lines: Vec<String> = content
+        .lines()
+        .take(PREVIEW_LINES)
+        .enumerate()
+        .map(|(number, line)| format!("{:>5} {}", number + 1, line.replace('\t', "    ")))
+        .collect();
+    if content.lines().count() > PREVIEW_LINES {
+        lines.push(format!("(first {} lines)", PREVIEW_LINES));
+    }
+    Ok(lines)
+}
+
+// Diffstat of a commit against its first parent, or everything for a root commit
+fn commit_files(repo: &Repository, commit: &git2::Commit) -> Result<Vec<(String, usize, usize)>> {
+    let parent_tree = match commit.parent(0) {
+        Ok(parent) => Some(parent.tree()?),
+        Err(_) => None,
+    };
+    let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), None)?;
+    let mut files = Vec::new();
+    for index in 0..diff.deltas().len() {
+        let Some(patch) = git2::Patch::from_diff(&diff, index)? else {
+            continue;
+        };
+        let delta = patch.delta();
+        let file = delta.new_file().path().or(delta.old_file().path()).map(|path| path.display().to_string()).unwrap_or_default();
+        let (_, insertions, deletions) = patch.line_stats()?;
+        files.push((file, insertions, deletions));
+    }
+    Ok(files)
+}
+
+// `git blame` of a file as of a commit, one line per line of the file, with the lines that commit last changed
+// marked. Also returns the first marked line to scroll to. A file the commit deleted is blamed as of its parent.
+fn blame_lines(path: &PathBuf, hash: &str, file: &str) -> Result<(Vec<String>, usize)> {
+    let repo = Repository::open(path)?;
+    let commit = repo.revparse_single(hash)?.peel_to_commit()?;
+    let file_path = Path::new(file);
+    let target = if commit.tree()?.get_path(file_path).is_ok() { commit.clone() } else { commit.parent(0)? };
+    let blob = target.tree()?.get_path(file_path)?.to_object(&repo)?.peel_to_blob()?;
+    if blob.is_binary() {
+        anyhow::bail!("{} is a binary file", file);
+    }
+    
+    let mut options = git2::BlameOptions::new();
+    options.newest_commit(target.id());
+    let blame = repo.blame_file(file_path, Some(&mut options))?;
+    let mut lines = Vec::new();
+    let mut first_changed = None;
+    for (number, line) in String::from_utf8_lossy(blob.content()).lines().enumerate() {
+        let line = line.replace('\t', "    ");
+        let Some(hunk) = blame.get_line(number + 1) else {
+            lines.push(format!("  {:8} {:12} {:10} {:>5} {}", "", "", "", number + 1, line));
+            continue;
+        };
+        let changed = hunk.final_commit_id() == commit.id();
+        if changed && first_changed.is_none() {
+            first_changed = Some(number);
+        }
+        let signature = hunk.final_signature();
+        let date = DateTime::from_timestamp(signature.when().seconds(), 0).map(|time| time.format("%Y-%m-%d").to_string()).unwrap_or_default();
+        lines.push(format!(
+            "{} {:.8} {} {:10} {:>5} {}",
+            if changed { '>' } else { ' ' },
+            hunk.final_commit_id(),
+            pad_width(truncate_width(signature.name().unwrap_or("Unknown"), 12), 12, '<'),
+            date,
+            number + 1,
+            line,
+        ));
+    }
+    Ok((lines, first_changed.unwrap_or(0)))
+}
+
+// "Key: value" lines from the message's final paragraph, such as Reviewed-by or Fixes
+fn commit_trailers(message: &str) -> Vec<(String, String)> {
+    git2::message_trailers_strs(message)
+        .map(|trailers| trailers.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect())
+        .unwrap_or_default()
+}
+
+// Alert about incoming commits on the current branch's remote-tracking branch whose trailers match a rule
+fn check_trailers(repo: &mut RepoStatus, console_messages: &Arc<Mutex<Vec<ConsoleMessage>>>) {
+    if repo.trailer_alerts.is_empty() {
+        return;
+    }
+    let Ok(git_repo) = Repository::open(&repo.path) else {
+        return;
+    };
+    let tracking = format!("refs/remotes/{}/{}", repo.remote, repo.current_branch);
+    let Some(tip) = git_repo.find_reference(&tracking).ok().and_then(|reference| reference.target()) else {
+        return;
+    };
+    // The first tip seen on a branch is the baseline; switching branches starts over
+    let previous = match repo.incoming_tip.replace((tracking.clone(), tip)) {
+        Some((branch, previous)) if branch == tracking && previous != tip => previous,
+        _ => return,
+    };
+    if repo.muted {
+        return;
+    }
+    
+    for oid in new_commits(&git_repo, tip, previous, false) {
+        let Ok(commit) = git_repo.find_commit(oid) else {
+            continue;
+        };
+        for (key, value) in commit_trailers(commit.message().unwrap_or("")) {
+            if repo.trailer_alerts.iter().any(|alert| alert.matches(&key, &value)) {
+                log_console(
+                    console_messages,
+                    &repo.name,
+                    "Trailer",
+                    EventKind::Policy,
+                    format!("{}: {} in {:.8} by {}: {}", key, value, oid, commit.author().name().unwrap_or("Unknown"), commit.summary().unwrap_or("")),
+                );
+            }
+        }
+    }
+}
+
+// Worked out again whenever HEAD moves while the branch is ahead; new warnings go to the console
+fn check_outgoing(repo: &mut RepoStatus, console_messages: &Arc<Mutex<Vec<ConsoleMessage>>>) {
+    if repo.ahead == 0 || repo.operation.is_some() {
+        repo.push_warnings.clear();
+        repo.outgoing_tip = None;
+        return;
+    }
+    let Some(tip) = Repository::open(&repo.path).ok().and_then(|git_repo| git_repo.head().ok()?.target()) else {
+        return;
+    };
+    if repo.outgoing_tip == Some(tip) {
+        return;
+    }
+    repo.outgoing_tip = Some(tip);
+    let warnings = push_warnings(&repo.path, &repo.remote, repo.large_file_warning, repo.expected_email.as_deref()).unwrap_or_default();
+    if !repo.muted {
+        for warning in warnings.iter().filter(|warning| !repo.push_warnings.contains(warning)) {
+            log_console(console_messages, &repo.name, "Policy", EventKind::Policy, format!("Before pushing: {}", warning));
+        }
+    }
+    repo.push_warnings = warnings;
+}
+
+// Worked out again whenever the upstream moves while the branch is behind, so a pull never brings a surprise
+// dataset onto a laptop
+fn check_incoming_files(repo: &mut RepoStatus, console_messages: &Arc<Mutex<Vec<ConsoleMessage>>>) {
+    if repo.behind == 0 {
+        repo.incoming_large.clear();
+        repo.incoming_checked = None;
+        return;
+    }
+    let tracking = format!("refs/remotes/{}/{}", repo.remote, repo.current_branch);
+    let Some(tip) = Repository::open(&repo.path).ok().and_then(|git_repo| git_repo.find_reference(&tracking).ok()?.target()) else {
+        return;
+    };
+    if repo.incoming_checked == Some(tip) {
+        return;
+    }
+    repo.incoming_checked = Some(tip);
+    let files = incoming_large_files(&repo.path, tip, repo.large_file_warning).unwrap_or_default();
+    if !repo.muted {
+        for (file, size) in files.iter().filter(|(file, _)| !repo.incoming_large.iter().any(|(known, _)| known == file)) {
+            log_console(console_messages, &repo.name, "Git Monitor", EventKind::Commits, format!("Pull will add {} {}", format_size(*size), file));
+        }
+    }
+    repo.incoming_large = files;
+}
+
+// Files over `large_file` bytes that merging `upstream` would add or grow, biggest first. Only what changed on the
+// upstream since it and HEAD diverged counts; fetching has downloaded them already, the checkout is what hurts.
+fn incoming_large_files(path: &PathBuf, upstream: git2::Oid, large_file: u64) -> Result<Vec<(String, u64)>> {
+    let repo = Repository::open(path)?;
+    let head = repo.head()?.peel_to_commit()?.id();
+    let base = repo.find_commit(repo.merge_base(head, upstream)?)?.tree()?;
+    let diff = repo.diff_tree_to_tree(Some(&base), Some(&repo.find_commit(upstream)?.tree()?), None)?;
+    let odb = repo.odb()?;
+    let mut files: Vec<(String, u64)> = diff
+        .deltas()
+        .filter(|delta| delta.status() != git2::Delta::Deleted)
+        .filter_map(|delta| {
+            let file = delta.new_file();
+            let (size, _) = odb.read_header(file.id()).ok()?;
+            let name = file.path()?.display().to_string();
+            (size as u64 > large_file).then_some((name, size as u64))
+        })
+        .collect();
+    files.sort_by_key(|(_, size)| std::cmp::Reverse(*size));
+    Ok(files)
+}
+
+// Messages that say the commit isn't meant to be pushed as it is
+fn unfinished_message(summary: &str) -> bool {
+    let summary = summary.trim().to_lowercase();
+    let first_word = summary.split(|c: char| !c.is_alphanumeric()).next().unwrap_or("");
+    ["fixup!", "squash!", "amend!"].iter().any(|prefix| summary.starts_with(prefix))
+        || ["wip", "tmp", "temp", "todo", "xxx"].contains(&first_word)
+        || summary.contains("do not merge")
+        || summary.contains("do not push")
+}
+
+// Things worth a look before pushing HEAD's commits that the upstream doesn't have: files over `large_file` bytes,
+// merge commits, work-in-progress or fixup messages, and authors other than the expected email
+fn push_warnings(path: &PathBuf, remote: &str, large_file: u64, expected_email: Option<&str>) -> Result<Vec<String>> {
+    let repo = Repository::open(path)?;
+    let head = repo.head()?;
+    let branch = head.shorthand().unwrap_or("HEAD").to_string();
+    let upstream = repo.find_reference(&format!("refs/remotes/{}/{}", remote, branch))?.peel_to_commit()?.id();
+    let odb = repo.odb()?;
+    
+    let mut revwalk = repo.revwalk()?;
+    revwalk.push(head.peel_to_commit()?.id())?;
+    revwalk.hide(upstream)?;
+    let mut warnings = Vec::new();
+    let mut large_files: Vec<String> = Vec::new();
+    for oid in revwalk.flatten().take(PUSH_CHECK_COMMITS) {
+        let commit = repo.find_commit(oid)?;
+        let summary = commit.summary().unwrap_or("");
+        if commit.parent_count() > 1 {
+            warnings.push(format!("{:.8} is a merge commit: {}", oid, summary));
+        } else if unfinished_message(summary) {
+            warnings.push(format!("{:.8} looks unfinished: {}", oid, summary));
+        }
+        let email = commit.author().email().unwrap_or("").to_string();
+        if let Some(expected) = expected_email
+            && !email_matches(&email, expected)
+        {
+            warnings.push(format!("{:.8} is authored as {}, expected {}", oid, email, expected));
+        }
+        
+        // Merges bring in what was already pushed elsewhere; only a commit's own changes count
+        if commit.parent_count() > 1 {
+            continue;
+        }
+        let parent = commit.parents().next().map(|parent| parent.tree()).transpose()?;
+        let diff = repo.diff_tree_to_tree(parent.as_ref(), Some(&commit.tree()?), None)?;
+        for delta in diff.deltas() {
+            let file = delta.new_file();
+            // The header has the size without reading the blob itself
+            let Ok((size, _)) = odb.read_header(file.id()) else {
+                continue;
+            };
+            let name = file.path().map(|path| path.display().to_string()).unwrap_or_default();
+            if size as u64 > large_file && !large_files.contains(&name) {
+                warnings.push(format!("{:.8} adds {} ({})", oid, name, format_size(size as u64)));
+                large_files.push(name);
+            }
+        }
+    }
+    Ok(warnings)
+}
+
+// Notes aren't covered by the default refspecs, so they're fetched separately
+fn fetch_notes(path: &PathBuf, remote: &str, logins: &[Login]) -> Result<()> {
+    fetch_repo(path, remote, &["+refs/notes/commits:refs/notes/commits".to_string()], false, None, None, logins)
+}
+
+fn record_fetch_time(repo: &mut RepoStatus, fetch_time: Duration) {
+    repo.fetch_times.push(fetch_time);
+    let len = repo.fetch_times.len();
+    if len > FETCH_HISTORY {
+        repo.fetch_times.drain(0..len - FETCH_HISTORY);
+    }
+}
+
+// Ref names and tips as advertised by a remote
+type AdvertisedRefs = Vec<(String, git2::Oid)>;
+
+// Default branch and every ref a URL advertises, without a local repository
+fn ls_remote(url: &str, logins: &[Login]) -> Result<(Option<String>, AdvertisedRefs)> {
+    let mut remote = git2::Remote::create_detached(url)?;
+    let connection = remote.connect_auth(git2::Direction::Fetch, Some(remote_callbacks(logins)), None)?;
+    let head = connection
+        .default_branch()
+        .ok()
+        .and_then(|name| name.as_str().map(|name| name.trim_start_matches("refs/heads/").to_string()));
+    let refs = connection.list()?.iter().map(|head| (head.name().to_string(), head.oid())).collect();
+    Ok((head, refs))
+}
+
+// "New branch x at 1a2b3c4d", "Updated tag v1: 1a2b3c4d → 5e6f7a8b", "Deleted branch x"
+fn describe_ref_changes(kind: &str, before: &BTreeMap<String, git2::Oid>, after: &BTreeMap<String, git2::Oid>) -> Vec<String> {
+    let mut changes = Vec::new();
+    for (name, oid) in after {
+        match before.get(name) {
+            None => changes.push(format!("New {} {} at {:.8}", kind, name, oid)),
+            Some(previous) if previous != oid => changes.push(format!("Updated {} {}: {:.8} → {:.8}", kind, name, previous, oid)),
+            Some(_) => {}
+        }
+    }
+    for name in before.keys().filter(|name| !after.contains_key(*name)) {
+        changes.push(format!("Deleted {} {}", kind, name));
+    }
+    changes
+}
+
+// Remote-only repositories are polled through the reference advertisement; branch and tag changes go to the console
+fn check_remote_refs(repo: &mut RepoStatus, console_messages: &Arc<Mutex<Vec<ConsoleMessage>>>) {
+    let Some(url) = repo.url.clone() else {
+        return;
+    };
+    let started = Instant::now();
+    let result = ls_remote(&url, &repo.logins);
+    let Some(refs) = repo.remote_refs.as_mut() else {
+        return;
+    };
+    let (head, advertised) = match result {
+        Ok(result) => result,
+        Err(err) => {
+            // Reported once, not on every poll while the remote stays unreachable
+            if refs.error.is_none() && !repo.muted {
+                log_console(console_messages, &repo.name, "System", EventKind::Error, format!("Remote check failed: {} (url: {})", err, url));
+            }
+            refs.error = Some(err.to_string());
+            return;
+        }
+    };
+    refs.error = None;
+    
+    let mut branches = BTreeMap::new();
+    let mut tags = BTreeMap::new();
+    for (name, oid) in advertised {
+        if let Some(branch) = name.strip_prefix("refs/heads/") {
+            branches.insert(branch.to_string(), oid);
+        } else if let Some(tag) = name.strip_prefix("refs/tags/")
+            && !tag.ends_with("^{}")
+        {
+            tags.insert(tag.to_string(), oid);
+        }
+    }
+    let mut changes = Vec::new();
+    if refs.checked {
+        changes.extend(describe_ref_changes("branch", &refs.branches, &branches).into_iter().map(|change| (EventKind::Commits, change)));
+        changes.extend(describe_ref_changes("tag", &refs.tags, &tags).into_iter().map(|change| (EventKind::Tag, change)));
+    }
+    // The branch column shows the default branch and its tip
+    repo.current_branch = match head.as_ref().map(|name| (name, branches.get(name))) {
+        Some((name, Some(oid))) => format!("{} @ {:.8}", name, oid),
+        Some((name, None)) => name.clone(),
+        None => format!("{} branches", branches.len()),
+    };
+    refs.head = head;
+    refs.branches = branches;
+    refs.tags = tags;
+    refs.checked = true;
+    record_fetch_time(repo, started.elapsed());
+    
+    if changes.is_empty() {
+        return;
+    }
+    repo.last_activity = Some(Utc::now());
+    if !repo.muted {
+        repo.changed_at = Some(Instant::now());
+        for (kind, change) in changes {
+            log_console(console_messages, &repo.name, "Git Monitor", kind, change);
+        }
+    }
+}
+
+// One repository's scheduled check, run on a blocking thread against a copy of its status that take_check
+// writes back
+fn check_repo(repo: &mut RepoStatus, policy: &RefreshPolicy, now: Instant, console_messages: &Arc<Mutex<Vec<ConsoleMessage>>>) {
+    if repo.remote_refs.is_some() {
+        check_remote_refs(repo, console_messages);
+        return;
+    }
     
-    if config_path.exists() {
-        let content = std::fs::read_to_string(&config_path)?;
-        Ok(toml::from_str(&content)?)
-    } else {
-        // Return default config without creating file
-        Ok(Config {
-            repositories: vec![
-                RepoConfig {
-                    name: "Current Directory".to_string(),
-                    path: ".".to_string(),
-                    remote: Some("origin".to_string()),
+    if !policy.metered {
+        run_backup(repo, console_messages);
+    }
+    if repo.notes && !policy.metered {
+        // Offline is handled by the status fetch below
+        let _ = fetch_notes(&repo.path, &repo.remote, &repo.logins);
+    }
+    
+    let prev_counted = repo.counted;
+    match get_repo_status(repo, policy.metered) {
+        Ok((ahead, behind, branch, fetch_time)) => {
+            repo.last_error = None;
+            if let Some(fetch_time) = fetch_time {
+                record_fetch_time(repo, fetch_time);
+            }
+            let prev_ahead = repo.ahead;
+            let prev_behind = repo.behind;
+            let prev_dirty = repo.dirty;
+            let prev_branch = std::mem::replace(&mut repo.current_branch, branch);
+            let prev_operation = std::mem::replace(&mut repo.operation, repo_operation(&repo.path));
+        
+            repo.ahead = ahead;
+            repo.behind = behind;
+            repo.dirty = count_dirty_files(&repo.path, !repo.ignore_untracked);
+            repo.diffstat = if repo.dirty > 0 { local_diffstat(&repo.path) } else { (0, 0) };
+            let had_conflicts = !repo.conflicts.is_empty();
+            repo.conflicts = if repo.dirty > 0 { conflicted_files(&repo.path) } else { Vec::new() };
+            // Same two tips, same newest commit
+            if repo.counted.is_none() || repo.counted != prev_counted {
+                repo.last_commit = last_commit_time(&repo.path, &repo.remote);
+            }
+            repo.default_branch = Repository::open(&repo.path)
+                .ok()
+                .and_then(|git_repo| default_branch(&git_repo, &repo.remote))
+                .map(|(name, _)| name.trim_start_matches(&format!("{}/", repo.remote)).to_string());
+            repo.upstream_gone = upstream_gone(&repo.path);
+            match default_branch_candidate(&repo.path, &repo.remote, repo.upstream_gone.as_deref()) {
+                None => repo.renamed_default = None,
+                // Asks the server, so only until the rename is known, and only again once a fetch changed the
+                // remote's branches: the new default branch has to arrive before the answer can change
+                Some(old) if repo.renamed_default.as_ref().is_none_or(|(known, _)| *known != old) => {
+                    let asked = remote_branches_fingerprint(&repo.path, &repo.remote).map(|branches| (old.clone(), branches));
+                    if asked.is_none() || asked != repo.rename_asked {
+                        repo.rename_asked = asked;
+                        repo.renamed_default = default_branch_rename(&repo.path, &repo.remote, &old, &repo.logins);
+                        if let Some((old, new)) = &repo.renamed_default
+                            && !repo.muted
+                        {
+                            let message = format!("{} renamed its default branch {} to {}, R retargets the branches tracking it", repo.remote, old, new);
+                            log_console(console_messages, &repo.name, "Git Monitor", EventKind::Status, message);
+                        }
+                    }
                 }
-            ],
-            refresh_interval: 5,
-            max_commits: 5,
-            colors: Some(ColorConfig {
-                ahead_color: Some("yellow".to_string()),
-                behind_color: Some("cyan".to_string()),
-            }),
-        })
+                Some(_) => {}
+            }
+            
+            if repo.last_activity.is_some() && !repo.muted && (ahead != prev_ahead || behind != prev_behind) {
+                repo.changed_at = Some(now);
+            }
+            if repo.last_activity.is_some()
+                && (ahead != prev_ahead || behind != prev_behind || repo.dirty != prev_dirty || repo.current_branch != prev_branch)
+            {
+                repo.last_activity = Some(Utc::now());
+            } else {
+                repo.last_activity = repo.last_activity.max(repo.last_commit);
+            }
+            repo.poll_interval = policy.interval_for_repo(repo);
+            repo.next_check = now + repo.poll_interval;
+            
+            check_policy(repo, console_messages);
+            check_identity(repo, console_messages);
+            if !policy.metered {
+                check_pull_requests(repo, console_messages);
+            }
+            check_trailers(repo, console_messages);
+            check_outgoing(repo, console_messages);
+            check_incoming_files(repo, console_messages);
+        
+            // Muted repositories keep their status current but stay quiet
+            if repo.muted {
+                return;
+            }
+        
+            if repo.operation != prev_operation {
+                let message = match (&repo.operation, &prev_operation) {
+                    (Some(operation), _) => format!("Stopped while {}, ahead/behind are misleading until it's finished or aborted", operation),
+                    (None, Some(operation)) => format!("No longer {}", operation),
+                    (None, None) => unreachable!(),
+                };
+                log_console(console_messages, &repo.name, "Git Monitor", EventKind::Status, message);
+            }
+            
+            // Until the conflicts are resolved the counts churn with every `git add`, so only report
+            // entering and leaving that state
+            match (had_conflicts, repo.conflicts.len()) {
+                (false, 0) => {}
+                (true, 0) => log_console(console_messages, &repo.name, "Git Monitor", EventKind::Status, "Conflicts resolved".to_string()),
+                (false, count) => {
+                    let message = format!("{} conflicted files, holding notifications until they're resolved (X lists them)", count);
+                    log_console(console_messages, &repo.name, "Git Monitor", EventKind::Status, message);
+                    return;
+                }
+                (true, _) => return,
+            }
+        
+            // Add console messages for changes
+            if behind > prev_behind && ahead > prev_ahead {
+                let mut console_guard = console_messages.lock().unwrap();
+                console_guard.push(ConsoleMessage {
+                    timestamp: Utc::now(),
+                    repo: repo.name.clone(),
+                    author: "Git Monitor".to_string(),
+                    kind: EventKind::Commits,
+                    message: format!("Status changed: {} ahead (+{}), {} behind (+{})", 
+                        ahead, ahead - prev_ahead, behind, behind - prev_behind),
+                });
+            } else if behind > prev_behind {
+                let mut console_guard = console_messages.lock().unwrap();
+                console_guard.push(ConsoleMessage {
+                    timestamp: Utc::now(),
+                    repo: repo.name.clone(),
+                    author: "Git Monitor".to_string(),
+                    kind: EventKind::Commits,
+                    message: format!("New commits available: {} behind (+{})", 
+                        behind, behind - prev_behind),
+                });
+            } else if ahead > prev_ahead {
+                let mut console_guard = console_messages.lock().unwrap();
+                console_guard.push(ConsoleMessage {
+                    timestamp: Utc::now(),
+                    repo: repo.name.clone(),
+                    author: "Git Monitor".to_string(),
+                    kind: EventKind::Local,
+                    message: format!("Local commits added: {} ahead (+{})", 
+                        ahead, ahead - prev_ahead),
+                });
+            }
+        
+            // Add console message when caught up
+            if (prev_behind > 0 || prev_ahead > 0) && behind == 0 && ahead == 0 {
+                let mut console_guard = console_messages.lock().unwrap();
+                console_guard.push(ConsoleMessage {
+                    timestamp: Utc::now(),
+                    repo: repo.name.clone(),
+                    author: "GitOp".to_string(),
+                    kind: EventKind::Status,
+                    message: format!("Repository is now up to date!{}", repo.icons.celebration()),
+                });
+            }
+        
+            // Add console message for new commits
+            if ahead > prev_ahead {
+                let recent = get_recent_commits(&repo.path, (ahead - prev_ahead).min(5), &repo.commits);
+                let mut console_guard = console_messages.lock().unwrap();
+                for commit in recent {
+                    console_guard.push(ConsoleMessage {
+                        timestamp: Utc::now(),
+                        repo: repo.name.clone(),
+                        author: commit.author,
+                        kind: EventKind::Local,
+                        message: commit.message,
+                    });
+                }
+                // Keep only last 50 messages
+                let len = console_guard.len();
+                if len > 50 {
+                    console_guard.drain(0..len - 50);
+                }
+            }
+        }
+        Err(err) => {
+            // If git operation fails, add a detailed console message
+            if !repo.muted {
+                let mut console_guard = console_messages.lock().unwrap();
+                console_guard.push(ConsoleMessage {
+                    timestamp: Utc::now(),
+                    repo: repo.name.clone(),
+                    author: "System".to_string(),
+                    kind: EventKind::Error,
+                    message: format!("Git error: {} (path: {})", err, repo.path.display()),
+                });
+            }
+            repo.last_error = Some(err.to_string());
+        }
     }
 }
 
-fn get_repo_status(path: &PathBuf, remote: &str) -> Result<(usize, usize, String)> {
-    let repo = Repository::open(path)?;
+async fn monitor_repositories(
+    repos: Arc<Mutex<Vec<RepoStatus>>>,
+    console_messages: Arc<Mutex<Vec<ConsoleMessage>>>,
+    mut refresh: watch::Receiver<RefreshPolicy>,
+    wake: Arc<Notify>,
+) {
+    loop {
+        let policy = refresh.borrow_and_update().clone();
+        let now = Instant::now();
+        
+        // Only the scheduling happens under the lock, released at the end of this block. Fetches and everything
+        // else a check does run on blocking threads, so a stalled remote never holds up drawing.
+        let next_wake = {
+            let mut repos_guard = repos.lock().unwrap();
+            
+            // Each repository has its own schedule; the selected and expanded ones go first. One whose last
+            // check is still running waits for it.
+            let mut due: Vec<usize> = (0..repos_guard.len())
+                .filter(|&index| {
+                    let repo = &repos_guard[index];
+                    repo.host.is_none() && !repo.paused && !repo.archived && !repo.checking && repo.next_check <= now
+                })
+                .collect();
+            due.sort_by_key(|&index| !(repos_guard[index].focused || repos_guard[index].expanded));
+            
+            for index in due {
+                let repo = &mut repos_guard[index];
+                repo.last_update = now;
+                repo.poll_interval = policy.current_interval();
+                repo.next_check = now + repo.poll_interval;
+                repo.checking = true;
+                if repo.remote_refs.is_none() {
+                    if !policy.metered {
+                        run_maintenance(repo, &repos, &console_messages);
+                    }
+                    refresh_stats(repo, &repos, &console_messages);
+                }
+                
+                let started = repo.clone();
+                let repos = repos.clone();
+                let console_messages = console_messages.clone();
+                let wake = wake.clone();
+                let policy = policy.clone();
+                tokio::spawn(async move {
+                    let check = {
+                        let mut checked = started.clone();
+                        let console_messages = console_messages.clone();
+                        tokio::task::spawn_blocking(move || {
+                            check_repo(&mut checked, &policy, now, &console_messages);
+                            checked
+                        })
+                    };
+                    let result = check.await;
+                    let states = {
+                        let mut repos = repos.lock().unwrap();
+                        // Gone if the config was reloaded without it meanwhile
+                        if let Some(repo) = repos
+                            .iter_mut()
+                            .find(|repo| repo.path == started.path && (started.remote_refs.is_none() || repo.url == started.url))
+                        {
+                            match result {
+                                Ok(checked) => repo.take_check(&started, checked),
+                                // A panicking check must not leave the repository unscheduled for good
+                                Err(err) => {
+                                    repo.checking = false;
+                                    repo.last_error = Some(format!("Check failed: {}", err));
+                                    log_console(&console_messages, &repo.name, "System", EventKind::Error, format!("Check failed: {}", err));
+                                }
+                            }
+                            // A check that took longer than the interval is due again right away
+                            if repo.next_check <= Instant::now() {
+                                wake.notify_one();
+                            }
+                        }
+                        prompt::states(&repos)
+                    };
+                    // A prompt that can't be updated just shows older numbers
+                    let _ = prompt::write_state(&states);
+                });
+            }
+            // Wake for the next repository that is due, but at least once per base interval so reloaded repositories are picked up
+            repos_guard
+                .iter()
+                .filter(|repo| repo.host.is_none() && !repo.archived && !repo.checking)
+                .map(|repo| repo.next_check)
+                .min()
+                .map_or(now + policy.interval, |next| next.min(now + policy.interval))
+        };
+        
+        tokio::select! {
+            _ = time::sleep_until(next_wake.into()) => {}
+            _ = wake.notified() => {}
+            Ok(()) = refresh.changed() => {
+                // Reschedule so a shorter interval takes effect right away
+                let interval = refresh.borrow().current_interval();
+                let deadline = Instant::now() + interval;
+                for repo in repos.lock().unwrap().iter_mut() {
+                    repo.next_check = repo.next_check.min(deadline);
+                }
+            }
+        }
+    }
+}
+
+fn ui(f: &mut Frame, app: &mut App) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(1)
+        .constraints([Constraint::Min(0), Constraint::Length(10), Constraint::Length(3)].as_ref())
+        .split(f.size());
+    let (table_area, pane_area) = match app.layout {
+        LayoutMode::Table => (chunks[0], None),
+        LayoutMode::Split => {
+            let halves = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(60), Constraint::Percentage(40)].as_ref())
+                .split(chunks[0]);
+            (halves[0], Some(halves[1]))
+        }
+    };
+
+    // Repository table (minus borders and header row)
+    app.page_size = (chunks[0].height as usize).saturating_sub(3).max(1);
+    let repos = app.repos.lock().unwrap();
+    let order = app.display_order(&repos);
+    let selected_position = app.selected_position(&order);
+    app.table_state.select(if order.is_empty() {
+        None
+    } else {
+        Some(calculate_table_row(&repos, &order, selected_position))
+    });
     
-    // Get current branch
-    let head = repo.head()?;
-    let current_branch = head.shorthand().unwrap_or("unknown").to_string();
+    let mut rows = Vec::new();
+    for (position, repo) in order.iter().map(|&i| &repos[i]).enumerate() {
+        if starts_group(&repos, &order, position) {
+            let header = match &repo.host {
+                _ if repo.archived => "── archived ──".to_string(),
+                Some(host) if repo.last_update.elapsed() > agent::OFFLINE_AFTER => format!("── {} (offline) ──", host),
+                Some(host) => format!("── {} ──", host),
+                None => "── local ──".to_string(),
+            };
+            rows.push(Row::new(vec![Cell::from(header)]).style(Style::default().fg(Color::DarkGray).add_modifier(Modifier::BOLD)));
+        }
+        
+        let highlight = app.highlights.iter().find(|rule| rule.when.matches(repo));
+        let style = if repo.hidden || repo.archived {
+            Style::default().fg(Color::DarkGray) // Only visible while revealing hidden or archived repos
+        } else if let Some(rule) = highlight {
+            Style::default().fg(parse_color(&rule.color))
+        } else {
+            Style::default()
+        };
+        let style = match (app.flash, repo.changed_at) {
+            (Some((duration, color)), Some(changed)) if changed.elapsed() < duration => style.bg(color).fg(Color::Black),
+            _ => style,
+        };
+        
+        // Create cells with color coding for ahead/behind; remote-only repositories and branches whose upstream
+        // is gone have nothing to compare, and the counts are grayed out in the middle of a rebase or merge since
+        // HEAD isn't where it will end up
+        let ahead_cell = if repo.remote_refs.is_some() || repo.upstream_gone.is_some() {
+            Cell::from("-").style(Style::default().fg(Color::DarkGray))
+        } else if repo.operation.is_some() {
+            Cell::from(repo.ahead.to_string()).style(Style::default().fg(Color::DarkGray))
+        } else if repo.ahead > 0 {
+            Cell::from(format!("↑{}", repo.ahead)).style(fg(app.colors.ahead))
+        } else {
+            Cell::from("0")
+        };
+        
+        let behind_cell = if repo.remote_refs.is_some() || repo.upstream_gone.is_some() {
+            Cell::from("-").style(Style::default().fg(Color::DarkGray))
+        } else if repo.operation.is_some() {
+            Cell::from(repo.behind.to_string()).style(Style::default().fg(Color::DarkGray))
+        } else if repo.behind > 0 {
+            Cell::from(format!("↓{}{}", repo.behind, repo.behind_bound())).style(fg(app.colors.behind))
+        } else {
+            Cell::from("0")
+        };
+        
+        let icons = app.repo_defaults.icons;
+        let marker = match (repo.marked, repo.pinned) {
+            (true, _) => icons.marked(),
+            (false, true) => icons.pinned(),
+            (false, false) => "",
+        };
+        let mut muted = match (repo.muted, repo.hidden) {
+            (true, true) => " [muted] [hidden]",
+            (true, false) => " [muted]",
+            (false, true) => " [hidden]",
+            (false, false) => "",
+        }
+        .to_string();
+        if repo.paused {
+            muted.push_str(" [paused]");
+        }
+        let mut errors = Vec::new();
+        if let Some(operation) = &repo.operation {
+            errors.push(format!("[{}]", operation));
+        }
+        if !repo.conflicts.is_empty() {
+            errors.push(format!("[{} conflicts]", repo.conflicts.len()));
+        }
+        if let Some(refs) = &repo.remote_refs {
+            muted.push_str(&format!(" [remote, {} tags]", refs.tags.len()));
+            if refs.error.is_some() {
+                errors.push("[unreachable]".to_string());
+            }
+        }
+        // Backup lag: failing pushes or branches that haven't reached the backup remote
+        if let Some(backup) = &repo.backup {
+            if backup.error.is_some() {
+                errors.push("[backup failed]".to_string());
+            } else if backup.lag > 0 {
+                muted.push_str(&format!(" [backup -{}]", backup.lag));
+            }
+        }
+        if let Some(policy) = &repo.policy {
+            if policy.direct_pushes > 0 {
+                errors.push(format!("[direct push {}]", policy.direct_pushes));
+            }
+            if policy.violations > policy.direct_pushes {
+                errors.push(format!("[policy {}]", policy.violations - policy.direct_pushes));
+            }
+        }
+        if let (Some(stats), Some(limit)) = (&repo.stats, repo.size_warning)
+            && stats.git_size > limit
+        {
+            errors.push(format!("[{}]", format_size(stats.git_size)));
+        }
+        if repo.wrong_identity.is_some() {
+            errors.push("[identity]".to_string());
+        }
+        if !repo.push_warnings.is_empty() {
+            errors.push(format!("[pre-push {}]", repo.push_warnings.len()));
+        }
+        if !repo.incoming_large.is_empty() {
+            errors.push(format!("[pull +{}]", format_size(repo.incoming_large.iter().map(|(_, size)| size).sum())));
+        }
+        if let Some(progress) = *repo.transfer.lock().unwrap() {
+            muted.push_str(&format!(" [{}]", progress.describe()));
+        }
+        
+        let mut name_spans = vec![Span::raw(marker)];
+        // Priority dot while sorted by attention
+        if matches!(app.sort_key, SortKey::Attention) {
+            let color = match repo.attention() {
+                0 => Color::Green,
+                1..100 => Color::Yellow,
+                _ => Color::Red,
+            };
+            name_spans.push(Span::styled(icons.priority(), fg(color)));
+        }
+        name_spans.extend([Span::styled(repo.name.clone(), fg(app.colors.repo)), Span::raw(muted)]);
+        for error in errors {
+            name_spans.push(Span::raw(" "));
+            name_spans.push(Span::styled(error, fg(app.colors.error)));
+        }
+        for label in &repo.labels {
+            name_spans.push(Span::raw(" "));
+            name_spans.push(Span::styled(format!("[{}]", label), Style::default().fg(Color::Magenta)));
+        }
+        let name_line = Line::from(name_spans);
+        let (name_cell, height) = match &repo.description {
+            Some(description) if app.show_descriptions => (
+                Cell::from(Text::from(vec![
+                    name_line,
+                    Line::styled(format!("  {}", description), Style::default().fg(Color::DarkGray)),
+                ])),
+                2,
+            ),
+            _ => (Cell::from(name_line), 1),
+        };
+        
+        let mut cells = vec![name_cell, ahead_cell, behind_cell];
+        if app.show_size {
+            cells.push(match &repo.stats {
+                Some(stats) => Cell::from(format_size(stats.disk_usage)),
+                None if repo.host.is_none() && repo.remote_refs.is_none() => Cell::from("…").style(Style::default().fg(Color::DarkGray)),
+                None => Cell::from(""),
+            });
+        }
+        if app.show_fetch_time {
+            cells.push(Cell::from(repo.fetch_times.last().map(|time| format_duration(*time)).unwrap_or_default()));
+        }
+        if app.show_diffstat {
+            cells.push(match repo.diffstat {
+                (0, 0) => Cell::from(""),
+                (insertions, deletions) => Cell::from(Line::from(vec![
+                    Span::styled(format!("+{}", insertions), fg(app.colors.ahead)),
+                    Span::raw(" "),
+                    Span::styled(format!("-{}", deletions), fg(app.colors.behind)),
+                ])),
+            });
+        }
+        let mut branch_spans = vec![Span::styled(format!("{}{}", icons.branch(), repo.current_branch), fg(app.colors.branch))];
+        // Often the first hint of a branch left checked out after a review
+        if let Some(default) = &repo.default_branch
+            && *default != repo.current_branch
+        {
+            branch_spans.push(Span::styled(format!(" ≠{}", default), Style::default().fg(Color::DarkGray)));
+        }
+        if repo.upstream_gone.is_some() {
+            branch_spans.push(Span::styled(" (gone)", fg(app.colors.error)));
+        }
+        if repo.dirty > 0 {
+            branch_spans.push(Span::styled(format!(" {}{}", icons.dirty(), repo.dirty), fg(app.colors.dirty)));
+        }
+        cells.push(Cell::from(Line::from(branch_spans)));
+        rows.push(Row::new(cells).height(height).style(style));
+        
+        // Add expanded commits if selected, or the tip of every branch for remote-only repositories
+        if repo.expanded
+            && let Some(refs) = &repo.remote_refs
+        {
+            for (branch, oid) in &refs.branches {
+                let mut cells = vec![Cell::from(format!("  └─ {:.8}", oid)), Cell::from(""), Cell::from("")];
+                for shown in [app.show_size, app.show_fetch_time, app.show_diffstat] {
+                    if shown {
+                        cells.push(Cell::from(""));
+                    }
+                }
+                cells.push(Cell::from(format!("({})", branch)));
+                rows.push(Row::new(cells).style(Style::default().fg(Color::Gray)));
+            }
+        } else if repo.expanded {
+            for commit in &repo.recent_commits {
+                let mut cells = vec![
+                    Cell::from(format!("  └─ {} - {}", commit.hash, commit.message)),
+                    Cell::from(commit.author.clone()),
+                    Cell::from(commit.timestamp.format("%m/%d %H:%M").to_string()),
+                ];
+                for shown in [app.show_size, app.show_fetch_time, app.show_diffstat] {
+                    if shown {
+                        cells.push(Cell::from(""));
+                    }
+                }
+                cells.push(Cell::from(format!("({})", commit.branch)));
+                rows.push(Row::new(cells).style(Style::default().fg(Color::Gray)));
+            }
+        }
+    }
+    // The archived section collapses to its header, after everything that can be selected
+    let archived_count = repos.iter().filter(|repo| repo.archived).count();
+    if archived_count > 0 && !app.show_archived {
+        let header = format!("── archived ({}, Z to show) ──", archived_count);
+        rows.push(Row::new(vec![Cell::from(header)]).style(Style::default().fg(Color::DarkGray).add_modifier(Modifier::BOLD)));
+    }
     
-    // Try to fetch from remote (ignore errors for offline/network issues)
-    if let Ok(mut remote_ref) = repo.find_remote(remote) {
-        let _ = remote_ref.fetch(&[] as &[&str], None, None);
+    // Optional columns take their space from the others
+    let columns = app.table_columns();
+    let extra = columns.len() as u16 - 4;
+    let mut widths = vec![Constraint::Percentage(35 - 2 * extra), Constraint::Percentage(15 - 3 * extra), Constraint::Percentage(15 - 3 * extra)];
+    widths.extend((0..extra).map(|_| Constraint::Percentage(10)));
+    widths.push(Constraint::Percentage(35 - 2 * extra));
+    // The column sorted by gets an arrow pointing the way it goes
+    let header: Vec<String> = columns
+        .iter()
+        .map(|&(title, key)| {
+            if app.sort_key == key {
+                format!("{}{}", title, app.repo_defaults.icons.sort_arrow(key.descending() != app.sort_reversed))
+            } else {
+                title.to_string()
+            }
+        })
+        .collect();
+    // Where the table puts each column, as Table lays them out, for header clicks
+    let inner = table_area.inner(&ratatui::layout::Margin { horizontal: 1, vertical: 1 });
+    let cells = Layout::horizontal(widths.clone()).flex(Flex::Start).spacing(1).split(Rect { height: 1, ..inner });
+    app.header_cells = cells.iter().zip(&columns).map(|(&area, &(_, key))| (area, key)).collect();
+    
+    let mut title = "GitOp - Repositories".to_string();
+    if let Some(label) = &app.label_filter {
+        title.push_str(&format!(" [label: {}]", label));
+    }
+    if let Some(text) = &app.name_filter {
+        title.push_str(&format!(" [filter: {}]", text));
     }
+    if !matches!(app.sort_key, SortKey::Config) || app.sort_reversed {
+        title.push_str(&format!(" [sort: {}{}]", app.sort_key.name(), if app.sort_reversed { ", reversed" } else { "" }));
+    }
+    let hidden_count = repos.iter().filter(|repo| repo.hidden).count();
+    if hidden_count > 0 && !app.show_hidden {
+        title.push_str(&format!(" [{} hidden]", hidden_count));
+    }
+    let refresh = app.refresh.borrow();
+    title.push_str(&format!(
+        " [refresh: {}s{}{}]",
+        refresh.current_interval().as_secs(),
+        if refresh.adaptive.is_some() { " adaptive" } else { "" },
+        if refresh.metered { " metered" } else { "" }
+    ));
+    drop(refresh);
     
-    let local_oid = head.target().unwrap();
-    let remote_branch = format!("{}/{}", remote, current_branch);
+    let table = Table::new(rows, widths)
+        .block(Block::default().title(title).borders(Borders::ALL))
+        .header(Row::new(header)
+            .style(fg(app.colors.header).add_modifier(Modifier::BOLD)))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED).fg(Color::White));
     
-    // Try to find remote branch, if it doesn't exist, assume 0 ahead/behind
-    if let Ok(remote_ref) = repo.find_reference(&format!("refs/remotes/{}", remote_branch)) {
-        if let Some(remote_oid) = remote_ref.target() {
-            // Calculate ahead/behind
-            let (ahead, behind) = repo.graph_ahead_behind(local_oid, remote_oid)?;
-            return Ok((ahead, behind, current_branch));
+    f.render_stateful_widget(table, table_area, &mut app.table_state);
+    
+    if let Some(area) = pane_area {
+        let selected = order.get(selected_position).copied();
+        let pane = app.side_pane.as_ref().filter(|pane| Some(pane.repo) == selected);
+        let (title, lines) = match selected.map(|index| &repos[index]) {
+            Some(repo) => (repo.name.clone(), side_pane_lines(repo, pane, &app.colors)),
+            None => ("Details".to_string(), Vec::new()),
+        };
+        let details = Paragraph::new(lines).block(Block::default().title(title).borders(Borders::ALL)).wrap(Wrap { trim: false });
+        f.render_widget(details, area);
+    }
+    
+    // Console
+    let console_messages = app.console_messages.lock().unwrap();
+    let selected_name = app.get_selected_repo_index(&repos).map(|index| console_name(&repos[index]));
+    let console_text: Vec<Line> = app
+        .console_view(&console_messages, &repos)
+        .into_iter()
+        .map(|index| &console_messages[index])
+        .enumerate()
+        .map(|(position, msg)| {
+            let dimmed = app.console_filter == ConsoleFilter::Dim && selected_name.as_ref().is_some_and(|name| msg.repo != *name);
+            let author_style = match author_color(&msg.author) {
+                Some(color) if app.author_colors && !dimmed => Style::default().fg(color),
+                _ => Style::default(),
+            };
+            let mut line = app.console_format.line(msg, author_style);
+            if dimmed {
+                line = line.style(Style::default().fg(Color::DarkGray));
+            }
+            if app.console_selected == Some(position) {
+                line.patch_style(Style::default().add_modifier(Modifier::REVERSED))
+            } else {
+                line
+            }
+        })
+        .collect();
+    
+    // Which message each row shows, for mouse clicks; wrapping is estimated from the line widths
+    let console_area = chunks[1].inner(&ratatui::layout::Margin { horizontal: 1, vertical: 1 });
+    let mut rows = Vec::new();
+    for (position, line) in console_text.iter().enumerate() {
+        let height = line.width().div_ceil(console_area.width.max(1) as usize).max(1);
+        rows.extend(std::iter::repeat_n(position, height));
+    }
+    rows.truncate(console_area.height as usize);
+    app.console_rows = (console_area, rows);
+    
+    let mut title = "Console".to_string();
+    match (app.console_filter, &selected_name) {
+        (ConsoleFilter::Dim, Some(name)) => title.push_str(&format!(" [highlighting {}]", name)),
+        (ConsoleFilter::Only, Some(name)) => title.push_str(&format!(" [only {}]", name)),
+        _ => {}
+    }
+    if app.console_selected.is_some() {
+        title.push_str(" (↑/↓: Select  Enter: Jump to Repo  Tab: Back)");
+    }
+    // Not trimmed, so padding from the console format survives on wrapped lines
+    let console = Paragraph::new(console_text)
+        .block(Block::default().title(title).borders(Borders::ALL))
+        .wrap(Wrap { trim: false });
+    
+    f.render_widget(console, chunks[1]);
+    
+    // Help footer
+    let help_text = "↑/↓: Navigate  PgUp/PgDn: Page  Home/End: First/Last  Enter: Expand/Collapse  Space: Mark  :: Command  1-9: Sort by Column  f/p/m/o: Fetch/Pull/Mute/Open  P: Pull All  i: Details  c: Commits  v/u: Incoming/Outgoing  t: Tag  d: Diverged  D: Pull Preview  g: Graph  T: File Tree  S: Status  X: Conflicts  M: Remotes  R: Retarget Renamed Default  /: Search All  b: Branches  w: Worktree  B: Clean Branches  l: Label Filter  s: Sort  *: Pin  h/H: Hide/Reveal  z/Z: Archive/Show Archived  +/-: Refresh Interval  W: Save Interval  N: Metered  a: Add Repo  e: Edit Config  C: Clone Missing  Tab: Console  F: Console Filter  q: Quit";
+    let help = match (&app.command_line, &app.command_error) {
+        (Some(input), _) => {
+            f.set_cursor(chunks[2].x + 2 + input.width() as u16, chunks[2].y + 1);
+            Paragraph::new(format!(":{}", input))
+                .block(Block::default().title("Command (Enter: Run  Tab: Complete  Esc: Cancel)").borders(Borders::ALL))
+        }
+        (None, Some(error)) => Paragraph::new(error.as_str())
+            .block(Block::default().title("Command").borders(Borders::ALL))
+            .style(fg(app.colors.error)),
+        (None, None) if app.layout == LayoutMode::Split => Paragraph::new(help_text.replace("Enter: Expand/Collapse", "Enter: Details"))
+            .block(Block::default().title("Controls").borders(Borders::ALL))
+            .style(Style::default().fg(Color::Gray)),
+        (None, None) => Paragraph::new(help_text)
+            .block(Block::default().title("Controls").borders(Borders::ALL))
+            .style(Style::default().fg(Color::Gray)),
+    };
+    
+    f.render_widget(help, chunks[2]);
+    
+    if let Some(popup) = &app.popup {
+        render_popup(f, popup, &repos);
+    }
+}
+
+// The split layout's pane: where the branch stands, the remotes, what last went wrong and the recent commits,
+// in the detail view's "Label:    " layout
+fn side_pane_lines(repo: &RepoStatus, pane: Option<&SidePane>, colors: &Palette) -> Vec<Line<'static>> {
+    let gray = Style::default().fg(Color::Gray);
+    let mut lines = Vec::new();
+    let mut errors = Vec::new();
+    if let Some(refs) = &repo.remote_refs {
+        lines.push(Line::from(format!("URL:      {}", repo.url.as_deref().unwrap_or_default())));
+        lines.push(Line::from(format!("Default:  {}", refs.head.as_deref().unwrap_or("unknown"))));
+        errors.extend(refs.error.clone());
+        if !refs.branches.is_empty() {
+            lines.push(Line::from(""));
+            lines.push(Line::from("Branches:"));
+            lines.extend(refs.branches.iter().map(|(branch, oid)| Line::styled(format!("  {:.8} {}", oid, branch), gray)));
+        }
+    } else {
+        if let Some(host) = &repo.host {
+            lines.push(Line::from(format!("Host:     {}", host)));
+        }
+        let default = match &repo.default_branch {
+            Some(default) if *default != repo.current_branch => format!(" (default is {})", default),
+            Some(_) => " (default)".to_string(),
+            None => String::new(),
+        };
+        lines.push(Line::from(format!("Branch:   {}{}", repo.current_branch, default)));
+        let upstream = format!("Upstream: {}/{}", repo.remote, repo.current_branch);
+        lines.push(match &repo.upstream_gone {
+            Some(_) => Line::from(vec![Span::raw(upstream), Span::styled(" (gone)", Style::default().fg(colors.error))]),
+            None => Line::from(vec![
+                Span::raw(upstream),
+                Span::styled(format!("  ↑{}", repo.ahead), Style::default().fg(colors.ahead)),
+                Span::styled(format!(" ↓{}{}", repo.behind, repo.behind_bound()), Style::default().fg(colors.behind)),
+            ]),
+        });
+        if repo.dirty > 0 {
+            lines.push(Line::from(format!("Changes:  {} files, +{} -{} lines", repo.dirty, repo.diffstat.0, repo.diffstat.1)));
+        }
+        if let Some(operation) = &repo.operation {
+            lines.push(Line::from(format!("State:    {}", operation)));
+        }
+        for (position, (name, url)) in pane.map(|pane| pane.remotes.as_slice()).unwrap_or_default().iter().enumerate() {
+            lines.push(Line::from(format!("{}{}  {}", if position == 0 { "Remotes:  " } else { "          " }, name, url)));
+        }
+        errors.extend(repo.last_error.clone());
+        if let Some(error) = repo.backup.as_ref().and_then(|backup| backup.error.as_ref()) {
+            errors.push(format!("backup: {}", error));
+        }
+        if !repo.recent_commits.is_empty() {
+            lines.push(Line::from(""));
+            lines.push(Line::from("Recent commits:"));
+            for commit in &repo.recent_commits {
+                lines.push(Line::from(format!("  {} {}", commit.hash, commit.message)));
+                lines.push(Line::styled(format!("    {}, {}", commit.author, commit.timestamp.format("%m/%d %H:%M")), gray));
+            }
+        }
+    }
+    // Right under the first lines, where they are seen
+    let position = lines.iter().position(|line| line.width() == 0).unwrap_or(lines.len());
+    let errors = errors.into_iter().enumerate().map(|(index, error)| {
+        Line::styled(format!("{}{}", if index == 0 { "Error:    " } else { "          " }, error), Style::default().fg(colors.error))
+    });
+    lines.splice(position..position, errors);
+    lines
+}
+
+fn render_popup(f: &mut Frame, popup: &Popup, repos: &[RepoStatus]) {
+    let title_buffer;
+    let (title, lines) = match popup {
+        Popup::PullAll { targets, skipped } => {
+            let mut lines = vec![format!("Fast-forward pull {} repositories:", targets.len())];
+            for repo in targets.iter().filter_map(|&i| repos.get(i)) {
+                lines.push(format!("  {} ({}) ↓{}", repo.name, repo.current_branch, repo.behind));
+            }
+            if !skipped.is_empty() {
+                lines.push(String::new());
+                lines.push("Skipped (uncommitted changes):".to_string());
+                for name in skipped {
+                    lines.push(format!("  {}", name));
+                }
+            }
+            lines.push(String::new());
+            lines.push("y/Enter: Pull  n/Esc: Cancel".to_string());
+            ("Pull All Behind", lines)
+        }
+        Popup::Welcome => {
+            let lines = vec![
+                "No config file was found, so only the current directory is monitored.".to_string(),
+                String::new(),
+                "  c    Create a default config file".to_string(),
+                "  s    Scan a directory for repositories and save them to a new config".to_string(),
+                "  d    Open the documentation".to_string(),
+                "  Esc  Continue without a config".to_string(),
+            ];
+            ("Welcome to GiTop", lines)
+        }
+        Popup::Diverged(index) => {
+            let Some(repo) = repos.get(*index) else {
+                return;
+            };
+            let upstream = format!("{}/{}", repo.remote, repo.current_branch);
+            let lines = vec![
+                format!("{} has diverged from {}:", repo.current_branch, upstream),
+                format!("  ↑{} local commits not on {}", repo.ahead, upstream),
+                format!("  ↓{}{} commits on {} not in {}", repo.behind, repo.behind_bound(), upstream, repo.current_branch),
+                String::new(),
+                "Rebasing replays the local commits on top of the upstream ones.".to_string(),
+                String::new(),
+                format!("r: Interactive rebase onto {}  Esc: Close", upstream),
+            ];
+            ("Diverged Branch", lines)
+        }
+        Popup::Branches { repo, default_branch, branches } => {
+            let Some(repo) = repos.get(*repo) else {
+                return;
+            };
+            let base_header = match default_branch {
+                Some(default_branch) => format!("vs {}", default_branch),
+                None => "vs default".to_string(),
+            };
+            let rows: Vec<[String; 5]> = branches
+                .iter()
+                .map(|branch| {
+                    let upstream = match &branch.upstream {
+                        Some(upstream) if branch.upstream_gone => format!("{} [gone]", upstream),
+                        Some(upstream) => upstream.clone(),
+                        None => "-".to_string(),
+                    };
+                    let sync = match (&branch.upstream, branch.upstream_gone) {
+                        (Some(_), false) if branch.ahead == 0 && branch.behind == 0 => "=".to_string(),
+                        (Some(_), false) => format!("↑{} ↓{}", branch.ahead, branch.behind),
+                        _ => String::new(),
+                    };
+                    let base = match branch.base {
+                        Some((0, 0)) => "=".to_string(),
+                        Some((ahead, behind)) => format!("↑{} ↓{}", ahead, behind),
+                        None => String::new(),
+                    };
+                    let name = format!("{} {}", if branch.current { "*" } else { " " }, branch.name);
+                    [name, upstream, sync, format_age(branch.last_commit), base]
+                })
+                .collect();
+            let header = ["  Branch".to_string(), "Upstream".to_string(), "Sync".to_string(), "Age".to_string(), base_header];
+            let mut widths = header.clone().map(|cell| cell.width());
+            for row in &rows {
+                for (width, cell) in widths.iter_mut().zip(row) {
+                    *width = (*width).max(cell.width());
+                }
+            }
+            let format_row = |row: &[String; 5]| {
+                row.iter().zip(widths).map(|(cell, width)| pad_width(cell, width, '<')).collect::<Vec<_>>().join("  ").trim_end().to_string()
+            };
+            let mut lines = vec![format_row(&header)];
+            lines.extend(rows.iter().map(format_row));
+            lines.push(String::new());
+            lines.push("Esc: Close".to_string());
+            (repo.name.as_str(), lines)
+        }
+        Popup::WorkTree { repo, lines, .. } => {
+            let Some(repo) = repos.get(*repo) else {
+                return;
+            };
+            title_buffer = format!("{} - Working Tree (Esc: Close)", repo.name);
+            (title_buffer.as_str(), lines.clone())
+        }
+        Popup::RemoteMatrix { repo, lines } => {
+            let Some(repo) = repos.get(*repo) else {
+                return;
+            };
+            title_buffer = format!("{} - Remotes (Esc: Close)", repo.name);
+            (title_buffer.as_str(), lines.clone())
+        }
+        Popup::Conflicts { repo, lines, .. } => {
+            let Some(repo) = repos.get(*repo) else {
+                return;
+            };
+            title_buffer = format!("{} - Conflicts (Esc: Close)", repo.name);
+            (title_buffer.as_str(), lines.clone())
+        }
+        Popup::Tree(tree) => {
+            let Some(repo) = repos.get(tree.repo) else {
+                return;
+            };
+            let mut lines: Vec<String> = tree
+                .entries
+                .iter()
+                .enumerate()
+                .map(|(index, (name, is_dir))| {
+                    let marker = if index == tree.selected { "> " } else { "  " };
+                    format!("{}{}{}", marker, name, if *is_dir { "/" } else { "" })
+                })
+                .collect();
+            if lines.is_empty() {
+                lines.push("  (empty)".to_string());
+            }
+            lines.push(String::new());
+            let switch = match &tree.other_rev {
+                Some(other) => format!("  u: Show {}", other),
+                None => String::new(),
+            };
+            lines.push(format!("↑/↓: Select  Enter: Open  ←: Up{}  Esc: Close", switch));
+            let rev = if tree.rev.len() == 40 { format!("{:.8}", tree.rev) } else { tree.rev.clone() };
+            title_buffer = format!("{} @ {}:/{}", repo.name, rev, tree.dir);
+            (title_buffer.as_str(), lines)
+        }
+        Popup::FilePreview { tree, file, lines, .. } => {
+            let rev = if tree.rev.len() == 40 { format!("{:.8}", tree.rev) } else { tree.rev.clone() };
+            title_buffer = format!("{} @ {} (read-only, Esc: Back)", file, rev);
+            (title_buffer.as_str(), lines.clone())
+        }
+        Popup::Blame { commit, file, lines, .. } => {
+            title_buffer = format!("{} at {:.8} (Esc: Back)", file, commit.hash);
+            (title_buffer.as_str(), lines.clone())
+        }
+        Popup::Graph { repo, lines, .. } => {
+            let Some(repo) = repos.get(*repo) else {
+                return;
+            };
+            (repo.name.as_str(), lines.clone())
+        }
+        Popup::PullPreview { repo, preview } => {
+            let Some(repo) = repos.get(*repo) else {
+                return;
+            };
+            let mut lines = vec![match &preview.outcome {
+                PullOutcome::UpToDate => format!("{} is up to date with {}", preview.branch, preview.upstream),
+                PullOutcome::FastForward => format!("Fast-forward {} by {} commits from {}", preview.branch, preview.commits, preview.upstream),
+                PullOutcome::Merge => format!("Merge {} commits from {} into {}, no conflicts", preview.commits, preview.upstream, preview.branch),
+                PullOutcome::Conflicts(paths) => format!("Merging {} commits from {} conflicts in {} files:", preview.commits, preview.upstream, paths.len()),
+            }];
+            if let PullOutcome::Conflicts(paths) = &preview.outcome {
+                lines.extend(paths.iter().map(|path| format!("  {}", path)));
+            }
+            if matches!(preview.outcome, PullOutcome::Merge | PullOutcome::Conflicts(_)) {
+                lines.push("p only fast-forwards, so this needs a manual pull or a rebase (d)".to_string());
+            }
+            if !preview.blocked.is_empty() {
+                lines.push(String::new());
+                lines.push(format!("Uncommitted changes to files the pull updates: {}", preview.blocked.join(", ")));
+            }
+            if !repo.incoming_large.is_empty() {
+                lines.push(String::new());
+                lines.push("Large files the pull adds:".to_string());
+                lines.extend(repo.incoming_large.iter().map(|(file, size)| format!("  {} {}", format_size(*size), file)));
+            }
+            if !preview.files.is_empty() {
+                lines.push(String::new());
+                // Pad to the longest path, like git's diffstat, but don't let one long path push the counts off screen
+                let width = preview.files.iter().map(|(file, _, _)| file.width()).max().unwrap_or(0).min(48);
+                for (file, insertions, deletions) in preview.files.iter().take(20) {
+                    lines.push(format!(" {} | +{} -{}", pad_width(file, width, '<'), insertions, deletions));
+                }
+                if preview.files.len() > 20 {
+                    lines.push(format!(" ... and {} more files", preview.files.len() - 20));
+                }
+                lines.push(format!(" {} files changed, {} insertions(+), {} deletions(-)", preview.files.len(), preview.insertions, preview.deletions));
+            }
+            lines.push(String::new());
+            lines.push("Esc: Close".to_string());
+            (repo.name.as_str(), lines)
         }
-    }
-    
-    // If no remote branch found, just return 0/0
-    Ok((0, 0, current_branch))
-}
-
-fn get_recent_commits(path: &PathBuf, count: usize) -> Vec<CommitInfo> {
-    let mut commits = Vec::new();
-    
-    if let Ok(repo) = Repository::open(path) {
-        // Get current branch name
-        let current_branch = if let Ok(head) = repo.head() {
-            head.shorthand().unwrap_or("unknown").to_string()
-        } else {
-            "unknown".to_string()
-        };
-        
-        if let Ok(mut revwalk) = repo.revwalk() {
-            revwalk.push_head().ok();
-            
-            for (i, oid) in revwalk.enumerate() {
-                if i >= count { break; }
-                
-                if let Ok(oid) = oid {
-                    if let Ok(commit) = repo.find_commit(oid) {
-                        commits.push(CommitInfo {
-                            hash: format!("{:.8}", oid),
-                            author: commit.author().name().unwrap_or("Unknown").to_string(),
-                            message: commit.message().unwrap_or("No message").lines().next().unwrap_or("").to_string(),
-                            branch: current_branch.clone(),
-                            timestamp: DateTime::from_timestamp(commit.time().seconds(), 0)
-                                .unwrap_or_else(|| Utc::now()),
-                        });
+        Popup::CleanupBranches { repo: _, default_branch, branches } => {
+            let mut lines = vec![format!("Delete {} local branches merged into {}:", branches.len(), default_branch)];
+            for branch in branches {
+                let gone = if branch.upstream_gone { ", upstream gone" } else { "" };
+                lines.push(format!("  {} ({:.8}{})", branch.name, branch.tip, gone));
+            }
+            lines.push(String::new());
+            lines.push("y/Enter: Delete  n/Esc: Cancel".to_string());
+            ("Clean Up Branches", lines)
+        }
+        Popup::RetargetUpstream { repo, old, new } => {
+            let Some(repo) = repos.get(*repo) else {
+                return;
+            };
+            let lines = vec![
+                format!("{} renamed its default branch {} to {}.", repo.remote, old, new),
+                format!("Make the local branches tracking {}/{} track {}/{} instead,", repo.remote, old, repo.remote, new),
+                format!("and point {}/HEAD at it? Local branch names are left alone.", repo.remote),
+                String::new(),
+                "y/Enter: Retarget  n/Esc: Cancel".to_string(),
+            ];
+            ("Default Branch Renamed", lines)
+        }
+        Popup::CheckoutBranch { repo, name } => {
+            let Some(repo) = repos.get(*repo) else {
+                return;
+            };
+            let lines = vec![
+                format!("Switch {} from {} to {}?", repo.name, repo.current_branch, name),
+                String::new(),
+                "y/Enter: Check out  n/Esc: Stay".to_string(),
+            ];
+            ("Check Out Branch", lines)
+        }
+        Popup::PushTag { repo, name } => {
+            let Some(repo) = repos.get(*repo) else {
+                return;
+            };
+            let lines = vec![
+                format!("Push tag {} to {}?", name, repo.remote),
+                String::new(),
+                "y/Enter: Push  n/Esc: Keep it local".to_string(),
+            ];
+            ("Push Tag", lines)
+        }
+        Popup::CloneMissing(targets) => {
+            let mut lines = vec![format!("{} configured repositories are missing locally:", targets.len())];
+            for repo in targets.iter().filter_map(|&i| repos.get(i)) {
+                lines.push(format!("  {} -> {}", repo.name, repo.path.display()));
+                lines.push(format!("      {}", repo.url.as_deref().unwrap_or_default()));
+            }
+            lines.push(String::new());
+            lines.push("y/Enter: Clone  n/Esc: Skip".to_string());
+            ("Clone Missing Repositories", lines)
+        }
+        Popup::Prompt(prompt) => {
+            let mut lines = vec![format!("> {}_", prompt.input)];
+            if let Some(error) = &prompt.error {
+                lines.push(String::new());
+                lines.push(format!("Error: {}", error));
+            }
+            lines.push(String::new());
+            lines.push("Enter: Confirm  Esc: Cancel".to_string());
+            (prompt.title.as_str(), lines)
+        }
+        Popup::Commit(detail) => {
+            let Some(repo) = repos.get(detail.repo) else {
+                return;
+            };
+            let mut lines = vec![
+                format!("Commit: {}", detail.hash),
+                format!("Author: {}", detail.author),
+                format!("Date:   {}", detail.timestamp.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M")),
+                String::new(),
+            ];
+            lines.extend(detail.message.lines().map(str::to_string));
+            if !detail.trailers.is_empty() {
+                lines.push(String::new());
+                lines.push("Trailers:".to_string());
+                lines.extend(detail.trailers.iter().map(|(key, value)| format!("    {}: {}", key, value)));
+            }
+            if repo.notes {
+                lines.push(String::new());
+                match &detail.notes {
+                    Some(notes) => {
+                        lines.push("Notes:".to_string());
+                        lines.extend(notes.lines().map(|line| format!("    {}", line)));
                     }
+                    None => lines.push("Notes: none".to_string()),
+                }
+            }
+            if !detail.files.is_empty() {
+                lines.push(String::new());
+                // Like the pull preview's diffstat, with the file blame opens for marked
+                let width = detail.files.iter().map(|(file, _, _)| file.width()).max().unwrap_or(0).min(48);
+                let start = detail.selected_file.saturating_sub(COMMIT_FILES - 1);
+                for (index, (file, insertions, deletions)) in detail.files.iter().enumerate().skip(start).take(COMMIT_FILES) {
+                    let marker = if index == detail.selected_file { '>' } else { ' ' };
+                    lines.push(format!("{} {} | +{} -{}", marker, pad_width(file, width, '<'), insertions, deletions));
+                }
+                if detail.files.len() > start + COMMIT_FILES {
+                    lines.push(format!("  ... and {} more files", detail.files.len() - start - COMMIT_FILES));
                 }
             }
+            lines.push(String::new());
+            lines.push(match &detail.search {
+                Some(search) => format!(
+                    "Match {}/{}  ↑/↓: Other Matches  ←/→: Select File  a: Blame File  t: Browse Tree  b: Branch Here  /: Search  Esc: Back to Results",
+                    search.selected + 1,
+                    search.hits.len()
+                ),
+                None => format!(
+                    "{}/{}  ↑/↓: Older/Newer  ←/→: Select File  a: Blame File  t: Browse Tree  b: Branch Here  /: Search  Esc: Close",
+                    detail.index + 1,
+                    repo.recent_commits.len()
+                ),
+            });
+            (repo.name.as_str(), lines)
         }
-    }
-    
-    commits
-}
-
-async fn monitor_repositories(
-    repos: Arc<Mutex<Vec<RepoStatus>>>,
-    console_messages: Arc<Mutex<Vec<ConsoleMessage>>>,
-    refresh_interval: Duration,
-) {
-    let mut interval = time::interval(refresh_interval);
-    
-    loop {
-        interval.tick().await;
-        
-        let mut repos_guard = repos.lock().unwrap();
-        for repo in repos_guard.iter_mut() {
-            let remote = "origin"; // Could be configurable
-            
-            // Always update the last_update time to show the monitor is running
-            repo.last_update = Instant::now();
-            
-            match get_repo_status(&repo.path, remote) {
-                Ok((ahead, behind, branch)) => {
-                    let prev_ahead = repo.ahead;
-                    let prev_behind = repo.behind;
-                    
-                    repo.ahead = ahead;
-                    repo.behind = behind;
-                    repo.current_branch = branch;
-                    
-                    // Add console messages for changes (no flashing)
-                    if behind > prev_behind && ahead > prev_ahead {
-                        let mut console_guard = console_messages.lock().unwrap();
-                        console_guard.push(ConsoleMessage {
-                            timestamp: Utc::now(),
-                            repo: repo.name.clone(),
-                            author: "Git Monitor".to_string(),
-                            message: format!("Status changed: {} ahead (+{}), {} behind (+{})", 
-                                ahead, ahead - prev_ahead, behind, behind - prev_behind),
-                        });
-                    } else if behind > prev_behind {
-                        let mut console_guard = console_messages.lock().unwrap();
-                        console_guard.push(ConsoleMessage {
-                            timestamp: Utc::now(),
-                            repo: repo.name.clone(),
-                            author: "Git Monitor".to_string(),
-                            message: format!("New commits available: {} behind (+{})", 
-                                behind, behind - prev_behind),
-                        });
-                    } else if ahead > prev_ahead {
-                        let mut console_guard = console_messages.lock().unwrap();
-                        console_guard.push(ConsoleMessage {
-                            timestamp: Utc::now(),
-                            repo: repo.name.clone(),
-                            author: "Git Monitor".to_string(),
-                            message: format!("Local commits added: {} ahead (+{})", 
-                                ahead, ahead - prev_ahead),
-                        });
+        Popup::Changelog(changelog) => {
+            let Some(repo) = repos.get(changelog.repo) else {
+                return;
+            };
+            let mut lines = changelog.lines(&repo.name, Some(changelog.selected));
+            // Below the header, a blank line and the commit count
+            if changelog.outgoing && !repo.push_warnings.is_empty() {
+                let warnings = repo.push_warnings.iter().map(|warning| format!("! {}", warning));
+                lines.splice(3..3, [String::new(), "Before pushing:".to_string()].into_iter().chain(warnings));
+            }
+            lines.push(String::new());
+            lines.push(format!(
+                "↑/↓: Select  {}  b: Branch Here  g: Group by {}  x: Export Markdown  /: Search  Esc: Close",
+                if changelog.outgoing { "r: Revert" } else { "p: Cherry-pick" },
+                if changelog.by_author { "type" } else { "author" },
+            ));
+            (if changelog.outgoing { "Outgoing Changes" } else { "Incoming Changes" }, lines)
+        }
+        Popup::CommitSearch(search) => {
+            let summary = if search.searching {
+                format!("Searching every repository for \"{}\"...", search.query)
+            } else {
+                format!("{} commits matching \"{}\"", search.hits.len(), search.query)
+            };
+            let mut lines = vec![summary, String::new()];
+            for (index, hit) in search.hits.iter().enumerate() {
+                // Searching everywhere groups the results under the repository names
+                if search.repo.is_none() && (index == 0 || search.hits[index - 1].repo != hit.repo) {
+                    if index > 0 {
+                        lines.push(String::new());
                     }
-                    
-                    // Add console message when caught up
-                    if (prev_behind > 0 || prev_ahead > 0) && behind == 0 && ahead == 0 {
-                        let mut console_guard = console_messages.lock().unwrap();
-                        console_guard.push(ConsoleMessage {
-                            timestamp: Utc::now(),
-                            repo: repo.name.clone(),
-                            author: "GitOp".to_string(),
-                            message: "Repository is now up to date! 🎉".to_string(),
-                        });
+                    lines.push(repos.get(hit.repo).map_or("?", |repo| repo.name.as_str()).to_string());
+                }
+                let marker = if index == search.selected { ">" } else { " " };
+                let location = match (hit.local, hit.upstream) {
+                    (true, true) => "",
+                    (true, false) => " [local only]",
+                    (false, true) => " [upstream only]",
+                    (false, false) => "",
+                };
+                lines.push(format!(
+                    "{} {:.8} {} {}: {}{}",
+                    marker,
+                    hit.hash,
+                    hit.timestamp.with_timezone(&chrono::Local).format("%Y-%m-%d"),
+                    hit.author,
+                    hit.summary,
+                    location
+                ));
+            }
+            lines.push(String::new());
+            lines.push("↑/↓: Select  Enter: Show Commit  /: Search Again  Esc: Close".to_string());
+            title_buffer = match search.repo.and_then(|index| repos.get(index)) {
+                Some(repo) => format!("{} - Commit Search", repo.name),
+                None => "Commit Search - All Repositories".to_string(),
+            };
+            (title_buffer.as_str(), lines)
+        }
+        Popup::ConfirmRevert { changelog, commit } => {
+            let Some(repo) = repos.get(changelog.repo) else {
+                return;
+            };
+            let lines = vec![
+                format!("Create a commit on {} that reverts:", repo.current_branch),
+                format!("  {} {} ({})", commit.hash, commit.message, commit.author),
+                String::new(),
+                "y/Enter: Revert  n/Esc: Cancel".to_string(),
+            ];
+            ("Revert Commit", lines)
+        }
+        Popup::Detail { repo: index, release, identity } => {
+            let Some(repo) = repos.get(*index) else {
+                return;
+            };
+            let mut lines = match &repo.remote_refs {
+                Some(refs) => {
+                    let mut lines = vec![
+                        format!("URL:      {}", repo.url.as_deref().unwrap_or_default()),
+                        "Host:     remote only, no clone".to_string(),
+                        format!("Default:  {}", refs.head.as_deref().unwrap_or("unknown")),
+                        format!("Branches: {}", refs.branches.len()),
+                        format!("Tags:     {}", refs.tags.len()),
+                        format!("Polling:  every {}s", repo.poll_interval.as_secs()),
+                    ];
+                    if let Some(error) = &refs.error {
+                        lines.push(format!("          last check failed: {}", error));
                     }
-                    
-                    // Add console message for new commits
-                    if ahead > prev_ahead {
-                        let recent = get_recent_commits(&repo.path, (ahead - prev_ahead).min(5));
-                        let mut console_guard = console_messages.lock().unwrap();
-                        for commit in recent {
-                            console_guard.push(ConsoleMessage {
-                                timestamp: Utc::now(),
-                                repo: repo.name.clone(),
-                                author: commit.author,
-                                message: commit.message,
-                            });
-                        }
-                        // Keep only last 50 messages
-                        let len = console_guard.len();
-                        if len > 50 {
-                            console_guard.drain(0..len - 50);
+                    lines
+                }
+                None => vec![
+                    format!("Path:     {}", repo.path.display()),
+                    format!("Host:     {}", repo.host.as_deref().unwrap_or("local")),
+                    format!("Remote:   {}", repo.remote),
+                    format!(
+                        "Refspecs: {}{}",
+                        if repo.refspecs.is_empty() { "(remote default)".to_string() } else { repo.refspecs.join(" ") },
+                        if repo.prune { ", pruned" } else { "" },
+                    ),
+                    format!(
+                        "Branch:   {}{}",
+                        repo.current_branch,
+                        match &repo.default_branch {
+                            Some(default) if *default != repo.current_branch => format!(" (default branch is {})", default),
+                            Some(_) => " (default branch)".to_string(),
+                            None => String::new(),
                         }
+                    ),
+                    format!("Ahead:    {}", repo.ahead),
+                    format!("Behind:   {}{}", repo.behind, repo.behind_bound()),
+                    format!("Changes:  {} files, +{} -{} lines", repo.dirty, repo.diffstat.0, repo.diffstat.1),
+                    format!("State:    {}", repo.operation.as_deref().map_or("clean".to_string(), |operation| format!("{}, ahead/behind are misleading until it's finished", operation))),
+                    format!("Polling:  every {}s{}", repo.poll_interval.as_secs(), if repo.light_check { ", fetching only when the branch tip moved" } else { "" }),
+                ],
+            };
+            if let Some(depth) = repo.fetch_depth {
+                let bound = if repo.behind_bound().is_empty() { "" } else { ", upstream not reached, so behind is a lower bound" };
+                lines.push(format!("Depth:    {} commits{}", depth, bound));
+            }
+            if let Some((old, new)) = &repo.renamed_default {
+                lines.push(format!("Renamed:  {} renamed {} to {}, R retargets the branches tracking it", repo.remote, old, new));
+            } else if let Some(gone) = &repo.upstream_gone {
+                lines.push(format!("Upstream: {} no longer exists on the remote", gone));
+                let default = repo.default_branch.as_deref().unwrap_or("main");
+                lines.push(format!("          if it was merged: git switch {} && git branch -d {}", default, repo.current_branch));
+                lines.push("          or switch and let B clean it up".to_string());
+            }
+            if let Some(identity) = identity {
+                let describe = |value: &Option<(String, &str)>| match value {
+                    Some((value, level)) => format!("{} ({} config)", value, level),
+                    None => "not set".to_string(),
+                };
+                lines.push(format!("Name:     {}", describe(&identity.name)));
+                lines.push(format!("Email:    {}", describe(&identity.email)));
+                if let (Some(expected), Some(_)) = (&repo.expected_email, &repo.wrong_identity) {
+                    lines.push(format!("          expected {}; fix it for this repository only with", expected));
+                    let example = if expected.starts_with('@') { format!("you{}", expected) } else { expected.clone() };
+                    lines.push(format!("          git -C {} config user.email {}", repo.path.display(), example));
+                }
+            }
+            for (position, warning) in repo.push_warnings.iter().enumerate() {
+                lines.push(format!("{}{}", if position == 0 { "Pre-push: " } else { "          " }, warning));
+            }
+            for (position, (file, size)) in repo.incoming_large.iter().enumerate() {
+                lines.push(format!("{}{} {}", if position == 0 { "Incoming: " } else { "          " }, format_size(*size), file));
+            }
+            if let Some(last) = repo.fetch_times.last() {
+                let average = repo.fetch_times.iter().sum::<Duration>() / repo.fetch_times.len() as u32;
+                lines.push(format!("Fetch:    {} (average {} over {} fetches)", format_duration(*last), format_duration(average), repo.fetch_times.len()));
+            }
+            if let Some(backup) = &repo.backup {
+                let last = backup
+                    .last_success
+                    .map(|time| format!("last pushed {}", time.with_timezone(&chrono::Local).format("%m/%d %H:%M")))
+                    .unwrap_or_else(|| "never pushed".to_string());
+                lines.push(format!("Backup:   {} ({}, {} branches behind)", backup.remote, last, backup.lag));
+                if let Some(error) = &backup.error {
+                    lines.push(format!("          last push failed: {}", error));
+                }
+            }
+            for pull_request in &repo.pull_requests {
+                let state = match (&pull_request.last_commit, pull_request.updated) {
+                    (Some(commit), Some(updated)) => format!("{} (updated {})", commit, updated.with_timezone(&chrono::Local).format("%m/%d %H:%M")),
+                    _ => "not fetched yet".to_string(),
+                };
+                lines.push(format!("PR #{}:{} {}", pull_request.number, " ".repeat(5usize.saturating_sub(pull_request.number.to_string().len())), state));
+            }
+            if let Some(maintenance) = &repo.maintenance {
+                let last = match (maintenance.running, maintenance.last_finished) {
+                    (true, _) => "running now".to_string(),
+                    (false, Some(time)) => format!("last ran {}", time.with_timezone(&chrono::Local).format("%m/%d %H:%M")),
+                    (false, None) => "not run yet".to_string(),
+                };
+                lines.push(format!("Maintain: every {}s ({})", maintenance.interval.as_secs(), last));
+                if let Some(error) = &maintenance.error {
+                    lines.push(format!("          last run failed: {}", error));
+                }
+            }
+            match &repo.stats {
+                Some(stats) => {
+                    lines.push(format!(
+                        "Size:     {}{} ({} loose objects in {}, {} packed in {} packs of {})",
+                        format_size(stats.git_size),
+                        if repo.size_warning.is_some_and(|limit| stats.git_size > limit) { ", over the size warning" } else { "" },
+                        stats.loose_objects,
+                        format_size(stats.loose_size),
+                        stats.packed_objects,
+                        stats.packs,
+                        format_size(stats.pack_size),
+                    ));
+                    if !stats.largest_files.is_empty() {
+                        let largest: Vec<String> = stats.largest_files.iter().map(|(file, size)| format!("{} ({})", file, format_size(*size))).collect();
+                        lines.push(format!("Largest:  {}", largest.join(", ")));
                     }
                 }
-                Err(err) => {
-                    // If git operation fails, add a detailed console message
-                    let mut console_guard = console_messages.lock().unwrap();
-                    console_guard.push(ConsoleMessage {
-                        timestamp: Utc::now(),
-                        repo: repo.name.clone(),
-                        author: "System".to_string(),
-                        message: format!("Git error: {} (path: {})", err, repo.path.display()),
-                    });
+                None if repo.host.is_none() && repo.remote_refs.is_none() => lines.push("Size:     computing...".to_string()),
+                None => {}
+            }
+            if let Some(release) = release {
+                lines.push(format!("Release:  {}", release.describe()));
+            }
+            if let Some(policy) = &repo.policy {
+                let mut rules = Vec::new();
+                if policy.require_signed {
+                    rules.push("signed commits");
+                }
+                if policy.detect_direct_pushes {
+                    rules.push("merges only");
                 }
+                lines.push(format!("Policy:   protects {} ({}), {} violations", policy.protected_branches.join(", "), rules.join(", "), policy.violations));
+                if let Some(alert) = policy.alerts.last() {
+                    lines.push(format!("          last: {}", alert.describe()));
+                }
+            }
+            if repo.muted {
+                lines.push("Notifications muted".to_string());
             }
+            if repo.paused {
+                lines.push("Polling paused, resume with `gitop ctl resume`".to_string());
+            }
+            if repo.archived {
+                lines.push("Archived, not checked until unarchived with z".to_string());
+            }
+            if let Some(description) = &repo.description {
+                lines.push(String::new());
+                lines.push(description.clone());
+            }
+            lines.push(String::new());
+            lines.push("Esc: Close".to_string());
+            (repo.name.as_str(), lines)
+        }
+    };
+    
+    let height = (lines.len() as u16 + 2).min(f.size().height);
+    // Keep the changelog's selected entry in view
+    let scroll = match popup {
+        Popup::Changelog(_) | Popup::Tree(_) | Popup::CommitSearch(_) => {
+            let selected_line = lines.iter().position(|line| line.starts_with("> ")).unwrap_or(0) as u16;
+            selected_line.saturating_sub(height.saturating_sub(2) / 2)
         }
-        drop(repos_guard); // Release the lock before sleeping
+        Popup::Graph { scroll, .. } | Popup::Blame { scroll, .. } | Popup::FilePreview { scroll, .. } | Popup::WorkTree { scroll, .. } | Popup::Conflicts { scroll, .. } => *scroll as u16,
+        _ => 0,
+    };
+    // Wrapped lines would break the columns, so the graph and branch table get more width and are cut off instead
+    let wide = matches!(popup, Popup::Graph { .. } | Popup::Branches { .. } | Popup::Blame { .. } | Popup::FilePreview { .. });
+    let area = centered_rect(if wide { 90 } else { 60 }, height, f.size());
+    let text = match popup {
+        // Only in the results, which are indented; the header, repository names and key hints would light up too
+        Popup::CommitSearch(search) => highlight_matches(&lines, &search.query, |line| line.starts_with("  ") || line.starts_with("> ")),
+        _ => Text::from(lines.join("\n")),
+    };
+    let mut paragraph = Paragraph::new(text)
+        .block(Block::default().title(title).borders(Borders::ALL))
+        .scroll((scroll, 0));
+    if !wide {
+        paragraph = paragraph.wrap(Wrap { trim: false });
     }
+    
+    f.render_widget(Clear, area);
+    f.render_widget(paragraph, area);
 }
 
-fn ui(f: &mut Frame, app: &mut App) {
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .margin(1)
-        .constraints([Constraint::Min(0), Constraint::Length(10), Constraint::Length(3)].as_ref())
-        .split(f.size());
-
-    // Repository table
-    let repos = app.repos.lock().unwrap();
-    
-    let mut rows = Vec::new();
-    for repo in repos.iter() {
-        // No more flashing - keep it simple and clean
-        let style = Style::default();
-        
-        // Create cells with color coding for ahead/behind
-        let ahead_color = app.colors.ahead_color.as_ref()
-            .map(|c| parse_color(c))
-            .unwrap_or(Color::Reset);
-        
-        let behind_color = app.colors.behind_color.as_ref()
-            .map(|c| parse_color(c))
-            .unwrap_or(Color::Reset);
-            
-        let ahead_cell = if repo.ahead > 0 {
-            Cell::from(format!("↑{}", repo.ahead)).style(Style::default().fg(ahead_color))
-        } else {
-            Cell::from("0")
-        };
-        
-        let behind_cell = if repo.behind > 0 {
-            Cell::from(format!("↓{}", repo.behind)).style(Style::default().fg(behind_color))
-        } else {
-            Cell::from("0")
-        };
-        
-        rows.push(Row::new(vec![
-            Cell::from(repo.name.clone()),
-            ahead_cell,
-            behind_cell,
-            Cell::from(repo.current_branch.clone()),
-        ]).style(style));
-        
-        // Add expanded commits if selected
-        if repo.expanded {
-            for commit in &repo.recent_commits {
-                rows.push(Row::new(vec![
-                    Cell::from(format!("  └─ {} - {}", commit.hash, commit.message)),
-                    Cell::from(commit.author.clone()),
-                    Cell::from(commit.timestamp.format("%m/%d %H:%M").to_string()),
-                    Cell::from(format!("({})", commit.branch)),
-                ]).style(Style::default().fg(Color::Gray)));
+// Occurrences of `query` in the lines `searched` accepts, ignoring case, picked out in reverse video
+fn highlight_matches(lines: &[String], query: &str, searched: impl Fn(&str) -> bool) -> Text<'static> {
+    let needle = query.to_lowercase();
+    let highlighted = Style::default().add_modifier(Modifier::REVERSED);
+    let lines = lines.iter().map(|line| {
+        // Lowercasing can change byte lengths outside ASCII; those lines are left as they are
+        let haystack = line.to_lowercase();
+        if needle.is_empty() || !searched(line) || haystack.len() != line.len() {
+            return Line::raw(line.clone());
+        }
+        let mut spans = Vec::new();
+        let mut start = 0;
+        for (position, _) in haystack.match_indices(&needle) {
+            if position < start {
+                continue;
             }
+            spans.push(Span::raw(line[start..position].to_string()));
+            spans.push(Span::styled(line[position..position + needle.len()].to_string(), highlighted));
+            start = position + needle.len();
         }
+        spans.push(Span::raw(line[start..].to_string()));
+        Line::from(spans)
+    });
+    Text::from(lines.collect::<Vec<_>>())
+}
+
+fn centered_rect(percent_x: u16, height: u16, area: Rect) -> Rect {
+    let width = area.width * percent_x / 100;
+    Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + area.height.saturating_sub(height) / 2,
+        width,
+        height: height.min(area.height),
     }
-    
-    let widths = [
-        Constraint::Percentage(35),
-        Constraint::Percentage(15),
-        Constraint::Percentage(15),
-        Constraint::Percentage(35),
-    ];
-    
-    let table = Table::new(rows, widths)
-        .block(Block::default().title("GitOp - Repositories").borders(Borders::ALL))
-        .header(Row::new(vec!["Repository", "Ahead", "Behind", "Branch"])
-            .style(Style::default().add_modifier(Modifier::BOLD)))
-        .highlight_style(Style::default().add_modifier(Modifier::REVERSED).fg(Color::White));
-    
-    f.render_stateful_widget(table, chunks[0], &mut app.table_state);
-    
-    // Console
-    let console_messages = app.console_messages.lock().unwrap();
-    let console_text = console_messages
-        .iter()
-        .rev()
-        .take(8)
-        .map(|msg| format!("[{}] {}: {} - {}", 
-            msg.timestamp.format("%H:%M:%S"),
-            msg.repo,
-            msg.author,
-            msg.message
-        ))
-        .collect::<Vec<_>>()
-        .join("\n");
-    
-    let console = Paragraph::new(console_text)
-        .block(Block::default().title("Console").borders(Borders::ALL))
-        .wrap(Wrap { trim: true });
-    
-    f.render_widget(console, chunks[1]);
-    
-    // Help footer
-    let help_text = "↑/↓: Navigate  Enter: Expand/Collapse  q: Quit";
-    let help = Paragraph::new(help_text)
-        .block(Block::default().title("Controls").borders(Borders::ALL))
-        .style(Style::default().fg(Color::Gray));
-    
-    f.render_widget(help, chunks[2]);
 }
 
-async fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App, refresh_interval: Duration) -> Result<()> {
-    // Start monitoring task (no flash colors needed)
+async fn run_app<B: Backend + io::Write>(terminal: &mut Terminal<B>, mut app: App) -> Result<()> {
+    // Start monitoring task
     let repos_clone = app.repos.clone();
     let console_clone = app.console_messages.clone();
-    tokio::spawn(monitor_repositories(repos_clone, console_clone, refresh_interval));
+    tokio::spawn(monitor_repositories(repos_clone, console_clone, app.refresh.subscribe(), app.wake_monitor.clone()));
     
     // UI loop
     let mut last_tick = Instant::now();
@@ -686,10 +8191,28 @@ async fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App, refresh_i
             .unwrap_or_else(|| Duration::from_secs(0));
             
         if crossterm::event::poll(timeout)? {
-            if let Event::Key(key) = event::read()? {
-                app.handle_key(key.code);
+            match event::read()? {
+                Event::Key(key) => app.handle_key(key.code),
+                Event::Mouse(mouse) => app.handle_mouse(mouse),
+                _ => {}
             }
         }
+        app.update_focus();
+        app.collect_search();
+        app.notifier.poll();
+        
+        if let Some(action) = app.suspend.take() {
+            let command = app.suspend_command(&action);
+            
+            disable_raw_mode()?;
+            execute!(terminal.backend_mut(), LeaveAlternateScreen, DisableMouseCapture)?;
+            let status = command.and_then(|mut command| Ok(command.status()?));
+            enable_raw_mode()?;
+            execute!(terminal.backend_mut(), EnterAlternateScreen, EnableMouseCapture)?;
+            terminal.clear()?;
+            
+            app.resume(action, status);
+        }
         
         if last_tick.elapsed() >= tick_rate {
             last_tick = Instant::now();
@@ -709,7 +8232,7 @@ async fn main() -> Result<()> {
     
     // Handle subcommands
     match cli.command {
-        Some(Commands::Init { force }) => {
+        Some(Commands::Init { force, scan, max_depth, interactive }) => {
             let config_path = get_config_path(cli.config.clone());
             
             if config_path.exists() && !force {
@@ -718,12 +8241,55 @@ async fn main() -> Result<()> {
                 std::process::exit(1);
             }
             
-            create_default_config(&config_path)?;
+            if interactive {
+                let config = run_init_wizard(max_depth)?;
+                write_config(&config_path, &config)?;
+                println!("\nCreated config with {} repositories at: {}", config.repositories.len(), config_path.display());
+            } else if let Some(root) = scan {
+                let root = expand_path(&root.to_string_lossy());
+                let repositories = scan_for_repositories(&root, max_depth);
+                if repositories.is_empty() {
+                    eprintln!("No git repositories found under: {}", root.display());
+                    std::process::exit(1);
+                }
+                
+                for repo in &repositories {
+                    println!("  + {} ({})", repo.name, repo.path);
+                }
+                let config = Config {
+                    repositories,
+                    ..Config::default()
+                };
+                write_config(&config_path, &config)?;
+                println!("Created config with {} repositories at: {}", config.repositories.len(), config_path.display());
+            } else {
+                create_default_config(&config_path)?;
+            }
             println!("\nTo start monitoring, run: gitop");
             println!("To edit config: {}", config_path.display());
             return Ok(());
         }
-        Some(Commands::Config) => {
+        Some(Commands::Config { action: Some(ConfigAction::Validate) }) => {
+            let config_path = get_config_path(cli.config.clone());
+            let issues = validate::validate_config_file(&config_path)?;
+            
+            for issue in &issues {
+                println!("{}", issue);
+            }
+            let errors = issues.iter().filter(|issue| issue.is_error()).count();
+            let warnings = issues.len() - errors;
+            if errors > 0 {
+                eprintln!("{}: {} errors, {} warnings", config_path.display(), errors, warnings);
+                std::process::exit(1);
+            }
+            println!("{}: ok ({} warnings)", config_path.display(), warnings);
+            return Ok(());
+        }
+        Some(Commands::Config { action: Some(ConfigAction::Schema) }) => {
+            println!("{}", serde_json::to_string_pretty(&schema::config_schema())?);
+            return Ok(());
+        }
+        Some(Commands::Config { action: None }) => {
             let config_path = get_config_path(cli.config.clone());
             println!("Config file location: {}", config_path.display());
             println!("Exists: {}", config_path.exists());
@@ -733,20 +8299,259 @@ async fn main() -> Result<()> {
                 println!("Repositories configured: {}", config.repositories.len());
                 for repo in &config.repositories {
                     println!("  - {} ({})", repo.name, repo.path);
+                    if let Some(description) = &repo.description {
+                        println!("      {}", description);
+                    }
+                    if let Some(labels) = repo.labels.as_ref().filter(|labels| !labels.is_empty()) {
+                        println!("      labels: {}", labels.join(", "));
+                    }
                 }
             } else {
                 println!("No config file found. Run 'gitop init' to create one.");
             }
             return Ok(());
         }
+        Some(Commands::Paths) => {
+            let config_path = get_config_path(cli.config.clone());
+            // A broken or missing config still has paths worth knowing
+            let persist = load_config(cli.config.clone()).ok().and_then(|config| config.persist_commit_cache).unwrap_or(false);
+            print_paths(&config_path, persist);
+            return Ok(());
+        }
+        Some(Commands::Import { from, file, dry_run }) => {
+            let config_path = get_config_path(cli.config.clone());
+            // Without a config file load_config returns the "Current Directory" placeholder, which is not a real entry
+            let existing = if config_path.exists() { load_config(cli.config.clone())?.repositories } else { Vec::new() };
+            
+            // Skip repositories that are already configured
+            let known: Vec<PathBuf> = existing
+                .iter()
+                .map(|repo| expand_path(&repo.path).canonicalize().unwrap_or_else(|_| expand_path(&repo.path)))
+                .collect();
+            let repositories: Vec<RepoConfig> = import_repositories(from, file)?
+                .into_iter()
+                .filter(|repo| {
+                    let path = expand_path(&repo.path);
+                    !known.contains(&path.canonicalize().unwrap_or(path))
+                })
+                .collect();
+            
+            for repo in &repositories {
+                println!("  + {} ({})", repo.name, repo.path);
+            }
+            if repositories.is_empty() {
+                println!("Nothing new to import");
+            } else if dry_run {
+                println!("Would import {} repositories into {}", repositories.len(), config_path.display());
+            } else {
+                if !config_path.exists() {
+                    write_config(&config_path, &Config { repositories: repositories.clone(), ..Config::default() })?;
+                } else {
+                    append_repos_to_config(&config_path, &repositories)?;
+                }
+                println!("Imported {} repositories into {}", repositories.len(), config_path.display());
+            }
+            return Ok(());
+        }
+        Some(Commands::SyncGithub { org, user, root, max_depth, dry_run }) => {
+            let config_path = get_config_path(cli.config.clone());
+            let config = load_config(cli.config.clone())?;
+            let owner = match (org, user) {
+                (Some(org), _) => forge::GithubOwner::Org(org),
+                (None, Some(user)) => forge::GithubOwner::User(user),
+                (None, None) => unreachable!("clap requires --org or --user"),
+            };
+            
+            let root = expand_path(&root.to_string_lossy());
+            let (added, missing, rate_limit) = sync_github(&config, &owner, &root, max_depth)?;
+            
+            for repo in &added {
+                println!("  + {} ({})", repo.name, repo.path);
+            }
+            if !missing.is_empty() {
+                println!("\nNot cloned under {}:", root.display());
+                for repo in &missing {
+                    let archived = if repo.archived { " [archived]" } else { "" };
+                    println!("  ! {}{}  {}", repo.full_name, archived, repo.clone_url);
+                }
+            }
+            
+            if added.is_empty() {
+                println!("\nConfig is already in sync");
+            } else if dry_run {
+                println!("\nWould add {} repositories to {}", added.len(), config_path.display());
+            } else {
+                append_repos_to_config(&config_path, &added)?;
+                println!("\nAdded {} repositories to {}", added.len(), config_path.display());
+            }
+            if let Some(limit) = rate_limit {
+                println!("GitHub API: {}", limit.describe());
+            }
+            return Ok(());
+        }
+        Some(Commands::Health) => {
+            let config = load_config(cli.config.clone())?;
+            let logins = secrets::logins(config.credentials.as_ref());
+            
+            // Remotes are slow; check them all at once and report in config order
+            let reports: Vec<HealthReport> = std::thread::scope(|scope| {
+                let handles: Vec<_> = config
+                    .repositories
+                    .iter()
+                    .map(|repo| scope.spawn(|| check_health(repo, &logins)))
+                    .collect();
+                handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+            });
+            
+            let mut failed = 0;
+            for report in &reports {
+                let url = report.url.as_deref().unwrap_or("-");
+                match &report.result {
+                    Ok((latency, refs)) => println!(
+                        "  ok    {} ({} {})  {}ms, {} refs",
+                        report.name, report.remote, url, latency.as_millis(), refs
+                    ),
+                    Err(err) => {
+                        failed += 1;
+                        println!("  FAIL  {} ({} {})  {}", report.name, report.remote, url, err);
+                    }
+                }
+            }
+            
+            println!("\n{} of {} remotes reachable", reports.len() - failed, reports.len());
+            if failed > 0 {
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+        Some(Commands::InstallHooks { repo }) => {
+            for result in control::install_hooks(&repo)? {
+                println!("  {}", result);
+            }
+            return Ok(());
+        }
+        Some(Commands::Ctl { action }) => {
+            match action {
+                CtlAction::Refresh { repo } => println!("{}", control::send(&format!("refresh {}", control::target(&repo)))?),
+                CtlAction::Pause { repo } => {
+                    let target = repo.map(|repo| control::target(&repo)).unwrap_or_default();
+                    println!("{}", control::send(&format!("pause {}", target))?);
+                }
+                CtlAction::Resume { repo } => {
+                    let target = repo.map(|repo| control::target(&repo)).unwrap_or_default();
+                    println!("{}", control::send(&format!("resume {}", target))?);
+                }
+                CtlAction::Status { json } => control::print_status(&control::send("status")?, json)?,
+            }
+            return Ok(());
+        }
+        Some(Commands::Credentials { action: CredentialsAction::Set { name } }) => {
+            let secret = ask_secret(&format!("Secret for {}", name))?;
+            if secret.is_empty() {
+                anyhow::bail!("Nothing entered, keyring unchanged");
+            }
+            secrets::store(&name, &secret)?;
+            println!("Stored {}; reference it in the config as \"keyring:{}\"", name, name);
+            return Ok(());
+        }
+        Some(Commands::Credentials { action: CredentialsAction::Delete { name } }) => {
+            secrets::delete(&name)?;
+            println!("Removed {} from the keyring", name);
+            return Ok(());
+        }
+        Some(Commands::Daemon { ws }) => {
+            let config = load_config(cli.config.clone())?;
+            return daemon::run(config, ws, false, true).await;
+        }
+        Some(Commands::Agent { central, host, token }) => {
+            let config = load_config(cli.config.clone())?;
+            let host = host.unwrap_or_else(agent::local_hostname);
+            let token = token.or_else(|| std::env::var("GITOP_AGENT_TOKEN").ok());
+            return agent::run(config, central, host, token).await;
+        }
+        Some(Commands::Prompt { repo }) => {
+            return prompt::run(&repo);
+        }
+        Some(Commands::Status { format, template, all }) => {
+            let config = load_config(cli.config.clone())?;
+            return status::run(&config, format, template.as_deref(), all);
+        }
+        Some(Commands::Check { max_behind, max_ahead, max_dirty, max_age }) => {
+            let limits = status::Limits { behind: max_behind, ahead: max_ahead, dirty: max_dirty, age: max_age };
+            if limits.is_empty() {
+                eprintln!("Nothing to check; give at least one of --max-behind, --max-ahead, --max-dirty or --max-age");
+                std::process::exit(2);
+            }
+            let config = load_config(cli.config.clone())?;
+            if !status::run_check(&config, &limits) {
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+        Some(Commands::Snapshot { out }) => {
+            let config = load_config(cli.config.clone())?;
+            let taken = snapshot::take(&config);
+            snapshot::write(&taken, &out)?;
+            println!("Saved the state of {} repositories to {}", taken.repos.len(), out.display());
+            return Ok(());
+        }
+        Some(Commands::DiffSnapshot { file }) => {
+            let config = load_config(cli.config.clone())?;
+            return snapshot::run_diff(&config, &file);
+        }
+        Some(Commands::Serve { web }) => {
+            let config = load_config(cli.config.clone())?;
+            return daemon::run(config, Some(web), true, false).await;
+        }
+        Some(Commands::CloneMissing { dry_run }) => {
+            let config = load_config(cli.config.clone())?;
+            let logins = secrets::logins(config.credentials.as_ref());
+            let missing: Vec<&RepoConfig> = config
+                .repositories
+                .iter()
+                .filter(|repo| repo.url.is_some() && !repo.path.is_empty() && !expand_path(&repo.path).exists())
+                .collect();
+            
+            if missing.is_empty() {
+                println!("No missing repositories with a url to clone");
+                return Ok(());
+            }
+            
+            let mut failed = 0;
+            for repo in missing {
+                let url = repo.url.as_deref().unwrap_or_default();
+                let path = expand_path(&repo.path);
+                let remote = repo.remote.as_deref().unwrap_or("origin");
+                if dry_run {
+                    println!("  would clone {} -> {}", url, path.display());
+                    continue;
+                }
+                
+                println!("  cloning {} -> {}", url, path.display());
+                if let Err(err) = clone_repo(url, &path, remote, repo.fetch_depth, &logins) {
+                    eprintln!("    failed: {}", err);
+                    failed += 1;
+                }
+            }
+            if failed > 0 {
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
         None => {
             // Default behavior - run the monitor
         }
     }
     
     // Load configuration
+    let config_path = get_config_path(cli.config.clone());
+    let first_run = !config_path.exists();
     let config = load_config(cli.config)?;
-    let refresh_interval = Duration::from_secs(config.refresh_interval);
+    if cli.plain {
+        return daemon::run_plain(config).await;
+    }
+    let agent_listen = config.agent_listen.clone();
+    let agent_token = config.agent_token.clone();
     
     // Setup terminal
     enable_raw_mode()?;
@@ -756,7 +8561,7 @@ async fn main() -> Result<()> {
     let mut terminal = Terminal::new(backend)?;
     
     // Create app and run
-    let app = App::new(config);
+    let mut app = App::new(config, config_path);
     
     // Add startup validation message
     {
@@ -768,16 +8573,26 @@ async fn main() -> Result<()> {
             timestamp: Utc::now(),
             repo: "System".to_string(),
             author: "GitOp".to_string(),
+            kind: EventKind::Status,
             message: format!("Started monitoring {} repositories", repos.len()),
         });
         
         // Validate each repo path
-        for repo in repos.iter() {
-            if !repo.path.exists() {
+        for repo in repos.iter().filter(|repo| repo.remote_refs.is_none()) {
+            if !repo.path.exists() && repo.url.is_some() {
+                console_guard.push(ConsoleMessage {
+                    timestamp: Utc::now(),
+                    repo: repo.name.clone(),
+                    author: "System".to_string(),
+                    kind: EventKind::Error,
+                    message: format!("Not cloned yet: {} (press C to clone)", repo.path.display()),
+                });
+            } else if !repo.path.exists() {
                 console_guard.push(ConsoleMessage {
                     timestamp: Utc::now(),
                     repo: repo.name.clone(),
                     author: "System".to_string(),
+                    kind: EventKind::Error,
                     message: format!("Warning: Path does not exist: {}", repo.path.display()),
                 });
             } else if !repo.path.join(".git").exists() {
@@ -785,13 +8600,38 @@ async fn main() -> Result<()> {
                     timestamp: Utc::now(),
                     repo: repo.name.clone(),
                     author: "System".to_string(),
+                    kind: EventKind::Error,
                     message: format!("Warning: Not a git repository: {}", repo.path.display()),
                 });
             }
         }
     }
     
-    let res = run_app(&mut terminal, app, refresh_interval).await;
+    if let Some(addr) = agent_listen {
+        let result = match addr.parse() {
+            Ok(socket_addr) => agent::listen(socket_addr, agent_token, app.repos.clone(), app.console_messages.clone()).await,
+            Err(err) => Err(anyhow::anyhow!("invalid address \"{}\": {}", addr, err)),
+        };
+        let message = match result {
+            Ok(()) => format!("Accepting agent reports on {}", addr),
+            Err(err) => format!("Could not listen for agents: {}", err),
+        };
+        log_console(&app.console_messages, "System", "GitOp", EventKind::Status, message);
+    }
+    
+    let message = match control::listen(app.repos.clone(), app.console_messages.clone(), app.wake_monitor.clone()) {
+        Ok(path) => format!("Control socket at {}", path.display()),
+        Err(err) => format!("No control socket: {}", err),
+    };
+    log_console(&app.console_messages, "System", "GitOp", EventKind::Status, message);
+    
+    // Offer to clone anything that is configured with a url but missing on disk
+    app.confirm_clone_missing();
+    if first_run {
+        app.popup = Some(Popup::Welcome);
+    }
+    
+    let res = run_app(&mut terminal, app).await;
     
     // Restore terminal
     disable_raw_mode()?;
@@ -808,3 +8648,53 @@ async fn main() -> Result<()> {
     
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::shell_words;
+
+    fn words(line: &str) -> Vec<String> {
+        shell_words(line).expect("quotes are closed")
+    }
+
+    #[test]
+    fn shell_words_splits_on_whitespace() {
+        assert_eq!(words("  git   rebase -i\t{upstream} "), ["git", "rebase", "-i", "{upstream}"]);
+        assert_eq!(words(""), Vec::<String>::new());
+    }
+
+    #[test]
+    fn shell_words_single_quotes_are_literal() {
+        assert_eq!(words("echo 'a b' 'c\\d' 'e\"f'"), ["echo", "a b", "c\\d", "e\"f"]);
+    }
+
+    #[test]
+    fn shell_words_double_quotes_keep_spaces() {
+        assert_eq!(words("echo \"a b\" x\"y z\"w"), ["echo", "a b", "xy zw"]);
+    }
+
+    #[test]
+    fn shell_words_backslash_escapes() {
+        // Outside quotes any character is escaped
+        assert_eq!(words("a\\ b \\'c \\\\d"), ["a b", "'c", "\\d"]);
+        // Inside double quotes only ", \, $ and ` are, other backslashes are kept
+        assert_eq!(words("\"a\\\"b\\\\c\\$d\\`e\\nf\""), ["a\"b\\c$d`e\\nf"]);
+    }
+
+    #[test]
+    fn shell_words_empty_quotes_make_a_word() {
+        assert_eq!(words("cmd '' \"\" x"), ["cmd", "", "", "x"]);
+    }
+
+    #[test]
+    fn shell_words_unclosed_quote_is_none() {
+        assert_eq!(shell_words("git 'rebase"), None);
+        assert_eq!(shell_words("git \"rebase"), None);
+        assert_eq!(shell_words("git \"a\\\""), None);
+    }
+
+    #[test]
+    fn shell_words_placeholder_inside_quotes() {
+        assert_eq!(words("tig '{upstream}..HEAD' \"--onto {upstream}\""), ["tig", "{upstream}..HEAD", "--onto {upstream}"]);
+    }
+}
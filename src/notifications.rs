@@ -0,0 +1,304 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::{console_name, log_console, secrets, ConsoleMessage, RepoStatus};
+
+// Lines per repository in a summary before the rest is counted
+const SUMMARY_LINES: usize = 3;
+
+// What the keys of `routes` can be, see EventKind, and what they can be routed to
+pub const EVENT_KINDS: &[&str] = &["error", "commits", "local", "tag", "policy", "status"];
+pub const SINKS: &[&str] = &["desktop", "webhook", "email"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Sink {
+    Desktop,
+    Webhook,
+    Email,
+}
+
+impl Sink {
+    fn name(self) -> &'static str {
+        match self {
+            Sink::Desktop => "desktop",
+            Sink::Webhook => "webhook",
+            Sink::Email => "email",
+        }
+    }
+}
+
+// What a console message is about, set where it is logged. The names are the keys of `routes`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    Error,
+    Commits, // new upstream commits, large files a pull would bring, branches of remote-only repositories moving
+    Local,   // new local commits, and their summaries under their authors' names
+    Tag,
+    Policy,
+    Status,
+    // A reply to something asked of gitop, with a key, `gitop ctl` or a webhook; never notified
+    Action,
+}
+
+impl EventKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            EventKind::Error => "error",
+            EventKind::Commits => "commits",
+            EventKind::Local => "local",
+            EventKind::Tag => "tag",
+            EventKind::Policy => "policy",
+            EventKind::Status => "status",
+            EventKind::Action => "action",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NotificationConfig {
+    pub desktop: Option<bool>,        // notify-send on Linux, osascript on macOS
+    pub webhook: Option<String>,      // URL that gets {"text": ...} POSTed, or an env:/keyring: reference to it
+    pub email: Option<String>,        // address to mail through `sendmail`
+    pub sendmail: Option<String>,     // command reading the mail on stdin, default "sendmail -t"
+    pub digest_interval: Option<u64>, // minutes; events are collected and sent as one summary
+    pub digest_times: Option<Vec<String>>, // local "HH:MM" times to send the summary at
+    pub routes: Option<BTreeMap<String, Vec<String>>>, // event kind to the sinks it goes to; unlisted kinds go to all
+}
+
+// "HH:MM" as the digest times are written
+pub fn parse_time(time: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(time.trim(), "%H:%M").ok()
+}
+
+// Sends repository console messages to the configured sinks, one at a time or as periodic digests. Polled from
+// the UI or daemon loop, which is where the console is read anyway.
+pub struct Notifier {
+    config: NotificationConfig,
+    mute_labels: Vec<String>, // repositories with any of these labels are left out
+    repos: Arc<Mutex<Vec<RepoStatus>>>,
+    console_messages: Arc<Mutex<Vec<ConsoleMessage>>>,
+    seen: DateTime<Utc>,        // newest message already collected
+    pending: Vec<ConsoleMessage>,
+    last_digest: Instant,
+    last_poll: DateTime<Local>, // digest times between this and now are due
+}
+
+impl Notifier {
+    pub fn new(
+        config: NotificationConfig,
+        mute_labels: Vec<String>,
+        repos: Arc<Mutex<Vec<RepoStatus>>>,
+        console_messages: Arc<Mutex<Vec<ConsoleMessage>>>,
+    ) -> Self {
+        Notifier {
+            config,
+            mute_labels,
+            repos,
+            console_messages,
+            seen: Utc::now(),
+            pending: Vec::new(),
+            last_digest: Instant::now(),
+            last_poll: Local::now(),
+        }
+    }
+
+    // A reloaded config keeps what was collected for the next digest
+    pub fn reconfigure(&mut self, config: NotificationConfig, mute_labels: Vec<String>) {
+        self.config = config;
+        self.mute_labels = mute_labels;
+    }
+
+    fn sinks(&self) -> Vec<Sink> {
+        let mut sinks = Vec::new();
+        if self.config.desktop.unwrap_or(false) {
+            sinks.push(Sink::Desktop);
+        }
+        if self.config.webhook.is_some() {
+            sinks.push(Sink::Webhook);
+        }
+        if self.config.email.is_some() {
+            sinks.push(Sink::Email);
+        }
+        sinks
+    }
+
+    fn routed(&self, message: &ConsoleMessage, sink: Sink) -> bool {
+        match self.config.routes.as_ref().and_then(|routes| routes.get(message.kind.name())) {
+            Some(sinks) => sinks.iter().any(|name| name == sink.name()),
+            None => true,
+        }
+    }
+
+    fn digest(&self) -> bool {
+        self.config.digest_interval.is_some() || self.config.digest_times.as_ref().is_some_and(|times| !times.is_empty())
+    }
+
+    pub fn poll(&mut self) {
+        let sinks = self.sinks();
+        if sinks.is_empty() {
+            return;
+        }
+
+        // gitop's own "System" messages are about gitop, not the repositories, and replies to what was asked of
+        // it are no news to whoever asked. What the monitor and policies find is left.
+        {
+            let messages = self.console_messages.lock().unwrap();
+            self.pending.extend(
+                messages
+                    .iter()
+                    .filter(|message| message.timestamp > self.seen && message.repo != "System" && message.kind != EventKind::Action)
+                    .cloned(),
+            );
+            if let Some(newest) = messages.last() {
+                self.seen = self.seen.max(newest.timestamp);
+            }
+        }
+
+        let now = Local::now();
+        let due = if self.digest() {
+            let interval_due = self
+                .config
+                .digest_interval
+                .is_some_and(|minutes| self.last_digest.elapsed() >= Duration::from_secs(minutes.max(1) * 60));
+            let time_due = self.config.digest_times.iter().flatten().filter_map(|time| parse_time(time)).any(|time| {
+                now.date_naive()
+                    .and_time(time)
+                    .and_local_timezone(Local)
+                    .earliest()
+                    .is_some_and(|at| self.last_poll < at && at <= now)
+            });
+            interval_due || time_due
+        } else {
+            true
+        };
+        self.last_poll = now;
+        if !due {
+            return;
+        }
+
+        self.last_digest = Instant::now();
+        // Label rules are checked at sending time, so labels that changed since, a reloaded config and the labels
+        // agents report all count
+        let silenced: Vec<String> = self
+            .repos
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|repo| repo.labels.iter().any(|label| self.mute_labels.contains(label)))
+            .map(console_name)
+            .collect();
+        let pending: Vec<ConsoleMessage> = std::mem::take(&mut self.pending)
+            .into_iter()
+            .filter(|message| !silenced.contains(&message.repo))
+            .collect();
+        if pending.is_empty() {
+            return;
+        }
+        // Each sink gets its own summary of what is routed to it
+        let deliveries: Vec<(Sink, String, String)> = sinks
+            .into_iter()
+            .filter_map(|sink| {
+                let routed: Vec<&ConsoleMessage> = pending.iter().filter(|message| self.routed(message, sink)).collect();
+                (!routed.is_empty()).then(|| {
+                    let (title, body) = summarize(&routed);
+                    (sink, title, body)
+                })
+            })
+            .collect();
+        let config = self.config.clone();
+        let console_messages = self.console_messages.clone();
+        // Sinks talk to other processes and servers; the caller is drawing the UI
+        std::thread::spawn(move || {
+            for (sink, title, body) in deliveries {
+                if let Err(err) = send(&config, sink, &title, &body) {
+                    log_console(&console_messages, "System", "System", EventKind::Error, format!("Notification failed: {}: {}", sink.name(), err));
+                }
+            }
+        });
+    }
+}
+
+// A single message as it is, several grouped by repository with the first few of each
+fn summarize(messages: &[&ConsoleMessage]) -> (String, String) {
+    if let [message] = messages {
+        return (format!("gitop: {}", message.repo), format!("{}: {}", message.author, message.message));
+    }
+
+    let mut by_repo: BTreeMap<&str, Vec<&ConsoleMessage>> = BTreeMap::new();
+    for &message in messages {
+        by_repo.entry(message.repo.as_str()).or_default().push(message);
+    }
+    let repositories = if by_repo.len() == 1 { "repository" } else { "repositories" };
+    let title = format!("gitop: {} events in {} {}", messages.len(), by_repo.len(), repositories);
+    let mut lines = Vec::new();
+    for (repo, messages) in &by_repo {
+        lines.push(format!("{} ({})", repo, messages.len()));
+        for message in messages.iter().take(SUMMARY_LINES) {
+            lines.push(format!("  {}: {}", message.author, message.message));
+        }
+        if messages.len() > SUMMARY_LINES {
+            lines.push(format!("  and {} more", messages.len() - SUMMARY_LINES));
+        }
+    }
+    (title, lines.join("\n"))
+}
+
+fn send(config: &NotificationConfig, sink: Sink, title: &str, body: &str) -> Result<()> {
+    match (sink, &config.webhook, &config.email) {
+        (Sink::Desktop, ..) => send_desktop(title, body),
+        (Sink::Webhook, Some(webhook), _) => send_webhook(webhook, title, body),
+        (Sink::Email, _, Some(address)) => send_email(config.sendmail.as_deref().unwrap_or("sendmail -t"), address, title, body),
+        // Unset since the summary was made, by a config reload
+        _ => Ok(()),
+    }
+}
+
+fn send_desktop(title: &str, body: &str) -> Result<()> {
+    let status = if cfg!(target_os = "macos") {
+        let quote = |text: &str| format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""));
+        Command::new("osascript")
+            .arg("-e")
+            .arg(format!("display notification {} with title {}", quote(body), quote(title)))
+            .status()
+    } else {
+        Command::new("notify-send").args(["--app-name", "gitop", title, body]).status()
+    };
+    let status = status.context("no notifier command")?;
+    if !status.success() {
+        anyhow::bail!("notifier exited with {}", status);
+    }
+    Ok(())
+}
+
+// Slack and Mattermost incoming webhooks read "text"; the parts are there for anything else
+fn send_webhook(webhook: &str, title: &str, body: &str) -> Result<()> {
+    // Chat webhook URLs carry their token, so they may live in the keyring like other secrets
+    let url = if webhook.starts_with("env:") || webhook.starts_with("keyring:") { secrets::resolve(webhook)? } else { webhook.to_string() };
+    let payload = serde_json::json!({ "text": format!("{}\n{}", title, body), "title": title, "body": body });
+    match ureq::post(&url).set("User-Agent", "gitop").send_json(payload) {
+        Ok(_) => Ok(()),
+        Err(ureq::Error::Status(code, _)) => anyhow::bail!("server returned {}", code),
+        Err(err) => Err(err.into()),
+    }
+}
+
+fn send_email(sendmail: &str, address: &str, title: &str, body: &str) -> Result<()> {
+    let mut parts = sendmail.split_whitespace();
+    let program = parts.next().context("sendmail is empty")?;
+    let mut child = Command::new(program).args(parts).stdin(Stdio::piped()).spawn().with_context(|| format!("running {}", program))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        write!(stdin, "To: {}\nSubject: {}\nContent-Type: text/plain; charset=utf-8\n\n{}\n", address, title, body)?;
+    }
+    let status = child.wait()?;
+    if !status.success() {
+        anyhow::bail!("{} exited with {}", program, status);
+    }
+    Ok(())
+}
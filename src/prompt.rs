@@ -0,0 +1,70 @@
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+
+use crate::daemon::RepoState;
+use crate::{state_dir, RepoStatus};
+
+// Where the monitor leaves the latest state of every local repository for `gitop prompt`
+pub fn state_path() -> Option<PathBuf> {
+    Some(state_dir()?.join("state.json"))
+}
+
+// Paths are canonicalized here so the prompt doesn't have to resolve every configured repository
+pub fn states(repos: &[RepoStatus]) -> Vec<RepoState> {
+    repos
+        .iter()
+        .filter(|repo| repo.host.is_none() && repo.remote_refs.is_none())
+        .map(|repo| {
+            let mut state = RepoState::from_status(repo);
+            if let Ok(path) = repo.path.canonicalize() {
+                state.path = path.display().to_string();
+            }
+            state
+        })
+        .collect()
+}
+
+// Written to a temporary file and renamed so a prompt never reads half a file
+pub fn write_state(states: &[RepoState]) -> Result<()> {
+    let Some(path) = state_path() else {
+        return Ok(());
+    };
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let temp = path.with_extension(format!("json.{}", std::process::id()));
+    std::fs::write(&temp, serde_json::to_vec(states)?)?;
+    std::fs::rename(&temp, &path)?;
+    Ok(())
+}
+
+// Print "↑1↓3" for the repository containing `dir`, from the cached state only. Prints nothing when the
+// repository is up to date, not monitored, or no gitop has run yet.
+pub fn run(dir: &Path) -> Result<()> {
+    let Some(content) = state_path().and_then(|path| std::fs::read(path).ok()) else {
+        return Ok(());
+    };
+    let states: Vec<RepoState> = serde_json::from_slice(&content).unwrap_or_default();
+    let dir = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+
+    // The innermost repository wins, for worktrees or repositories nested in another
+    let Some(state) = states
+        .iter()
+        .filter(|state| dir.starts_with(&state.path))
+        .max_by_key(|state| state.path.len())
+    else {
+        return Ok(());
+    };
+
+    let mut segment = String::new();
+    if state.ahead > 0 {
+        segment.push_str(&format!("↑{}", state.ahead));
+    }
+    if state.behind > 0 {
+        segment.push_str(&format!("↓{}", state.behind));
+    }
+    if !segment.is_empty() {
+        println!("{}", segment);
+    }
+    Ok(())
+}
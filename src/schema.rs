@@ -0,0 +1,180 @@
+use serde_json::{json, Map, Value};
+
+// What try_parse_color accepts besides hex
+const COLOR_NAMES: &[&str] = &[
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "gray", "grey", "darkgray", "darkgrey", "lightred", "lightgreen",
+    "lightyellow", "lightblue", "lightmagenta", "lightcyan", "white", "reset", "default", "normal",
+];
+
+// JSON Schema (draft 7) of the config file, for editor completion and validation through taplo or a
+// `#:schema` comment. Written by hand, so a key added to Config, RepoConfig or one of their sections needs a
+// line here too; `gitop config validate` stays the authority on what is accepted.
+pub fn config_schema() -> Value {
+    let mut schema = object("gitop config", &[
+        ("repositories", json!({ "type": "array", "description": "Repositories to monitor", "items": repository() })),
+        ("refresh_interval", integer("Seconds between checks", 1)),
+        ("max_commits", integer("Commits shown when a repository is expanded", 0)),
+        ("colors", object("Table colors", &[
+            ("theme", enumeration("Theme for colors not set here", &["default", "high-contrast", "monochrome"])),
+            ("ahead_color", color("Ahead count arrows")),
+            ("behind_color", color("Behind count arrows")),
+            ("branch_color", color("Branch column")),
+            ("repo_color", color("Repository names")),
+            ("header_color", color("Table header row")),
+            ("dirty_color", color("Uncommitted change count next to the branch")),
+            ("error_color", color("Failed backups, policy violations and size warnings")),
+        ], &[])),
+        ("show_descriptions", boolean("Dimmed second line under each repository name")),
+        ("show_size", boolean("Disk usage column")),
+        ("show_fetch_time", boolean("Column with how long the last fetch took")),
+        ("show_diffstat", boolean("Column with +insertions/-deletions of the uncommitted changes")),
+        ("persist_commit_cache", boolean("Keep commit metadata under ~/.cache/gitop/commits between runs")),
+        ("search_depth", integer("Commits searched back from HEAD and from the upstream, default 2000", 1)),
+        ("author_colors", boolean("Color commit authors in the console")),
+        ("console_format", string("Console line layout, e.g. \"{time} {repo:>12} {author} {message}\"")),
+        ("highlights", json!({
+            "type": "array",
+            "description": "Row colors by threshold, the first matching rule wins",
+            "items": object("Highlight rule", &[
+                ("when", pattern("Condition such as \"behind >= 10\" or \"age > 30d\"", r"^\s*((ahead|behind|dirty)\s+(>=|<=|==|>|<)\s+-?\d+|age\s+(>=|<=|==|>|<)\s+-?\d+d?)\s*$")),
+                ("color", color("Row color")),
+            ], &["when", "color"]),
+        })),
+        ("flash", object("Briefly color rows whose ahead/behind changed", &[
+            ("duration", integer("Seconds, default 2", 1)),
+            ("color", color("Row background, default yellow")),
+        ], &[])),
+        ("mute_labels", strings("Repositories with any of these labels are never notified about")),
+        ("adaptive_refresh", object("Poll idle repositories less often, active ones more", &[
+            ("min_interval", integer("Seconds, for repositories with activity in the last hour", 1)),
+            ("max_interval", integer("Seconds, for repositories idle for a week or more", 1)),
+        ], &[])),
+        ("focus_interval", integer("Seconds between refreshes of the selected or expanded repositories", 1)),
+        ("metered", boolean("Start in metered mode: longer intervals, ls-remote checks and only the checked-out branch fetched")),
+        ("agent_listen", string("Address to accept `gitop agent` reports on, e.g. 0.0.0.0:7879")),
+        ("agent_token", secret("Token required from agents")),
+        ("webhook_listen", string("Address for GitHub/GitLab webhooks in daemon mode, e.g. 0.0.0.0:7880")),
+        ("rebase_command", string("Run for diverged branches instead of `git rebase -i {upstream}`")),
+        ("worktree_dir", string("Where new worktrees go, default \"{path}-{branch}\"")),
+        ("prune", boolean("Fetches delete remote-tracking branches that are gone on the server")),
+        ("maintenance_interval", integer("Seconds between `git maintenance` runs, off by default", 0)),
+        ("expected_email", string("user.email commits should get, or \"@example.com\" for any address there")),
+        ("large_file_warning", integer("Megabytes; bigger files in outgoing and incoming commits are flagged, default 50", 1)),
+        ("size_warning", integer("Megabytes; repositories whose .git grows past this are highlighted", 1)),
+        ("credentials", object("Forge tokens and HTTPS passwords as env:/keyring: references", &[
+            ("github_token", secret("Used by sync-github instead of GITHUB_TOKEN")),
+            ("https", json!({
+                "type": "array",
+                "description": "Username and password for HTTPS remotes on a host",
+                "items": object("HTTPS login", &[
+                    ("host", string("Host name, e.g. git.example.com")),
+                    ("username", string("User name")),
+                    ("password", secret("Password or token")),
+                ], &["host", "username", "password"]),
+            })),
+            ("webhook_secret", secret("Expected on incoming webhooks")),
+        ], &[])),
+        ("icons", enumeration("Decorations: emoji (default), nerdfont for Nerd Font glyphs, or none for plain ASCII", &["emoji", "nerdfont", "none"])),
+        ("layout", enumeration("split shows the selected repository in a pane next to the table instead of expanding it", &["table", "split"])),
+        ("notifications", object("Desktop, webhook and email notifications", &[
+            ("desktop", boolean("notify-send on Linux, osascript on macOS")),
+            ("webhook", string("URL that gets {\"text\": ...} POSTed, or an env:/keyring: reference to it")),
+            ("email", string("Address to mail through sendmail")),
+            ("sendmail", string("Command reading the mail on stdin, default \"sendmail -t\"")),
+            ("digest_interval", integer("Minutes between summaries", 1)),
+            ("digest_times", json!({
+                "type": "array",
+                "description": "Local times to send the summary at",
+                "items": pattern("HH:MM", r"^([01]?\d|2[0-3]):[0-5]\d$"),
+            })),
+            ("routes", json!({
+                "type": "object",
+                "description": "Event kind to the sinks it goes to; unlisted kinds go to every sink",
+                "propertyNames": { "enum": crate::notifications::EVENT_KINDS },
+                "additionalProperties": { "type": "array", "items": { "enum": crate::notifications::SINKS } },
+            })),
+        ], &[])),
+    ], &["repositories", "refresh_interval", "max_commits"]);
+    schema["$schema"] = json!("http://json-schema.org/draft-07/schema#");
+    schema["title"] = json!("gitop config");
+    schema
+}
+
+fn repository() -> Value {
+    object("Repository", &[
+        ("name", string("Name shown in the table")),
+        ("path", string("Local clone, ~ is expanded; leave out to watch `url` without a clone")),
+        ("remote", string("Remote to compare with, default \"origin\"")),
+        ("description", string("Shown in the details and under the name")),
+        ("labels", strings("Labels to filter and sort by")),
+        ("pinned", boolean("Always shown at the top of the table")),
+        ("url", string("Clone source when the path doesn't exist yet, or the repository to watch when there is no path")),
+        ("backup_remote", string("Remote that all branches and tags are mirrored to")),
+        ("backup_interval", integer("Seconds between backup pushes, default 3600", 1)),
+        ("protected_branches", strings("Incoming commits on these are checked against the policy")),
+        ("require_signed", boolean("Warn about unsigned commits on protected branches")),
+        ("detect_direct_pushes", boolean("Flag non-merge commits landing on protected branches")),
+        ("refspecs", strings("Fetched instead of the remote's configured refspecs")),
+        ("prs", json!({ "type": "array", "description": "Pull/merge request numbers whose head refs are tracked", "items": { "type": "integer", "minimum": 1 } })),
+        ("forge", enumeration("Pull request ref layout, detected from the url by default", &["github", "gitlab"])),
+        ("notes", boolean("Fetch refs/notes/commits and show notes in the commit view")),
+        ("trailer_alerts", json!({
+            "type": "array",
+            "description": "Console alerts for incoming commits carrying these trailers",
+            "items": object("Trailer alert", &[
+                ("trailer", string("Trailer key, e.g. \"Fixes\", matched case-insensitively")),
+                ("contains", string("Only values containing this")),
+            ], &["trailer"]),
+        })),
+        ("prune", boolean("Overrides the top-level prune")),
+        ("maintenance_interval", integer("Overrides the top-level maintenance_interval, 0 turns it off", 0)),
+        ("check_mode", enumeration("\"ls-remote\" fetches only when the branch tip moved", &["fetch", "ls-remote"])),
+        ("archived", boolean("Kept in the config but never checked")),
+        ("ignore_untracked", boolean("Untracked files don't count as changes")),
+        ("expected_email", string("Overrides the top-level expected_email")),
+        ("fetch_depth", integer("Commits fetched back from each tip, like `git fetch --depth`; behind counts that reach the cut-off are lower bounds", 1)),
+    ], &["name"])
+}
+
+fn object(description: &str, properties: &[(&str, Value)], required: &[&str]) -> Value {
+    let properties: Map<String, Value> = properties.iter().map(|(key, value)| (key.to_string(), value.clone())).collect();
+    json!({ "type": "object", "description": description, "additionalProperties": false, "properties": properties, "required": required })
+}
+
+fn string(description: &str) -> Value {
+    json!({ "type": "string", "description": description })
+}
+
+fn strings(description: &str) -> Value {
+    json!({ "type": "array", "description": description, "items": { "type": "string" } })
+}
+
+fn boolean(description: &str) -> Value {
+    json!({ "type": "boolean", "description": description })
+}
+
+fn integer(description: &str, minimum: u64) -> Value {
+    json!({ "type": "integer", "description": description, "minimum": minimum })
+}
+
+fn enumeration(description: &str, values: &[&str]) -> Value {
+    json!({ "type": "string", "description": description, "enum": values })
+}
+
+fn pattern(description: &str, pattern: &str) -> Value {
+    json!({ "type": "string", "description": description, "pattern": pattern })
+}
+
+// Color names in any case, or RGB hex. JSON Schema patterns have no case-insensitive flag, so every letter
+// becomes a [xX] class.
+fn color(description: &str) -> Value {
+    let names: Vec<String> = COLOR_NAMES
+        .iter()
+        .map(|name| name.chars().map(|c| format!("[{}{}]", c, c.to_ascii_uppercase())).collect())
+        .collect();
+    pattern(description, &format!("^({}|#?[0-9a-fA-F]{{6}})$", names.join("|")))
+}
+
+fn secret(description: &str) -> Value {
+    pattern(description, "^(env|keyring):.+")
+}
@@ -0,0 +1,90 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+// Service name gitop's entries are stored under in the OS keyring
+const KEYRING_SERVICE: &str = "gitop";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CredentialsConfig {
+    pub github_token: Option<String>,       // used by sync-github instead of GITHUB_TOKEN
+    pub https: Option<Vec<HttpsCredential>>, // username and password for HTTPS remotes on a host
+    pub webhook_secret: Option<String>,      // expected on webhooks when set
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HttpsCredential {
+    pub host: String,
+    pub username: String,
+    pub password: String,
+}
+
+// An HTTPS credential with its password looked up
+#[derive(Clone)]
+pub struct Login {
+    pub host: String,
+    pub username: String,
+    pub password: String,
+}
+
+// RepoStatus derives Debug and holds the logins, so the password must not come along
+impl std::fmt::Debug for Login {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Login").field("host", &self.host).field("username", &self.username).field("password", &"<redacted>").finish()
+    }
+}
+
+impl Login {
+    pub fn matches(&self, url: &str) -> bool {
+        let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+        let authority = rest.split('/').next().unwrap_or_default();
+        let host = authority.rsplit('@').next().unwrap_or_default();
+        host.split(':').next().unwrap_or_default().eq_ignore_ascii_case(&self.host)
+    }
+}
+
+// "env:NAME" reads an environment variable, "keyring:NAME" an entry stored with `gitop credentials set NAME`.
+// Anything else is refused so secrets can't end up in the config file.
+pub fn resolve(reference: &str) -> Result<String> {
+    if let Some(name) = reference.strip_prefix("env:") {
+        return std::env::var(name).with_context(|| format!("environment variable {} is not set", name));
+    }
+    if let Some(name) = reference.strip_prefix("keyring:") {
+        return match keyring::Entry::new(KEYRING_SERVICE, name)?.get_password() {
+            Err(keyring::Error::NoEntry) => anyhow::bail!("no keyring entry \"{}\", add it with `gitop credentials set {}`", name, name),
+            result => Ok(result?),
+        };
+    }
+    anyhow::bail!("expected \"env:NAME\" or \"keyring:NAME\" instead of the secret itself")
+}
+
+// Tokens sent by clients are compared without stopping at the first differing byte, so response times don't
+// reveal how much of a guess was right
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+pub fn store(name: &str, secret: &str) -> Result<()> {
+    keyring::Entry::new(KEYRING_SERVICE, name)?.set_password(secret)?;
+    Ok(())
+}
+
+pub fn delete(name: &str) -> Result<()> {
+    keyring::Entry::new(KEYRING_SERVICE, name)?.delete_credential()?;
+    Ok(())
+}
+
+// Credentials that can't be resolved are left out; `gitop config validate` reports them
+pub fn logins(credentials: Option<&CredentialsConfig>) -> Vec<Login> {
+    credentials
+        .and_then(|credentials| credentials.https.as_ref())
+        .into_iter()
+        .flatten()
+        .filter_map(|credential| {
+            Some(Login {
+                host: credential.host.clone(),
+                username: credential.username.clone(),
+                password: resolve(&credential.password).ok()?,
+            })
+        })
+        .collect()
+}
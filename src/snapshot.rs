@@ -0,0 +1,87 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::daemon::RepoState;
+use crate::{format_age, status, Config};
+
+// Every local repository's state at one moment, for comparing against later
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub taken: DateTime<Utc>,
+    pub repos: Vec<RepoState>,
+}
+
+pub fn take(config: &Config) -> Snapshot {
+    let mut repos = Vec::new();
+    for (name, result) in status::check_all(config) {
+        match result {
+            Ok(status) => repos.push(RepoState::from_status(&status)),
+            Err(err) => eprintln!("  skipped {}: {}", name, err),
+        }
+    }
+    Snapshot { taken: Utc::now(), repos }
+}
+
+pub fn write(snapshot: &Snapshot, path: &Path) -> Result<()> {
+    std::fs::write(path, serde_json::to_vec_pretty(snapshot)?).with_context(|| format!("writing {}", path.display()))
+}
+
+pub fn read(path: &Path) -> Result<Snapshot> {
+    let content = std::fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+    serde_json::from_slice(&content).with_context(|| format!("{} is not a gitop snapshot", path.display()))
+}
+
+// One line per repository that changed between the two snapshots, matched by path so renaming a repository in
+// the config doesn't make it look new
+pub fn diff(before: &Snapshot, after: &Snapshot) -> Vec<String> {
+    let mut lines = Vec::new();
+    for state in &after.repos {
+        let Some(old) = before.repos.iter().find(|old| old.path == state.path) else {
+            lines.push(format!("  + {}  new since the snapshot", state.repo));
+            continue;
+        };
+
+        let mut changes = Vec::new();
+        if old.branch != state.branch {
+            changes.push(format!("branch {} → {}", old.branch, state.branch));
+        }
+        for (what, old_count, count) in [("ahead", old.ahead, state.ahead), ("behind", old.behind, state.behind), ("dirty", old.dirty, state.dirty)] {
+            if old_count != count {
+                changes.push(format!("{} {} → {}", what, old_count, count));
+            }
+        }
+        match (&old.operation, &state.operation) {
+            (None, Some(operation)) => changes.push(format!("now {}", operation)),
+            (Some(operation), None) => changes.push(format!("no longer {}", operation)),
+            _ => {}
+        }
+        if !changes.is_empty() {
+            lines.push(format!("  ~ {}  {}", state.repo, changes.join(", ")));
+        }
+    }
+    for old in &before.repos {
+        if !after.repos.iter().any(|state| state.path == old.path) {
+            lines.push(format!("  - {}  no longer monitored", old.repo));
+        }
+    }
+    lines
+}
+
+// `gitop diff-snapshot FILE`: what changed between the snapshot and now
+pub fn run_diff(config: &Config, path: &Path) -> Result<()> {
+    let before = read(path)?;
+    let after = take(config);
+    let taken = format!("{} ({} ago)", before.taken.with_timezone(&Local).format("%Y-%m-%d %H:%M"), format_age(before.taken));
+    let lines = diff(&before, &after);
+    if lines.is_empty() {
+        println!("Nothing changed since {}", taken);
+    } else {
+        println!("Changes since {}:", taken);
+        for line in lines {
+            println!("{}", line);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,198 @@
+use anyhow::Result;
+use chrono::Utc;
+use clap::ValueEnum;
+
+use crate::{
+    conflicted_files, count_dirty_files, get_repo_status, last_commit_time, repo_operation, upstream_gone, wrong_identity, Config, RepoDefaults,
+    RepoStatus,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum StatusFormat {
+    /// One readable line per repository
+    Text,
+    /// "path:1:1: message" lines for :cfile, :cexpr or `vim -q`, and compile buffers in other editors
+    VimQuickfix,
+    /// Each repository through --template
+    Template,
+}
+
+// What `gitop check` holds every repository to; limits left out aren't checked
+#[derive(Debug, Clone, Default)]
+pub struct Limits {
+    pub behind: Option<usize>,
+    pub ahead: Option<usize>,
+    pub dirty: Option<usize>,
+    pub age: Option<i64>, // days since the newest local or upstream commit, as in the age highlight
+}
+
+impl Limits {
+    pub fn is_empty(&self) -> bool {
+        self.behind.is_none() && self.ahead.is_none() && self.dirty.is_none() && self.age.is_none()
+    }
+}
+
+// "14d" or "14", as ages are written in highlight conditions
+pub fn parse_days(days: &str) -> Result<i64, String> {
+    days.strip_suffix('d')
+        .unwrap_or(days)
+        .parse()
+        .ok()
+        .filter(|days: &i64| *days >= 0)
+        .ok_or_else(|| format!("invalid age \"{}\", expected days such as \"14d\"", days))
+}
+
+// Fetches every local repository at once and fills in what the monitor's first check would; archived
+// repositories are left out
+pub fn check_all(config: &Config) -> Vec<(String, Result<RepoStatus>)> {
+    let defaults = RepoDefaults::from_config(config);
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = config
+            .repositories
+            .iter()
+            .filter(|repo_config| !repo_config.path.is_empty() && !repo_config.archived.unwrap_or(false))
+            .map(|repo_config| {
+                let mut status = RepoStatus::from_config(repo_config.clone(), &defaults);
+                (repo_config.name.clone(), scope.spawn(move || check(&mut status).map(|()| status)))
+            })
+            .collect();
+        handles.into_iter().map(|(name, handle)| (name, handle.join().unwrap())).collect()
+    })
+}
+
+fn check(status: &mut RepoStatus) -> Result<()> {
+    let (ahead, behind, branch, _) = get_repo_status(status, false)?;
+    status.ahead = ahead;
+    status.behind = behind;
+    status.current_branch = branch;
+    status.dirty = count_dirty_files(&status.path, !status.ignore_untracked);
+    status.operation = repo_operation(&status.path);
+    status.conflicts = if status.dirty > 0 { conflicted_files(&status.path) } else { Vec::new() };
+    status.upstream_gone = upstream_gone(&status.path);
+    status.last_commit = last_commit_time(&status.path, &status.remote);
+    status.wrong_identity = wrong_identity(status);
+    Ok(())
+}
+
+// What needs doing, most pressing first, e.g. "2 conflicts, merging, 3 behind"
+fn summary(status: &RepoStatus) -> String {
+    let mut parts = Vec::new();
+    if !status.conflicts.is_empty() {
+        parts.push(format!("{} conflicts", status.conflicts.len()));
+    }
+    if let Some(operation) = &status.operation {
+        parts.push(operation.clone());
+    }
+    if let Some(upstream) = &status.upstream_gone {
+        parts.push(format!("upstream {} gone", upstream));
+    }
+    if let (Some(email), Some(expected)) = (&status.wrong_identity, &status.expected_email) {
+        let current = if email.is_empty() { "no user.email" } else { email.as_str() };
+        parts.push(format!("commits would use {}, expected {}", current, expected));
+    }
+    if status.behind > 0 {
+        parts.push(format!("{}{} behind", status.behind, status.behind_bound()));
+    }
+    if status.dirty > 0 {
+        parts.push(format!("{} changed files", status.dirty));
+    }
+    if status.ahead > 0 {
+        parts.push(format!("{} ahead", status.ahead));
+    }
+    if parts.is_empty() {
+        parts.push("up to date".to_string());
+    }
+    parts.join(", ")
+}
+
+// {name}, {path}, {branch}, {ahead}, {behind}, {dirty}, {operation} and {summary}
+fn fill(template: &str, status: &RepoStatus) -> String {
+    [
+        ("{name}", status.name.clone()),
+        ("{path}", status.path.display().to_string()),
+        ("{branch}", status.current_branch.clone()),
+        ("{ahead}", status.ahead.to_string()),
+        ("{behind}", status.behind.to_string()),
+        ("{dirty}", status.dirty.to_string()),
+        ("{operation}", status.operation.clone().unwrap_or_default()),
+        ("{summary}", summary(status)),
+    ]
+    .iter()
+    .fold(template.to_string(), |line, (placeholder, value)| line.replace(placeholder, value))
+}
+
+// How the repository breaks the limits, e.g. "3 behind (max 0)"; empty when it doesn't
+fn violations(status: &RepoStatus, limits: &Limits) -> Vec<String> {
+    let mut found = Vec::new();
+    for (what, count, limit) in [("behind", status.behind, limits.behind), ("ahead", status.ahead, limits.ahead), ("changed files", status.dirty, limits.dirty)] {
+        if let Some(limit) = limit
+            && count > limit
+        {
+            found.push(format!("{} {} (max {})", count, what, limit));
+        }
+    }
+    if let Some(limit) = limits.age {
+        match status.last_commit {
+            Some(time) if (Utc::now() - time).num_days() > limit => {
+                found.push(format!("newest commit {} days old (max {})", (Utc::now() - time).num_days(), limit));
+            }
+            None => found.push("no commits to date".to_string()),
+            _ => {}
+        }
+    }
+    found
+}
+
+// `gitop check`: every repository against the limits, one line per violation. A repository that can't be
+// fetched counts as one, since its freshness can't be vouched for. Returns whether everything passed.
+pub fn run_check(config: &Config, limits: &Limits) -> bool {
+    let results = check_all(config);
+    let total = results.len();
+    let mut failing = 0;
+    for (name, result) in results {
+        let found = match result {
+            Ok(status) => violations(&status, limits),
+            Err(err) => vec![format!("check failed: {}", err)],
+        };
+        if !found.is_empty() {
+            failing += 1;
+        }
+        for violation in found {
+            println!("{}: {}", name, violation);
+        }
+    }
+    if failing > 0 {
+        println!("{} of {} repositories violate the policy", failing, total);
+    } else {
+        println!("All {} repositories are within the limits", total);
+    }
+    failing == 0
+}
+
+// `gitop status`: the repositories that need attention, most urgent first, or all of them in config order
+pub fn run(config: &Config, format: StatusFormat, template: Option<&str>, all: bool) -> Result<()> {
+    let mut statuses = Vec::new();
+    for (name, result) in check_all(config) {
+        match result {
+            Ok(status) => statuses.push(status),
+            Err(err) => eprintln!("  skipped {}: {}", name, err),
+        }
+    }
+    if !all {
+        statuses.retain(|status| status.attention() > 0 || status.upstream_gone.is_some() || status.wrong_identity.is_some());
+        statuses.sort_by_key(|status| std::cmp::Reverse(status.attention()));
+    }
+
+    for status in &statuses {
+        let line = match format {
+            StatusFormat::Text => format!("{} on {}: {}", status.name, status.current_branch, summary(status)),
+            StatusFormat::VimQuickfix => fill("{path}:1:1: {name} ({branch}): {summary}", status),
+            StatusFormat::Template => fill(template.unwrap_or_default(), status),
+        };
+        println!("{}", line);
+    }
+    if statuses.is_empty() && format == StatusFormat::Text {
+        println!("Nothing needs attention");
+    }
+    Ok(())
+}
@@ -0,0 +1,436 @@
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use toml_edit::{ImDocument, Item, TableLike, Value};
+
+use crate::{expand_path, notifications, secrets, shell_words, try_parse_color, Config, Icons, LayoutMode, Palette};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone)]
+pub struct Issue {
+    pub file: PathBuf,
+    pub severity: Severity,
+    pub position: Option<(usize, usize)>, // 1-based line and column
+    pub message: String,
+}
+
+impl Issue {
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+// Compiler-style "file:line:col: error: message" so editors and CI logs can jump to it
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        match self.position {
+            Some((line, column)) => write!(f, "{}:{}:{}: {}: {}", self.file.display(), line, column, severity, self.message),
+            None => write!(f, "{}: {}: {}", self.file.display(), severity, self.message),
+        }
+    }
+}
+
+struct Validator<'a> {
+    file: &'a Path,
+    content: &'a str,
+    issues: Vec<Issue>,
+}
+
+impl Validator<'_> {
+    fn report(&mut self, severity: Severity, span: Option<Range<usize>>, message: String) {
+        let position = span.map(|span| line_column(self.content, span.start));
+        self.issues.push(Issue { file: self.file.to_path_buf(), severity, position, message });
+    }
+}
+
+pub fn validate_config_file(path: &Path) -> Result<Vec<Issue>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let mut validator = Validator { file: path, content: &content, issues: Vec::new() };
+
+    let document = match ImDocument::parse(content.as_str()) {
+        Ok(document) => document,
+        Err(err) => {
+            validator.report(Severity::Error, err.span(), err.message().trim().replace('\n', ", "));
+            return Ok(validator.issues);
+        }
+    };
+
+    // Deserialize through serde_ignored so keys gitop doesn't know about are reported instead of dropped
+    let mut unknown = Vec::new();
+    let config: Config = match serde_ignored::deserialize(toml::Deserializer::new(&content), |path| {
+        unknown.push(path.to_string())
+    }) {
+        Ok(config) => config,
+        Err(err) => {
+            let err: toml::de::Error = err;
+            validator.report(Severity::Error, err.span(), err.message().trim().replace('\n', ", "));
+            return Ok(validator.issues);
+        }
+    };
+
+    for path in unknown {
+        let segments: Vec<&str> = path.split('.').filter(|segment| *segment != "?").collect();
+        let span = lookup(&document, &segments).and_then(|(key, _)| key);
+        validator.report(Severity::Error, span, format!("unknown key `{}`", segments.join(".")));
+    }
+
+    check_config(&mut validator, &document, &config);
+    Ok(validator.issues)
+}
+
+fn check_config(validator: &mut Validator, document: &ImDocument<&str>, config: &Config) {
+    let value_span = |segments: &[&str]| lookup(document, segments).and_then(|(_, value)| value);
+
+    // A zero tokio interval panics, so catch it here rather than at startup
+    if config.refresh_interval == 0 {
+        validator.report(Severity::Error, value_span(&["refresh_interval"]), "refresh_interval must be at least 1 second".to_string());
+    }
+
+    if config.focus_interval == Some(0) {
+        validator.report(Severity::Error, value_span(&["focus_interval"]), "focus_interval must be at least 1 second".to_string());
+    }
+
+    if let Some(addr) = &config.agent_listen
+        && addr.parse::<std::net::SocketAddr>().is_err()
+    {
+        validator.report(Severity::Error, value_span(&["agent_listen"]), format!("agent_listen \"{}\" is not an address like 0.0.0.0:7879", addr));
+    }
+
+    if let Some(addr) = config.agent_listen.as_ref().and_then(|addr| addr.parse::<std::net::SocketAddr>().ok())
+        && !addr.ip().is_loopback()
+        && config.agent_token.is_none()
+    {
+        validator.report(
+            Severity::Error,
+            value_span(&["agent_listen"]),
+            format!("agent_listen \"{}\" accepts reports from other machines, so agent_token is required", addr),
+        );
+    }
+
+    if let Some(addr) = &config.webhook_listen
+        && addr.parse::<std::net::SocketAddr>().is_err()
+    {
+        validator.report(Severity::Error, value_span(&["webhook_listen"]), format!("webhook_listen \"{}\" is not an address like 0.0.0.0:7880", addr));
+    }
+
+    if let Some(addr) = config.webhook_listen.as_ref().and_then(|addr| addr.parse::<std::net::SocketAddr>().ok())
+        && !addr.ip().is_loopback()
+        && config.credentials.as_ref().is_none_or(|credentials| credentials.webhook_secret.is_none())
+    {
+        validator.report(
+            Severity::Error,
+            value_span(&["webhook_listen"]),
+            format!("webhook_listen \"{}\" accepts webhooks from other machines, so credentials.webhook_secret is required", addr),
+        );
+    }
+
+    if config.size_warning == Some(0) {
+        validator.report(Severity::Error, value_span(&["size_warning"]), "size_warning must be at least 1 megabyte".to_string());
+    }
+
+    if let Some(command) = &config.rebase_command {
+        match shell_words(command) {
+            None => validator.report(Severity::Error, value_span(&["rebase_command"]), "rebase_command has an unclosed quote".to_string()),
+            Some(words) if words.is_empty() => validator.report(Severity::Error, value_span(&["rebase_command"]), "rebase_command is empty".to_string()),
+            Some(_) => {}
+        }
+    }
+
+    if let Some(adaptive) = &config.adaptive_refresh {
+        if adaptive.min_interval == Some(0) {
+            validator.report(Severity::Error, value_span(&["adaptive_refresh", "min_interval"]), "min_interval must be at least 1 second".to_string());
+        }
+        if let (Some(min), Some(max)) = (adaptive.min_interval, adaptive.max_interval)
+            && min > max
+        {
+            validator.report(Severity::Error, value_span(&["adaptive_refresh", "max_interval"]), format!("max_interval ({}) is less than min_interval ({})", max, min));
+        }
+    }
+
+    if let Some(icons) = &config.icons
+        && Icons::from_name(icons).is_none()
+    {
+        validator.report(Severity::Error, value_span(&["icons"]), format!("unknown icons \"{}\", expected emoji, nerdfont or none", icons));
+    }
+
+    if let Some(layout) = &config.layout
+        && LayoutMode::from_name(layout).is_none()
+    {
+        validator.report(Severity::Error, value_span(&["layout"]), format!("unknown layout \"{}\", expected table or split", layout));
+    }
+
+    if let Some(colors) = &config.colors {
+        if let Some(theme) = &colors.theme
+            && Palette::theme(theme).is_none()
+        {
+            validator.report(Severity::Error, value_span(&["colors", "theme"]), format!("unknown theme \"{}\", expected default, high-contrast or monochrome", theme));
+        }
+        for (key, value) in [
+            ("ahead_color", &colors.ahead_color),
+            ("behind_color", &colors.behind_color),
+            ("branch_color", &colors.branch_color),
+            ("repo_color", &colors.repo_color),
+            ("header_color", &colors.header_color),
+            ("dirty_color", &colors.dirty_color),
+            ("error_color", &colors.error_color),
+        ] {
+            if let Some(value) = value
+                && try_parse_color(value).is_none()
+            {
+                validator.report(Severity::Error, value_span(&["colors", key]), format!("invalid color \"{}\" for {}", value, key));
+            }
+        }
+    }
+
+    if let Some(flash) = &config.flash {
+        if flash.duration == Some(0) {
+            validator.report(Severity::Error, value_span(&["flash", "duration"]), "flash duration must be at least 1 second".to_string());
+        }
+        if let Some(color) = &flash.color
+            && try_parse_color(color).is_none()
+        {
+            validator.report(Severity::Error, value_span(&["flash", "color"]), format!("invalid color \"{}\" for flash", color));
+        }
+    }
+
+    if let Some(notifications) = &config.notifications {
+        if notifications.digest_interval == Some(0) {
+            validator.report(Severity::Error, value_span(&["notifications", "digest_interval"]), "digest_interval must be at least 1 minute".to_string());
+        }
+        for (index, time) in notifications.digest_times.iter().flatten().enumerate() {
+            if notifications::parse_time(time).is_none() {
+                validator.report(Severity::Error, value_span(&["notifications", "digest_times", &index.to_string()]), format!("digest time \"{}\" is not HH:MM", time));
+            }
+        }
+        for (kind, sinks) in notifications.routes.iter().flatten() {
+            if !notifications::EVENT_KINDS.contains(&kind.as_str()) {
+                let span = lookup(document, &["notifications", "routes", kind]).and_then(|(key, _)| key);
+                validator.report(Severity::Error, span, format!("unknown event kind \"{}\", expected one of {}", kind, notifications::EVENT_KINDS.join(", ")));
+                continue;
+            }
+            for (index, sink) in sinks.iter().enumerate() {
+                let span = value_span(&["notifications", "routes", kind, &index.to_string()]);
+                let configured = match sink.as_str() {
+                    "desktop" => notifications.desktop.unwrap_or(false),
+                    "webhook" => notifications.webhook.is_some(),
+                    "email" => notifications.email.is_some(),
+                    _ => {
+                        validator.report(Severity::Error, span, format!("unknown sink \"{}\", expected one of {}", sink, notifications::SINKS.join(", ")));
+                        continue;
+                    }
+                };
+                if !configured {
+                    validator.report(Severity::Warning, span, format!("{} events are routed to {}, which isn't set up", kind, sink));
+                }
+            }
+        }
+        if let Some(webhook) = &notifications.webhook
+            && (webhook.starts_with("env:") || webhook.starts_with("keyring:"))
+            && let Err(err) = secrets::resolve(webhook)
+        {
+            validator.report(Severity::Warning, value_span(&["notifications", "webhook"]), format!("notifications.webhook can't be read right now: {}", err));
+        }
+    }
+
+    let mut references = Vec::new();
+    if let Some(token) = &config.agent_token {
+        references.push(("agent_token".to_string(), token));
+    }
+    if let Some(credentials) = &config.credentials {
+        if let Some(token) = &credentials.github_token {
+            references.push(("credentials.github_token".to_string(), token));
+        }
+        if let Some(secret) = &credentials.webhook_secret {
+            references.push(("credentials.webhook_secret".to_string(), secret));
+        }
+        for (index, https) in credentials.https.iter().flatten().enumerate() {
+            references.push((format!("credentials.https.{}.password", index), &https.password));
+        }
+    }
+    for (key, reference) in references {
+        let span = value_span(&key.split('.').collect::<Vec<_>>());
+        if !reference.starts_with("env:") && !reference.starts_with("keyring:") {
+            // Don't echo what is probably the secret itself
+            validator.report(Severity::Error, span, format!("{} must be \"env:NAME\" or \"keyring:NAME\", not the secret itself", key));
+        } else if let Err(err) = secrets::resolve(reference) {
+            validator.report(Severity::Warning, span, format!("{} can't be read right now: {}", key, err));
+        }
+    }
+
+    for (index, rule) in config.highlights.iter().flatten().enumerate() {
+        if try_parse_color(&rule.color).is_none() {
+            validator.report(Severity::Error, value_span(&["highlights", &index.to_string(), "color"]), format!("invalid color \"{}\" for highlight", rule.color));
+        }
+    }
+
+    let mut names: HashMap<&str, usize> = HashMap::new();
+    let mut paths: Vec<(PathBuf, usize)> = Vec::new();
+    for (index, repo) in config.repositories.iter().enumerate() {
+        let index_str = index.to_string();
+        let field_span = |field: &str| value_span(&["repositories", &index_str, field]);
+
+        if let Some(first) = names.get(repo.name.as_str()) {
+            validator.report(Severity::Error, field_span("name"), format!("duplicate repository name \"{}\" (first used by repository #{})", repo.name, first + 1));
+        } else {
+            names.insert(&repo.name, index);
+        }
+
+        if repo.protected_branches.as_ref().is_none_or(|branches| branches.is_empty()) {
+            for (key, value) in [("require_signed", repo.require_signed), ("detect_direct_pushes", repo.detect_direct_pushes)] {
+                if value == Some(true) {
+                    validator.report(Severity::Warning, field_span(key), format!("{} has no effect without protected_branches", key));
+                }
+            }
+        }
+
+        for (refspec_index, refspec) in repo.refspecs.iter().flatten().enumerate() {
+            if !is_valid_refspec(refspec) {
+                let span = value_span(&["repositories", &index_str, "refspecs", &refspec_index.to_string()]);
+                validator.report(Severity::Error, span, format!("invalid refspec \"{}\"", refspec));
+            }
+        }
+
+        if let Some(forge) = &repo.forge
+            && !forge.eq_ignore_ascii_case("github")
+            && !forge.eq_ignore_ascii_case("gitlab")
+        {
+            validator.report(Severity::Error, field_span("forge"), format!("unknown forge \"{}\", expected \"github\" or \"gitlab\"", forge));
+        }
+
+        for (alert_index, alert) in repo.trailer_alerts.iter().flatten().enumerate() {
+            if alert.trailer.trim().is_empty() || alert.trailer.contains(':') {
+                let span = value_span(&["repositories", &index_str, "trailer_alerts", &alert_index.to_string(), "trailer"]);
+                validator.report(Severity::Error, span, format!("invalid trailer name \"{}\", expected a key like \"Fixes\"", alert.trailer));
+            }
+        }
+
+        if let Some(mode) = &repo.check_mode
+            && !mode.eq_ignore_ascii_case("fetch")
+            && !mode.eq_ignore_ascii_case("ls-remote")
+        {
+            validator.report(Severity::Error, field_span("check_mode"), format!("unknown check_mode \"{}\", expected \"fetch\" or \"ls-remote\"", mode));
+        }
+
+        if repo.fetch_depth == Some(0) {
+            validator.report(Severity::Error, field_span("fetch_depth"), "fetch_depth must be at least 1 commit".to_string());
+        }
+
+        if repo.backup_interval == Some(0) {
+            validator.report(Severity::Error, field_span("backup_interval"), "backup_interval must be at least 1 second".to_string());
+        }
+
+        // Without a path the repository is only watched through its url
+        if repo.path.is_empty() {
+            if repo.url.is_none() {
+                validator.report(Severity::Error, value_span(&["repositories", &index_str]), "repository needs a path, or a url to watch without a clone".to_string());
+            }
+            let local_only = [
+                ("backup_remote", repo.backup_remote.is_some()),
+                ("protected_branches", repo.protected_branches.is_some()),
+                ("refspecs", repo.refspecs.is_some()),
+                ("prs", repo.prs.is_some()),
+                ("notes", repo.notes.is_some()),
+                ("trailer_alerts", repo.trailer_alerts.is_some()),
+                ("maintenance_interval", repo.maintenance_interval.is_some()),
+                ("check_mode", repo.check_mode.is_some()),
+                ("fetch_depth", repo.fetch_depth.is_some()),
+            ];
+            for (key, _) in local_only.iter().filter(|(_, set)| *set) {
+                validator.report(Severity::Warning, field_span(key), format!("{} has no effect without a path", key));
+            }
+            continue;
+        }
+
+        let expanded = expand_path(&repo.path);
+        let path = expanded.canonicalize().unwrap_or(expanded);
+        for (other, other_index) in &paths {
+            let other_name = &config.repositories[*other_index].name;
+            if *other == path {
+                validator.report(Severity::Error, field_span("path"), format!("path is the same as repository \"{}\"", other_name));
+            } else if path.starts_with(other) || other.starts_with(&path) {
+                validator.report(Severity::Warning, field_span("path"), format!("path overlaps with repository \"{}\"", other_name));
+            }
+        }
+        paths.push((path, index));
+    }
+
+    if let Some(mute_labels) = &config.mute_labels {
+        for (index, label) in mute_labels.iter().enumerate() {
+            let used = config.repositories.iter()
+                .any(|repo| repo.labels.as_ref().is_some_and(|labels| labels.contains(label)));
+            if !used {
+                validator.report(Severity::Warning, value_span(&["mute_labels", &index.to_string()]), format!("mute label \"{}\" is not used by any repository", label));
+            }
+        }
+    }
+}
+
+// Syntax check only: optional "+", a source, and an optional ":destination" with matching wildcards
+fn is_valid_refspec(refspec: &str) -> bool {
+    let spec = refspec.strip_prefix('+').unwrap_or(refspec);
+    let (src, dst) = spec.split_once(':').unwrap_or((spec, ""));
+    !src.is_empty()
+        && !spec.chars().any(char::is_whitespace)
+        && (dst.is_empty() || src.contains('*') == dst.contains('*'))
+}
+
+type Spans = (Option<Range<usize>>, Option<Range<usize>>);
+
+// Key and value spans for a dotted path such as ["repositories", "0", "name"]
+fn lookup(document: &ImDocument<&str>, segments: &[&str]) -> Option<Spans> {
+    let mut table: &dyn TableLike = document.as_table();
+    let mut i = 0;
+    loop {
+        let (key, item) = table.get_key_value(segments.get(i)?)?;
+        let index = segments.get(i + 1);
+        table = match (item, index) {
+            (_, None) => return Some((key.span(), item.span())),
+            (Item::Table(inner), _) => {
+                i += 1;
+                inner
+            }
+            (Item::Value(Value::InlineTable(inner)), _) => {
+                i += 1;
+                inner
+            }
+            (Item::ArrayOfTables(array), Some(index)) => {
+                let inner = array.get(index.parse().ok()?)?;
+                if i + 2 == segments.len() {
+                    return Some((key.span(), inner.span()));
+                }
+                i += 2;
+                inner
+            }
+            (Item::Value(Value::Array(array)), Some(index)) => {
+                let element = array.get(index.parse().ok()?)?;
+                match element {
+                    Value::InlineTable(inner) if i + 2 < segments.len() => {
+                        i += 2;
+                        inner
+                    }
+                    _ => return Some((key.span(), element.span())),
+                }
+            }
+            _ => return None,
+        };
+    }
+}
+
+fn line_column(content: &str, offset: usize) -> (usize, usize) {
+    let before = &content[..offset.min(content.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().map_or(0, |rest| rest.chars().count()) + 1;
+    (line, column)
+}
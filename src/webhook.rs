@@ -0,0 +1,139 @@
+use anyhow::Result;
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    routing::post,
+    Router,
+};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+use tokio::{net::TcpListener, sync::Notify};
+
+use crate::notifications::EventKind;
+use crate::{log_console, remote_urls, secrets, ConsoleMessage, RepoStatus};
+
+const WEBHOOK_PATH: &str = "/webhook";
+
+#[derive(Clone)]
+struct Receiver {
+    repos: Arc<Mutex<Vec<RepoStatus>>>,
+    console_messages: Arc<Mutex<Vec<ConsoleMessage>>>,
+    wake: Arc<Notify>,
+    secret: Option<String>,
+}
+
+// Accept GitHub and GitLab webhooks and refresh the repositories they are about right away
+pub async fn listen(
+    addr: SocketAddr,
+    secret: Option<String>,
+    repos: Arc<Mutex<Vec<RepoStatus>>>,
+    console_messages: Arc<Mutex<Vec<ConsoleMessage>>>,
+    wake: Arc<Notify>,
+) -> Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    let receiver = Receiver { repos, console_messages, wake, secret };
+    let router = Router::new().route(WEBHOOK_PATH, post(webhook_handler)).with_state(receiver);
+    tokio::spawn(async move { axum::serve(listener, router).await });
+    Ok(())
+}
+
+async fn webhook_handler(State(receiver): State<Receiver>, headers: HeaderMap, body: Bytes) -> StatusCode {
+    if let Some(secret) = &receiver.secret
+        && !is_signed(secret, &headers, &body)
+    {
+        return StatusCode::UNAUTHORIZED;
+    }
+    let Ok(payload) = serde_json::from_slice::<serde_json::Value>(&body) else {
+        return StatusCode::BAD_REQUEST;
+    };
+
+    // GitHub describes the repository under "repository", GitLab under "project" and "repository"
+    let urls: Vec<String> = [
+        "/repository/clone_url",
+        "/repository/ssh_url",
+        "/repository/html_url",
+        "/repository/git_http_url",
+        "/repository/git_ssh_url",
+        "/project/git_http_url",
+        "/project/git_ssh_url",
+        "/project/web_url",
+    ]
+    .iter()
+    .filter_map(|pointer| payload.pointer(pointer)?.as_str())
+    .map(normalize_url)
+    .collect();
+    if urls.is_empty() {
+        return StatusCode::NO_CONTENT;
+    }
+
+    // Remote URLs are read without holding the lock, the monitor may be fetching, and off the async runtime.
+    // Remote-only repositories are matched by their configured url.
+    let candidates: Vec<_> = receiver
+        .repos
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|repo| repo.host.is_none())
+        .map(|repo| (repo.name.clone(), repo.path.clone(), repo.remote.clone(), repo.remote_refs.as_ref().and(repo.url.clone())))
+        .collect();
+    let matched = tokio::task::spawn_blocking(move || -> Vec<String> {
+        candidates
+            .into_iter()
+            .filter(|(_, path, remote, watched_url)| match watched_url {
+                Some(url) => urls.contains(&normalize_url(url)),
+                None => remote_urls(path)
+                    .iter()
+                    .any(|(name, url)| name == remote && urls.contains(&normalize_url(url))),
+            })
+            .map(|(name, ..)| name)
+            .collect()
+    })
+    .await
+    .unwrap_or_default();
+    if matched.is_empty() {
+        return StatusCode::NO_CONTENT;
+    }
+
+    let mut repos = receiver.repos.lock().unwrap();
+    for repo in repos.iter_mut().filter(|repo| repo.host.is_none() && matched.contains(&repo.name)) {
+        repo.next_check = Instant::now();
+        if !repo.muted {
+            log_console(&receiver.console_messages, &repo.name, "GitOp", EventKind::Action, "Webhook received, refreshing".to_string());
+        }
+    }
+    drop(repos);
+    receiver.wake.notify_one();
+    StatusCode::ACCEPTED
+}
+
+// GitHub signs the body with the secret (X-Hub-Signature-256), GitLab sends the secret itself (X-Gitlab-Token)
+fn is_signed(secret: &str, headers: &HeaderMap, body: &[u8]) -> bool {
+    if let Some(signature) = headers.get("x-hub-signature-256").and_then(|value| value.to_str().ok()) {
+        let Some(signature) = signature.strip_prefix("sha256=").and_then(|hex| hex::decode(hex).ok()) else {
+            return false;
+        };
+        let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(secret.as_bytes()) else {
+            return false;
+        };
+        mac.update(body);
+        return mac.verify_slice(&signature).is_ok();
+    }
+    headers.get("x-gitlab-token").is_some_and(|token| secrets::constant_time_eq(token.as_bytes(), secret.as_bytes()))
+}
+
+// "host/owner/name" for https, ssh and scp-like URLs, so a payload matches whichever form the remote uses
+fn normalize_url(url: &str) -> String {
+    let (authority, path) = match url.split_once("://") {
+        Some((_, rest)) => rest.split_once('/').unwrap_or((rest, "")),
+        None => url.split_once(':').unwrap_or((url, "")),
+    };
+    let host = authority.rsplit('@').next().unwrap_or(authority);
+    let host = host.split(':').next().unwrap_or(host);
+    format!("{}/{}", host, path.trim_matches('/').trim_end_matches(".git")).to_lowercase()
+}